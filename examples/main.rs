@@ -1,12 +1,38 @@
 extern crate clap;
 extern crate kms_rs;
 
+fn print_result<T: serde::Serialize>(result: Result<T, kms_rs::KmsRsError>) {
+    match result {
+        Ok(resp) => println!("{}", serde_json::to_string(&resp).unwrap()),
+        Err(err) => eprintln!("Error: {}", err),
+    }
+}
+
+/// Parses the `--tag KEY=VALUE` args, or returns the first malformed one.
+fn parse_tags(matches: &clap::ArgMatches) -> Result<Vec<kms_rs::Tag>, String> {
+    matches
+        .values_of("tag")
+        .into_iter()
+        .flatten()
+        .map(|tag| match tag.split_once('=') {
+            Some((key, value)) => Ok(kms_rs::Tag {
+                tag_key: key.to_string(),
+                tag_value: value.to_string(),
+            }),
+            None => Err(tag.to_string()),
+        })
+        .collect()
+}
+
 // clap examples: https://github.com/clap-rs/clap/blob/33bebeda52b52c6f643b4ed6fa880671ba0ab80a/examples
-fn main() {
+#[tokio::main]
+async fn main() {
     let matches = clap::App::new("KmsRsExample")
         .version("0.1.0")
         .author("Jeff Rade <jeffrade@gmail.com>")
         .about("Example command line tool showing usage of kms_rs")
+        .arg_from_usage("--region=[REGION] 'AWS Region to use (defaults to AWS_DEFAULT_REGION or us-east-1)'")
+        .arg_from_usage("--endpoint=[URL] 'custom KMS endpoint, e.g. http://localhost:4599'")
         .subcommand(
             clap::SubCommand::with_name("list-keys")
                 .about("Gets a list of all customer master keys (CMKs) in the caller's AWS account and Region.")
@@ -14,11 +40,139 @@ fn main() {
         .subcommand(
             clap::SubCommand::with_name("describe-key")
                 .about("Provides detailed information about a customer master key (CMK).")
-                .arg_from_usage("--key-id=[KEYID] 'metadata for a given key-id'")
+                .arg_from_usage("--key-id=[KEYID] 'metadata for a given key-id, key ARN or alias (e.g. alias/prod)'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("list-aliases")
+                .about("Gets a list of aliases in the caller's AWS account and Region.")
+                .arg_from_usage("--key-id=[KEYID] 'only list the aliases of this key-id'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("create-alias")
+                .about("Creates a friendly name for a customer master key (CMK).")
+                .arg_from_usage("--alias-name=[ALIAS] 'alias to create, e.g. alias/prod'")
+                .arg_from_usage("--key-id=[KEYID] 'key-id the alias refers to'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("update-alias")
+                .about("Associates an existing alias with a different customer master key (CMK).")
+                .arg_from_usage("--alias-name=[ALIAS] 'alias to update'")
+                .arg_from_usage("--key-id=[KEYID] 'key-id the alias will refer to'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("delete-alias")
+                .about("Deletes an alias.")
+                .arg_from_usage("--alias-name=[ALIAS] 'alias to delete'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("create-grant")
+                .about("Adds a grant to a customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id the grant is on'")
+                .arg_from_usage("--grantee-principal=[PRINCIPAL] 'ARN of the principal allowed to use the key'")
+                .arg_from_usage("--operations=[OPERATIONS] 'comma separated operations, e.g. Encrypt,Decrypt'")
+                .arg_from_usage("--retiring-principal=[PRINCIPAL] 'ARN of the principal allowed to retire the grant'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("list-grants")
+                .about("Gets a list of all grants for the specified customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to list the grants of'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("retire-grant")
+                .about("Retires a grant given its grant token.")
+                .arg_from_usage("--grant-token=[TOKEN] 'token of the grant to retire'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("revoke-grant")
+                .about("Revokes the specified grant for the specified customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id the grant is on'")
+                .arg_from_usage("--grant-id=[GRANTID] 'grant-id to revoke'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("get-key-policy")
+                .about("Gets the key policy of a customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to get the policy of'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("put-key-policy")
+                .about("Replaces the key policy of a customer master key (CMK), unless the linter flags it.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to attach the policy to'")
+                .arg_from_usage("--policy-file=[FILE] 'path to the JSON policy document'")
+                .arg_from_usage("--admin=[ARN]... 'principal allowed to be granted kms:*, besides the account root'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("tag-resource")
+                .about("Adds or overwrites tags on a customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to tag'")
+                .arg_from_usage("--tag=[KEY=VALUE]... 'tag to add'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("untag-resource")
+                .about("Removes tags from a customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to untag'")
+                .arg_from_usage("--tag-key=[KEY]... 'key of the tag to remove'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("list-resource-tags")
+                .about("Gets every tag on a customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to list the tags of'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("find-keys")
+                .about("Finds the customer master keys (CMKs) matching every given condition.")
+                .arg_from_usage("--tag=[KEY=VALUE]... 'tag the key must have'")
+                .arg_from_usage("--has-tag=[KEY]... 'tag key the key must have'")
+                .arg_from_usage("--without-tag=[KEY]... 'tag key the key must not have'")
+                .arg_from_usage("--key-state=[STATE]... 'Enabled, Disabled, PendingDeletion, ...'")
+                .arg_from_usage("--key-spec=[SPEC]... 'SYMMETRIC_DEFAULT, RSA_2048, ECC_NIST_P256, ...'")
         )
         .subcommand(
             clap::SubCommand::with_name("create-key")
                 .about("Creates a unique customer managed customer master key (CMK) in your AWS account and Region.")
+                .arg_from_usage("--key-usage=[KEY_USAGE] 'ENCRYPT_DECRYPT (default) or SIGN_VERIFY'")
+                .arg_from_usage("--customer-master-key-spec=[SPEC] 'SYMMETRIC_DEFAULT (default), RSA_2048, ECC_NIST_P256, ...'")
+                .arg_from_usage("--description=[DESCRIPTION] 'description of the key'")
+                .arg_from_usage("--origin=[ORIGIN] 'AWS_KMS (default) or EXTERNAL to import key material'")
+                .arg_from_usage("--multi-region 'creates a multi-Region primary key'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("replicate-key")
+                .about("Replicates a multi-Region primary key into another Region.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id of the primary key'")
+                .arg_from_usage("--replica-region=[REGION] 'Region to replicate the key into'")
+                .arg_from_usage("--description=[DESCRIPTION] 'description of the replica'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("update-primary-region")
+                .about("Makes the replica of a multi-Region key in another Region the primary key.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id of the primary key'")
+                .arg_from_usage("--primary-region=[REGION] 'Region of the replica to promote'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("list-replicas")
+                .about("Describes every replica of a multi-Region key.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id of the multi-Region key'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("describe-custom-key-stores")
+                .about("Gets the custom key stores (AWS CloudHSM clusters) in the caller's AWS account and Region.")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("connect-custom-key-store")
+                .about("Connects a custom key store to its AWS CloudHSM cluster and waits until it is connected.")
+                .arg_from_usage("--custom-key-store-id=[ID] 'ID of the custom key store'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("import-key-material")
+                .about("Imports 256-bit key material into a customer master key (CMK) created with origin EXTERNAL.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to import into'")
+                .arg_from_usage("--material-file=[FILE] 'path to the 32 bytes of raw key material'")
+                .arg_from_usage("--valid-to=[DATE] 'RFC 3339 date when KMS deletes the material (never by default)'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("delete-imported-key-material")
+                .about("Deletes the key material imported into a customer master key (CMK).")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to delete the key material of'")
         )
         .subcommand(
             clap::SubCommand::with_name("schedule-key-deletion")
@@ -41,6 +195,25 @@ fn main() {
                 .about("Sets the key state to disabled of a customer master key (CMK) to enabled.")
                 .arg_from_usage("--key-id=[KEYID] 'key-id to disable'")
         )
+        .subcommand(
+            clap::SubCommand::with_name("enable-key-rotation")
+                .about("Turns on automatic yearly rotation of the key material of a symmetric CMK.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to rotate'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("disable-key-rotation")
+                .about("Turns off automatic rotation of the key material of a symmetric CMK.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to stop rotating'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("get-key-rotation-status")
+                .about("Gets whether automatic rotation of the key material is enabled for a symmetric CMK.")
+                .arg_from_usage("--key-id=[KEYID] 'key-id to get the rotation status of'")
+        )
+        .subcommand(
+            clap::SubCommand::with_name("keys-without-rotation")
+                .about("Lists the symmetric customer managed CMKs whose key material is not rotated automatically.")
+        )
         .subcommand(
             clap::SubCommand::with_name("generate-data-key")
                 .about("Generates a unique symmetric data key for client-side encryption.")
@@ -58,31 +231,332 @@ fn main() {
         )
         .get_matches();
 
+    let mut builder = kms_rs::KmsRs::builder();
+    if let Some(region) = matches.value_of("region") {
+        match region.parse::<kms_rs::Region>() {
+            Ok(region) => builder = builder.region(region),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return;
+            }
+        }
+    }
+    if let Some(endpoint) = matches.value_of("endpoint") {
+        builder = builder.endpoint(endpoint);
+    }
+    let kms = match builder.build() {
+        Ok(kms) => kms,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return;
+        }
+    };
+
     if matches.subcommand_matches("list-keys").is_some() {
-        let keys: serde_json::value::Value = kms_rs::list_keys();
-        println!("{}", keys.to_string());
+        print_result(kms.list_keys().await);
     } else if let Some(matches) = matches.subcommand_matches("generate-data-key") {
         let key_id: &str = matches.value_of("key-id").unwrap();
-        let resp: serde_json::value::Value =
-            kms_rs::generate_data_key(key_id, Some("AES_128".to_string()), None);
-        println!("{}", resp.to_string());
+        print_result(
+            kms.generate_data_key(key_id, Some("AES_128".to_string()), None, None, None)
+                .await,
+        );
     } else if let Some(matches) = matches.subcommand_matches("generate-data-key-without-plaintext")
     {
         let key_id: &str = matches.value_of("key-id").unwrap();
-        let resp: serde_json::value::Value =
-            kms_rs::generate_data_key_without_plaintext(key_id, None, Some(196 as i64));
-        println!("{}", resp.to_string());
+        print_result(
+            kms.generate_data_key_without_plaintext(key_id, None, Some(196_i64), None, None)
+                .await,
+        );
     } else if let Some(matches) = matches.subcommand_matches("describe-key") {
         if matches.is_present("key-id") {
             let key_id: &str = matches.value_of("key-id").unwrap();
-            let resp: serde_json::value::Value = kms_rs::describe_key(key_id);
-            println!("{}", resp.to_string());
+            print_result(kms.describe_key(key_id).await);
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("list-aliases") {
+        print_result(kms.list_aliases(matches.value_of("key-id")).await);
+    } else if let Some(matches) = matches.subcommand_matches("create-alias") {
+        match (matches.value_of("alias-name"), matches.value_of("key-id")) {
+            (Some(alias_name), Some(key_id)) => {
+                if let Err(err) = kms.create_alias(alias_name, key_id).await {
+                    eprintln!("Error: {}", err);
+                }
+            }
+            _ => println!("You must provide the alias-name and key-id args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("update-alias") {
+        match (matches.value_of("alias-name"), matches.value_of("key-id")) {
+            (Some(alias_name), Some(key_id)) => {
+                if let Err(err) = kms.update_alias(alias_name, key_id).await {
+                    eprintln!("Error: {}", err);
+                }
+            }
+            _ => println!("You must provide the alias-name and key-id args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("delete-alias") {
+        if matches.is_present("alias-name") {
+            let alias_name: &str = matches.value_of("alias-name").unwrap();
+            if let Err(err) = kms.delete_alias(alias_name).await {
+                eprintln!("Error: {}", err);
+            }
+        } else {
+            println!("You must provide the alias-name arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("create-grant") {
+        match (
+            matches.value_of("key-id"),
+            matches.value_of("grantee-principal"),
+            matches.value_of("operations"),
+        ) {
+            (Some(key_id), Some(grantee_principal), Some(operations)) => {
+                let mut options = kms_rs::CreateGrantOptions::new(grantee_principal);
+                for operation in operations.split(',') {
+                    options = options.operation(operation.trim());
+                }
+                if let Some(retiring_principal) = matches.value_of("retiring-principal") {
+                    options = options.retiring_principal(retiring_principal);
+                }
+                print_result(kms.create_grant(key_id, options).await);
+            }
+            _ => println!("You must provide the key-id, grantee-principal and operations args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("list-grants") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            print_result(kms.list_grants(key_id).await);
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("retire-grant") {
+        if matches.is_present("grant-token") {
+            let grant_token: &str = matches.value_of("grant-token").unwrap();
+            if let Err(err) = kms.retire_grant(grant_token).await {
+                eprintln!("Error: {}", err);
+            }
+        } else {
+            println!("You must provide the grant-token arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("revoke-grant") {
+        match (matches.value_of("key-id"), matches.value_of("grant-id")) {
+            (Some(key_id), Some(grant_id)) => {
+                if let Err(err) = kms.revoke_grant(key_id, grant_id).await {
+                    eprintln!("Error: {}", err);
+                }
+            }
+            _ => println!("You must provide the key-id and grant-id args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("get-key-policy") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            print_result(kms.get_key_policy(key_id).await);
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("put-key-policy") {
+        match (matches.value_of("key-id"), matches.value_of("policy-file")) {
+            (Some(key_id), Some(policy_file)) => {
+                let policy = match std::fs::read_to_string(policy_file) {
+                    Ok(policy) => policy,
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        return;
+                    }
+                };
+                let policy = match kms_rs::policy::KeyPolicy::from_json(&policy) {
+                    Ok(policy) => policy,
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        return;
+                    }
+                };
+                let mut linter = kms_rs::policy::PolicyLinter::new();
+                for admin in matches.values_of("admin").into_iter().flatten() {
+                    linter = linter.admin(admin);
+                }
+                if let Err(err) = kms.put_key_policy(key_id, &policy, &linter).await {
+                    eprintln!("Error: {}", err);
+                }
+            }
+            _ => println!("You must provide the key-id and policy-file args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("tag-resource") {
+        match (matches.value_of("key-id"), parse_tags(matches)) {
+            (Some(key_id), Ok(tags)) => {
+                if let Err(err) = kms.tag_resource(key_id, tags).await {
+                    eprintln!("Error: {}", err);
+                }
+            }
+            (Some(_), Err(tag)) => println!("Tags must be formatted KEY=VALUE, got {}", tag),
+            (None, _) => println!("You must provide the key-id arg!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("untag-resource") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            let tag_keys = matches
+                .values_of("tag-key")
+                .into_iter()
+                .flatten()
+                .map(str::to_string)
+                .collect();
+            if let Err(err) = kms.untag_resource(key_id, tag_keys).await {
+                eprintln!("Error: {}", err);
+            }
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("list-resource-tags") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            print_result(kms.list_resource_tags(key_id).await);
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("find-keys") {
+        let mut filter = kms_rs::KeyFilter::new();
+        match parse_tags(matches) {
+            Ok(tags) => {
+                for tag in tags {
+                    filter = filter.tag(tag.tag_key, tag.tag_value);
+                }
+            }
+            Err(tag) => {
+                println!("Tags must be formatted KEY=VALUE, got {}", tag);
+                return;
+            }
+        }
+        for key in matches.values_of("has-tag").into_iter().flatten() {
+            filter = filter.has_tag(key);
+        }
+        for key in matches.values_of("without-tag").into_iter().flatten() {
+            filter = filter.without_tag(key);
+        }
+        for key_state in matches.values_of("key-state").into_iter().flatten() {
+            filter = filter.key_state(key_state);
+        }
+        for key_spec in matches.values_of("key-spec").into_iter().flatten() {
+            filter = filter.key_spec(key_spec);
+        }
+        print_result(kms.find_keys(&filter).await);
+    } else if let Some(matches) = matches.subcommand_matches("create-key") {
+        let mut options = kms_rs::CreateKeyOptions::new();
+        if let Some(key_usage) = matches.value_of("key-usage") {
+            options = options.key_usage(key_usage);
+        }
+        if let Some(spec) = matches.value_of("customer-master-key-spec") {
+            options = options.customer_master_key_spec(spec);
+        }
+        if let Some(description) = matches.value_of("description") {
+            options = options.description(description);
+        }
+        if let Some(origin) = matches.value_of("origin") {
+            options = options.origin(origin);
+        }
+        if matches.is_present("multi-region") {
+            options = options.multi_region(true);
+        }
+        print_result(kms.create_key(options).await);
+    } else if let Some(matches) = matches.subcommand_matches("replicate-key") {
+        match (
+            matches.value_of("key-id"),
+            matches.value_of("replica-region"),
+        ) {
+            (Some(key_id), Some(replica_region)) => match replica_region.parse() {
+                Ok(replica_region) => {
+                    let description = matches.value_of("description").map(str::to_string);
+                    print_result(
+                        kms.replicate_key(key_id, &replica_region, description, Vec::new())
+                            .await,
+                    );
+                }
+                Err(err) => eprintln!("Error: {}", err),
+            },
+            _ => println!("You must provide the key-id and replica-region args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("update-primary-region") {
+        match (
+            matches.value_of("key-id"),
+            matches.value_of("primary-region"),
+        ) {
+            (Some(key_id), Some(primary_region)) => match primary_region.parse() {
+                Ok(primary_region) => {
+                    if let Err(err) = kms.update_primary_region(key_id, &primary_region).await {
+                        eprintln!("Error: {}", err);
+                    }
+                }
+                Err(err) => eprintln!("Error: {}", err),
+            },
+            _ => println!("You must provide the key-id and primary-region args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("list-replicas") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            print_result(kms.list_replicas(key_id).await);
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if matches
+        .subcommand_matches("describe-custom-key-stores")
+        .is_some()
+    {
+        print_result(kms.describe_custom_key_stores().await);
+    } else if let Some(matches) = matches.subcommand_matches("connect-custom-key-store") {
+        if matches.is_present("custom-key-store-id") {
+            let custom_key_store_id: &str = matches.value_of("custom-key-store-id").unwrap();
+            if let Err(err) = kms.connect_custom_key_store(custom_key_store_id).await {
+                eprintln!("Error: {}", err);
+                return;
+            }
+            print_result(
+                kms.wait_for_custom_key_store(
+                    custom_key_store_id,
+                    std::time::Duration::from_secs(30),
+                    std::time::Duration::from_secs(60 * 60),
+                )
+                .await,
+            );
+        } else {
+            println!("You must provide the custom-key-store-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("import-key-material") {
+        match (
+            matches.value_of("key-id"),
+            matches.value_of("material-file"),
+        ) {
+            (Some(key_id), Some(material_file)) => {
+                let material = match std::fs::read(material_file) {
+                    Ok(material) => zeroize::Zeroizing::new(material),
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        return;
+                    }
+                };
+                let expiration = match matches.value_of("valid-to").map(|valid_to| {
+                    chrono::DateTime::parse_from_rfc3339(valid_to)
+                        .map(|valid_to| valid_to.with_timezone(&chrono::Utc))
+                }) {
+                    Some(Ok(valid_to)) => Some(valid_to),
+                    Some(Err(err)) => {
+                        eprintln!("Error: {}", err);
+                        return;
+                    }
+                    None => None,
+                };
+                if let Err(err) = kms.import_key_material(key_id, &material, expiration).await {
+                    eprintln!("Error: {}", err);
+                }
+            }
+            _ => println!("You must provide the key-id and material-file args!"),
+        }
+    } else if let Some(matches) = matches.subcommand_matches("delete-imported-key-material") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            if let Err(err) = kms.delete_imported_key_material(key_id).await {
+                eprintln!("Error: {}", err);
+            }
         } else {
             println!("You must provide the key-id arg!");
         }
-    } else if matches.subcommand_matches("create-key").is_some() {
-        let resp: serde_json::value::Value = kms_rs::create_key();
-        println!("{}", resp.to_string());
     } else if let Some(matches) = matches.subcommand_matches("schedule-key-deletion") {
         if matches.is_present("key-id") {
             let key_id: String = matches.value_of("key-id").unwrap().to_string();
@@ -93,16 +567,12 @@ fn main() {
                     .parse::<i64>()
                 {
                     Ok(days) => {
-                        let resp: serde_json::value::Value =
-                            kms_rs::schedule_key_deletion(key_id, days);
-                        println!("{}", resp.to_string())
+                        print_result(kms.schedule_key_deletion(key_id, days).await);
                     }
                     Err(value) => println!("Error: {:?}", value),
                 }
             } else {
-                let resp: serde_json::value::Value =
-                    kms_rs::schedule_key_deletion(key_id, 30 as i64);
-                println!("{}", resp.to_string())
+                print_result(kms.schedule_key_deletion(key_id, 30_i64).await);
             }
         } else {
             println!("You must provide the key-id arg!");
@@ -110,17 +580,15 @@ fn main() {
     } else if let Some(matches) = matches.subcommand_matches("cancel-key-deletion") {
         if matches.is_present("key-id") {
             let key_id: String = matches.value_of("key-id").unwrap().to_string();
-            let resp: serde_json::value::Value = kms_rs::cancel_key_deletion(key_id);
-            println!("{}", resp.to_string());
+            print_result(kms.cancel_key_deletion(key_id).await);
         } else {
             println!("You must provide the key-id arg!");
         }
     } else if let Some(matches) = matches.subcommand_matches("enable-key") {
         if matches.is_present("key-id") {
             let key_id: &str = matches.value_of("key-id").unwrap();
-            match kms_rs::enable_key(key_id) {
-                Some(resp) => println!("{}", resp.to_string()),
-                None => (),
+            if let Err(err) = kms.enable_key(key_id).await {
+                eprintln!("Error: {}", err);
             }
         } else {
             println!("You must provide the key-id arg!");
@@ -128,17 +596,45 @@ fn main() {
     } else if let Some(matches) = matches.subcommand_matches("disable-key") {
         if matches.is_present("key-id") {
             let key_id: &str = matches.value_of("key-id").unwrap();
-            match kms_rs::disable_key(key_id) {
-                Some(resp) => println!("{}", resp.to_string()),
-                None => (),
+            if let Err(err) = kms.disable_key(key_id).await {
+                eprintln!("Error: {}", err);
+            }
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("enable-key-rotation") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            if let Err(err) = kms.enable_key_rotation(key_id).await {
+                eprintln!("Error: {}", err);
+            }
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if let Some(matches) = matches.subcommand_matches("disable-key-rotation") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            if let Err(err) = kms.disable_key_rotation(key_id).await {
+                eprintln!("Error: {}", err);
             }
         } else {
             println!("You must provide the key-id arg!");
         }
+    } else if let Some(matches) = matches.subcommand_matches("get-key-rotation-status") {
+        if matches.is_present("key-id") {
+            let key_id: &str = matches.value_of("key-id").unwrap();
+            print_result(kms.get_key_rotation_status(key_id).await);
+        } else {
+            println!("You must provide the key-id arg!");
+        }
+    } else if matches
+        .subcommand_matches("keys-without-rotation")
+        .is_some()
+    {
+        print_result(kms.keys_without_rotation().await);
     } else if let Some(matches) = matches.subcommand_matches("get-public-key") {
         let key_id: String = matches.value_of("key-id").unwrap().to_string();
-        let resp: serde_json::value::Value = kms_rs::get_public_key(key_id, None);
-        println!("{}", resp.to_string());
+        print_result(kms.get_public_key(key_id, None).await);
     } else {
         println!("You must pass a valid command!");
     }
@@ -0,0 +1,3064 @@
+//! A [`KmsBackend`] that keeps keys in memory and performs every operation locally.
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use rand_core::{OsRng, RngCore};
+use rusoto_core::Region;
+use rusoto_kms::{
+    AliasListEntry, CancelKeyDeletionRequest, CancelKeyDeletionResponse,
+    ConnectCustomKeyStoreRequest, CreateAliasRequest, CreateCustomKeyStoreRequest,
+    CreateCustomKeyStoreResponse, CreateGrantRequest, CreateGrantResponse, CreateKeyRequest,
+    CreateKeyResponse, CustomKeyStoresListEntry, DecryptRequest, DecryptResponse,
+    DeleteAliasRequest, DeleteCustomKeyStoreRequest, DeleteImportedKeyMaterialRequest,
+    DescribeCustomKeyStoresRequest, DescribeCustomKeyStoresResponse, DescribeKeyRequest,
+    DescribeKeyResponse, DisableKeyRequest, DisableKeyRotationRequest,
+    DisconnectCustomKeyStoreRequest, EnableKeyRequest, EnableKeyRotationRequest, EncryptRequest,
+    EncryptResponse, GenerateDataKeyPairRequest, GenerateDataKeyPairResponse,
+    GenerateDataKeyPairWithoutPlaintextRequest, GenerateDataKeyPairWithoutPlaintextResponse,
+    GenerateDataKeyRequest, GenerateDataKeyResponse, GenerateDataKeyWithoutPlaintextRequest,
+    GenerateDataKeyWithoutPlaintextResponse, GenerateRandomRequest, GenerateRandomResponse,
+    GetKeyPolicyRequest, GetKeyPolicyResponse, GetKeyRotationStatusRequest,
+    GetKeyRotationStatusResponse, GetParametersForImportRequest, GetParametersForImportResponse,
+    GetPublicKeyRequest, GetPublicKeyResponse, GrantListEntry, ImportKeyMaterialRequest,
+    KeyListEntry, KeyMetadata, ListAliasesRequest, ListAliasesResponse, ListGrantsRequest,
+    ListGrantsResponse, ListKeyPoliciesRequest, ListKeyPoliciesResponse, ListKeysRequest,
+    ListKeysResponse, ListResourceTagsRequest, ListResourceTagsResponse,
+    ListRetirableGrantsRequest, MultiRegionConfiguration, MultiRegionKey, PutKeyPolicyRequest,
+    ReEncryptRequest, ReEncryptResponse, ReplicateKeyRequest, ReplicateKeyResponse,
+    RetireGrantRequest, RevokeGrantRequest, ScheduleKeyDeletionRequest,
+    ScheduleKeyDeletionResponse, SignRequest, SignResponse, Tag, TagResourceRequest,
+    UntagResourceRequest, UpdateAliasRequest, UpdateCustomKeyStoreRequest,
+    UpdatePrimaryRegionRequest, VerifyRequest, VerifyResponse,
+};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use zeroize::Zeroizing;
+
+use super::KmsBackend;
+use crate::crypto::{self, PrivateKey, PublicKey};
+use crate::error::KmsRsError;
+use crate::policy::KeyPolicy;
+use crate::sync::lock;
+
+/// Identifies ciphertext blobs produced by symmetric CMKs of an [`InMemoryKms`].
+const BLOB_VERSION: u8 = 1;
+
+/// The name of the only key policy a CMK has.
+const DEFAULT_POLICY_NAME: &str = "default";
+
+/// The maximum number of tags on a CMK.
+const MAX_TAGS: usize = 50;
+
+/// Operations a grant can allow.
+const GRANT_OPERATIONS: [&str; 14] = [
+    "CreateGrant",
+    "Decrypt",
+    "DescribeKey",
+    "Encrypt",
+    "GenerateDataKey",
+    "GenerateDataKeyPair",
+    "GenerateDataKeyPairWithoutPlaintext",
+    "GenerateDataKeyWithoutPlaintext",
+    "GetPublicKey",
+    "ReEncryptFrom",
+    "ReEncryptTo",
+    "RetireGrant",
+    "Sign",
+    "Verify",
+];
+
+/// How long the parameters returned by GetParametersForImport can be used, in seconds.
+const IMPORT_PARAMETERS_VALIDITY: f64 = 24.0 * 60.0 * 60.0;
+
+/// Encrypt, GenerateRandom and Sign accept at most 4096 bytes (GenerateRandom 1024).
+const MAX_PLAINTEXT_BYTES: usize = 4096;
+
+/// A fake AWS KMS for tests: keys live in memory, and encryption, signing and data key generation are
+/// performed locally with real cryptography.
+///
+/// Key states follow KMS: cryptographic operations fail with [`KmsRsError::Disabled`] on a disabled CMK
+/// and with [`KmsRsError::KmsInvalidState`] on a CMK pending deletion or pending import. Ciphertexts are
+/// only meaningful to the `InMemoryKms` that produced them. Clones share the same keys. Imported key
+/// material is kept past its `ValidTo` date.
+///
+/// Custom key stores are simulated: a connected store finishes `CONNECTING` the first time it is described,
+/// and the CMKs created in it (with `Origin=AWS_CLOUDHSM`) are `Unavailable` while it is disconnected.
+///
+/// Each Region has its own keys, aliases and grants; [`KmsBackend::for_region`] returns a view of another
+/// Region of the same KMS, where multi-Region keys can be replicated.
+///
+/// ```
+/// # async fn run() -> Result<(), kms_rs::KmsRsError> {
+/// use bytes::Bytes;
+/// use kms_rs::{CreateKeyOptions, InMemoryKms, KmsRs};
+///
+/// let kms = KmsRs::from_backend(InMemoryKms::new());
+/// let key = kms.create_key(CreateKeyOptions::new()).await?;
+/// let encrypted = kms
+///     .encrypt(key.key_id, Bytes::from_static(b"secret"), None, None, None)
+///     .await?;
+/// let decrypted = kms
+///     .decrypt(None, encrypted.ciphertext_blob, None, None, None)
+///     .await?;
+/// assert_eq!(decrypted.plaintext, Bytes::from_static(b"secret"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InMemoryKms {
+    region: String,
+    account_id: String,
+    store: Arc<Mutex<Store>>,
+    /// The store of every Region of this KMS, by Region name.
+    regions: Arc<Mutex<HashMap<String, Arc<Mutex<Store>>>>>,
+}
+
+#[derive(Default)]
+struct Store {
+    keys: Vec<StoredKey>,
+    aliases: Vec<StoredAlias>,
+    grants: Vec<StoredGrant>,
+    imports: Vec<PendingImport>,
+    custom_key_stores: Vec<StoredCustomKeyStore>,
+}
+
+struct StoredAlias {
+    alias_name: String,
+    target_key_id: String,
+    creation_date: f64,
+    last_updated_date: f64,
+}
+
+struct StoredKey {
+    metadata: KeyMetadata,
+    material: KeyMaterial,
+    /// The key policy document, as given (KMS returns it unchanged).
+    policy: String,
+    tags: Vec<Tag>,
+    /// Kept while the CMK is disabled or pending deletion, like KMS does.
+    rotation_enabled: bool,
+    /// SHA-256 of the key material first imported into an `EXTERNAL` CMK. KMS only accepts the same
+    /// material again, so ciphertexts stay decryptable.
+    imported_digest: Option<Vec<u8>>,
+}
+
+struct StoredGrant {
+    grant_id: String,
+    grant_token: String,
+    /// Key ID of the CMK the grant is on.
+    key_id: String,
+    request: CreateGrantRequest,
+    creation_date: f64,
+}
+
+/// A custom key store. Its CloudHSM cluster is only simulated: any cluster ID starting with `cluster-` exists.
+struct StoredCustomKeyStore {
+    entry: CustomKeyStoresListEntry,
+    key_store_password: String,
+}
+
+/// The wrapping key and import token returned by GetParametersForImport, usable until `valid_to`.
+struct PendingImport {
+    import_token: Vec<u8>,
+    /// Key ID of the CMK the parameters are for.
+    key_id: String,
+    wrapping_algorithm: String,
+    wrapping_key: PrivateKey,
+    valid_to: f64,
+}
+
+#[derive(Clone)]
+enum KeyMaterial {
+    /// Key material is pending import.
+    None,
+    Symmetric(Zeroizing<Vec<u8>>),
+    Asymmetric(PrivateKey),
+}
+
+impl Default for InMemoryKms {
+    fn default() -> InMemoryKms {
+        InMemoryKms::new()
+    }
+}
+
+impl InMemoryKms {
+    /// Creates an empty KMS in us-east-1 for the account `111122223333`.
+    pub fn new() -> InMemoryKms {
+        InMemoryKms::with_account(Region::UsEast1, "111122223333")
+    }
+
+    /// Creates an empty KMS whose key ARNs use the given Region and account.
+    pub fn with_account<S: Into<String>>(region: Region, account_id: S) -> InMemoryKms {
+        let store = Arc::new(Mutex::new(Store::default()));
+        let mut regions = HashMap::new();
+        regions.insert(region.name().to_string(), store.clone());
+        InMemoryKms {
+            region: region.name().to_string(),
+            account_id: account_id.into(),
+            store,
+            regions: Arc::new(Mutex::new(regions)),
+        }
+    }
+
+    /// This KMS in another Region, whose store is created on first use.
+    fn in_region(&self, region: &str) -> InMemoryKms {
+        let mut regions = lock(&self.regions);
+        let store = regions.entry(region.to_string()).or_default().clone();
+        InMemoryKms {
+            region: region.to_string(),
+            account_id: self.account_id.clone(),
+            store,
+            regions: self.regions.clone(),
+        }
+    }
+
+    /// Sets the multi-Region configuration of every key sharing `key_id`, in each of its Regions. Stores
+    /// are locked one at a time, so callers must not hold their own.
+    fn set_multi_region_configuration(
+        &self,
+        key_id: &str,
+        primary: &MultiRegionKey,
+        replicas: &[MultiRegionKey],
+    ) {
+        let members = std::iter::once((primary, "PRIMARY"))
+            .chain(replicas.iter().map(|replica| (replica, "REPLICA")));
+        for (member, key_type) in members {
+            let kms = self.in_region(member.region.as_deref().unwrap_or_default());
+            let mut store = kms.store();
+            if let Ok(key) = kms.find_mut(&mut store, key_id) {
+                key.metadata.multi_region_configuration = Some(MultiRegionConfiguration {
+                    multi_region_key_type: Some(key_type.to_string()),
+                    primary_key: Some(primary.clone()),
+                    replica_keys: Some(replicas.to_vec()),
+                });
+            }
+        }
+    }
+
+    fn store(&self) -> MutexGuard<'_, Store> {
+        lock(&self.store)
+    }
+
+    fn find_custom_key_store<'a>(
+        &self,
+        store: &'a mut Store,
+        custom_key_store_id: &str,
+    ) -> Result<&'a mut StoredCustomKeyStore, KmsRsError> {
+        store
+            .custom_key_stores
+            .iter_mut()
+            .find(|custom_key_store| {
+                custom_key_store.entry.custom_key_store_id.as_deref() == Some(custom_key_store_id)
+            })
+            .ok_or_else(|| {
+                KmsRsError::CustomKeyStoreNotFound(format!(
+                    "Custom key store {} does not exist",
+                    custom_key_store_id
+                ))
+            })
+    }
+
+    /// Sets the connection state of a custom key store, making its CMKs unavailable unless it is
+    /// `CONNECTED`.
+    fn set_connection_state(&self, store: &mut Store, custom_key_store_id: &str, state: &str) {
+        if let Ok(custom_key_store) = self.find_custom_key_store(store, custom_key_store_id) {
+            custom_key_store.entry.connection_state = Some(state.to_string());
+        }
+        for key in store
+            .keys
+            .iter_mut()
+            .filter(|key| key.metadata.custom_key_store_id.as_deref() == Some(custom_key_store_id))
+        {
+            let key_state = match key.metadata.key_state.as_deref() {
+                Some("PendingDeletion") => continue,
+                _ if state != "CONNECTED" => "Unavailable",
+                _ if key.metadata.enabled == Some(true) => "Enabled",
+                _ => "Disabled",
+            };
+            key.metadata.key_state = Some(key_state.to_string());
+        }
+    }
+
+    fn key_arn(&self, key_id: &str) -> String {
+        format!(
+            "arn:aws:kms:{}:{}:key/{}",
+            self.region, self.account_id, key_id
+        )
+    }
+
+    fn alias_arn(&self, alias_name: &str) -> String {
+        format!(
+            "arn:aws:kms:{}:{}:{}",
+            self.region, self.account_id, alias_name
+        )
+    }
+
+    /// Finds a CMK by key ID, key ARN, alias name or alias ARN.
+    fn find<'a>(&self, store: &'a Store, key_id: &str) -> Result<&'a StoredKey, KmsRsError> {
+        let index = self.key_index(store, key_id)?;
+        Ok(&store.keys[index])
+    }
+
+    fn find_mut<'a>(
+        &self,
+        store: &'a mut Store,
+        key_id: &str,
+    ) -> Result<&'a mut StoredKey, KmsRsError> {
+        let index = self.key_index(store, key_id)?;
+        Ok(&mut store.keys[index])
+    }
+
+    fn key_index(&self, store: &Store, key_id: &str) -> Result<usize, KmsRsError> {
+        let key_id = match alias_name(key_id) {
+            Some(alias_name) => {
+                &self
+                    .find_alias(store, alias_name)
+                    .ok_or_else(|| self.alias_not_found(alias_name))?
+                    .target_key_id
+            }
+            None => key_id,
+        };
+        store
+            .keys
+            .iter()
+            .position(|key| {
+                key.metadata.key_id == key_id || key.metadata.arn.as_deref() == Some(key_id)
+            })
+            .ok_or_else(|| self.not_found(key_id))
+    }
+
+    fn find_alias<'a>(&self, store: &'a Store, alias_name: &str) -> Option<&'a StoredAlias> {
+        store
+            .aliases
+            .iter()
+            .find(|alias| alias.alias_name == alias_name)
+    }
+
+    fn alias_not_found(&self, alias_name: &str) -> KmsRsError {
+        KmsRsError::NotFound(format!(
+            "Alias {} is not found.",
+            self.alias_arn(alias_name)
+        ))
+    }
+
+    fn grant_list_entry(&self, grant: &StoredGrant) -> GrantListEntry {
+        GrantListEntry {
+            constraints: grant.request.constraints.clone(),
+            creation_date: Some(grant.creation_date),
+            grant_id: Some(grant.grant_id.clone()),
+            grantee_principal: Some(grant.request.grantee_principal.clone()),
+            issuing_account: Some(format!("arn:aws:iam::{}:root", self.account_id)),
+            key_id: Some(self.key_arn(&grant.key_id)),
+            name: grant.request.name.clone(),
+            operations: Some(grant.request.operations.clone()),
+            retiring_principal: grant.request.retiring_principal.clone(),
+        }
+    }
+
+    fn grant_not_found(grant_id: &str) -> KmsRsError {
+        KmsRsError::NotFound(format!("Grant ID {} not found", grant_id))
+    }
+
+    fn not_found(&self, key_id: &str) -> KmsRsError {
+        let arn = if key_id.starts_with("arn:") {
+            key_id.to_string()
+        } else {
+            self.key_arn(key_id)
+        };
+        KmsRsError::NotFound(format!("Key '{}' does not exist", arn))
+    }
+
+    /// Encrypts under a symmetric CMK. The blob names the CMK, so Decrypt does not need a key ID.
+    fn encrypt_blob(
+        key: &StoredKey,
+        plaintext: &[u8],
+        encryption_context: &Option<HashMap<String, String>>,
+    ) -> Result<Bytes, KmsRsError> {
+        let material = key.symmetric_material("Encrypt")?;
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let aad = blob_aad(&key.metadata.key_id, encryption_context);
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(material))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| KmsRsError::KmsInternal("Encryption failed".to_string()))?;
+
+        let key_id = key.metadata.key_id.as_bytes();
+        let mut blob = Vec::with_capacity(2 + key_id.len() + nonce.len() + ciphertext.len());
+        blob.push(BLOB_VERSION);
+        blob.push(key_id.len() as u8);
+        blob.extend_from_slice(key_id);
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&ciphertext);
+        Ok(Bytes::from(blob))
+    }
+
+    /// Decrypts a blob produced by [`InMemoryKms::encrypt_blob`], returning the CMK ARN and the plaintext.
+    fn decrypt_blob(
+        &self,
+        store: &Store,
+        key_id: Option<&str>,
+        blob: &[u8],
+        encryption_context: &Option<HashMap<String, String>>,
+    ) -> Result<(String, Zeroizing<Vec<u8>>), KmsRsError> {
+        let invalid = || KmsRsError::InvalidCiphertext(String::new());
+        let (blob_key_id, rest) = match blob {
+            [BLOB_VERSION, len, rest @ ..] if rest.len() > *len as usize + 12 => {
+                rest.split_at(*len as usize)
+            }
+            _ => return Err(invalid()),
+        };
+        let blob_key_id = std::str::from_utf8(blob_key_id).map_err(|_| invalid())?;
+        let key = self.find(store, blob_key_id).map_err(|_| invalid())?;
+        if let Some(key_id) = key_id {
+            let requested = self.find(store, key_id)?;
+            if requested.metadata.key_id != key.metadata.key_id {
+                return Err(KmsRsError::IncorrectKey(
+                    "The key ID in the request does not identify a CMK that can perform this operation.".to_string(),
+                ));
+            }
+        }
+        key.check_usable()?;
+        let material = key.symmetric_material("Decrypt")?;
+
+        let (nonce, ciphertext) = rest.split_at(12);
+        let aad = blob_aad(&key.metadata.key_id, encryption_context);
+        let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(material))
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| invalid())?;
+        Ok((key.arn(), Zeroizing::new(plaintext)))
+    }
+
+    /// Encrypt, returning the CMK ARN and the ciphertext blob.
+    fn encrypt_with(
+        &self,
+        store: &Store,
+        key_id: &str,
+        plaintext: &[u8],
+        encryption_context: &Option<HashMap<String, String>>,
+        encryption_algorithm: &str,
+    ) -> Result<(String, Bytes), KmsRsError> {
+        check_length("Plaintext", plaintext.len(), MAX_PLAINTEXT_BYTES)?;
+        let key = self.find(store, key_id)?;
+        key.check_usable()?;
+        let ciphertext_blob = if encryption_algorithm == "SYMMETRIC_DEFAULT" {
+            InMemoryKms::encrypt_blob(key, plaintext, encryption_context)?
+        } else {
+            if key.metadata.key_usage.as_deref() != Some("ENCRYPT_DECRYPT") {
+                return Err(key.invalid_key_usage("Encrypt"));
+            }
+            if encryption_context.is_some() {
+                return Err(KmsRsError::Validation(
+                    "Asymmetric CMKs do not support an encryption context".to_string(),
+                ));
+            }
+            let private_key = key.private_key("Encrypt")?;
+            Bytes::from(
+                private_key
+                    .public_key()
+                    .encrypt(encryption_algorithm, plaintext)?,
+            )
+        };
+        Ok((key.arn(), ciphertext_blob))
+    }
+
+    /// Decrypt, returning the CMK ARN and the plaintext. Asymmetric ciphertexts need `key_id`.
+    fn decrypt_with(
+        &self,
+        store: &Store,
+        key_id: Option<&str>,
+        ciphertext_blob: &[u8],
+        encryption_context: &Option<HashMap<String, String>>,
+        encryption_algorithm: &str,
+    ) -> Result<(String, Zeroizing<Vec<u8>>), KmsRsError> {
+        if encryption_algorithm == "SYMMETRIC_DEFAULT" {
+            return self.decrypt_blob(store, key_id, ciphertext_blob, encryption_context);
+        }
+
+        let key_id = key_id.ok_or_else(|| {
+            KmsRsError::Validation(
+                "KeyId is required to decrypt with an asymmetric CMK".to_string(),
+            )
+        })?;
+        let key = self.find(store, key_id)?;
+        key.check_usable()?;
+        if key.metadata.key_usage.as_deref() != Some("ENCRYPT_DECRYPT") {
+            return Err(key.invalid_key_usage("Decrypt"));
+        }
+        let plaintext = key
+            .private_key("Decrypt")?
+            .decrypt(encryption_algorithm, ciphertext_blob)?;
+        Ok((key.arn(), plaintext))
+    }
+
+    fn data_key(
+        &self,
+        key_id: &str,
+        key_spec: Option<&str>,
+        number_of_bytes: Option<i64>,
+        encryption_context: &Option<HashMap<String, String>>,
+    ) -> Result<(String, Bytes, Bytes), KmsRsError> {
+        let len = match (key_spec, number_of_bytes) {
+            (Some("AES_256"), None) => 32,
+            (Some("AES_128"), None) => 16,
+            (None, Some(bytes)) if (1..=1024).contains(&bytes) => bytes as usize,
+            (Some(key_spec), None) => {
+                return Err(KmsRsError::Validation(format!(
+                    "Unsupported key spec {}",
+                    key_spec
+                )))
+            }
+            _ => {
+                return Err(KmsRsError::Validation(
+                    "Specify either KeySpec or NumberOfBytes (1 to 1024)".to_string(),
+                ))
+            }
+        };
+        let store = self.store();
+        let key = self.find(&store, key_id)?;
+        key.check_usable()?;
+
+        let mut plaintext = vec![0u8; len];
+        OsRng.fill_bytes(&mut plaintext);
+        let ciphertext_blob = InMemoryKms::encrypt_blob(key, &plaintext, encryption_context)?;
+        Ok((key.arn(), ciphertext_blob, Bytes::from(plaintext)))
+    }
+
+    fn data_key_pair(
+        &self,
+        key_id: &str,
+        key_pair_spec: &str,
+        encryption_context: &Option<HashMap<String, String>>,
+    ) -> Result<DataKeyPair, KmsRsError> {
+        {
+            let store = self.store();
+            let key = self.find(&store, key_id)?;
+            key.check_usable()?;
+            key.symmetric_material("GenerateDataKeyPair")?;
+        }
+        // RSA key generation is slow, so it happens without holding the lock.
+        let private_key = PrivateKey::generate(key_pair_spec)?;
+        let private_key_plaintext = private_key.to_pkcs8_der()?;
+        let public_key = Bytes::from(private_key.public_key().to_der()?);
+
+        let store = self.store();
+        let key = self.find(&store, key_id)?;
+        let private_key_ciphertext_blob =
+            InMemoryKms::encrypt_blob(key, &private_key_plaintext, encryption_context)?;
+        Ok(DataKeyPair {
+            key_id: key.arn(),
+            private_key_ciphertext_blob,
+            private_key_plaintext,
+            public_key,
+        })
+    }
+
+    /// Looks up a signing CMK and returns the digest to sign or verify.
+    fn signing_digest<'a>(
+        &self,
+        store: &'a Store,
+        key_id: &str,
+        message: &[u8],
+        message_type: Option<&str>,
+        signing_algorithm: &str,
+    ) -> Result<(&'a StoredKey, Vec<u8>), KmsRsError> {
+        let key = self.find(store, key_id)?;
+        key.check_usable()?;
+        if key.metadata.key_usage.as_deref() != Some("SIGN_VERIFY") {
+            return Err(key.invalid_key_usage("Sign"));
+        }
+        if !key
+            .metadata
+            .signing_algorithms
+            .iter()
+            .flatten()
+            .any(|algorithm| algorithm == signing_algorithm)
+        {
+            return Err(KmsRsError::InvalidKeyUsage(format!(
+                "Algorithm {} is incompatible with key spec {}.",
+                signing_algorithm,
+                key.metadata
+                    .customer_master_key_spec
+                    .as_deref()
+                    .unwrap_or_default()
+            )));
+        }
+        check_length("Message", message.len(), MAX_PLAINTEXT_BYTES)?;
+        let digest = match message_type.unwrap_or("RAW") {
+            "RAW" => crypto::digest(signing_algorithm, message)?,
+            "DIGEST" => message.to_vec(),
+            message_type => {
+                return Err(KmsRsError::Validation(format!(
+                    "Unsupported message type {}",
+                    message_type
+                )))
+            }
+        };
+        Ok((key, digest))
+    }
+}
+
+/// A generated data key pair, with the private key encrypted under a CMK.
+struct DataKeyPair {
+    key_id: String,
+    private_key_ciphertext_blob: Bytes,
+    private_key_plaintext: Zeroizing<Vec<u8>>,
+    public_key: Bytes,
+}
+
+impl StoredKey {
+    fn arn(&self) -> String {
+        self.metadata.arn.clone().unwrap_or_default()
+    }
+
+    /// Fails unless the CMK can be used in cryptographic operations.
+    fn check_usable(&self) -> Result<(), KmsRsError> {
+        match self.metadata.key_state.as_deref() {
+            Some("Enabled") => Ok(()),
+            Some("Disabled") => Err(KmsRsError::Disabled(format!("{} is disabled.", self.arn()))),
+            Some("PendingDeletion") => Err(KmsRsError::KmsInvalidState(format!(
+                "{} is pending deletion.",
+                self.arn()
+            ))),
+            Some("PendingImport") => Err(KmsRsError::KmsInvalidState(format!(
+                "{} is pending import.",
+                self.arn()
+            ))),
+            state => Err(KmsRsError::KmsInvalidState(format!(
+                "{} is in state {}.",
+                self.arn(),
+                state.unwrap_or_default()
+            ))),
+        }
+    }
+
+    /// Fails with the error KMS returns for a key state change that is not allowed.
+    fn check_not_pending(&self) -> Result<(), KmsRsError> {
+        match self.metadata.key_state.as_deref() {
+            Some("PendingDeletion") | Some("PendingImport") => self.check_usable(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Aliases cannot be pointed at a CMK that is pending deletion.
+    fn check_not_pending_deletion(&self) -> Result<(), KmsRsError> {
+        match self.metadata.key_state.as_deref() {
+            Some("PendingDeletion") => self.check_usable(),
+            _ => Ok(()),
+        }
+    }
+
+    fn symmetric_material(&self, operation: &str) -> Result<&[u8], KmsRsError> {
+        match &self.material {
+            KeyMaterial::Symmetric(material) => Ok(material),
+            _ => Err(self.invalid_key_usage(operation)),
+        }
+    }
+
+    fn private_key(&self, operation: &str) -> Result<&PrivateKey, KmsRsError> {
+        match &self.material {
+            KeyMaterial::Asymmetric(private_key) => Ok(private_key),
+            _ => Err(self.invalid_key_usage(operation)),
+        }
+    }
+
+    fn invalid_key_usage(&self, operation: &str) -> KmsRsError {
+        KmsRsError::InvalidKeyUsage(format!(
+            "{} key usage is {} which is not valid for {}.",
+            self.arn(),
+            self.metadata.key_usage.as_deref().unwrap_or_default(),
+            operation
+        ))
+    }
+
+    /// Only CMKs created without key material (`Origin=EXTERNAL`) accept imported key material.
+    fn check_external(&self) -> Result<(), KmsRsError> {
+        match self.metadata.origin.as_deref() {
+            Some("EXTERNAL") => Ok(()),
+            origin => Err(KmsRsError::UnsupportedOperation(format!(
+                "{} origin is {} which is not valid for this operation.",
+                self.arn(),
+                origin.unwrap_or_default()
+            ))),
+        }
+    }
+
+    /// Automatic rotation is only available for symmetric CMKs whose key material KMS generated.
+    fn check_rotation_supported(&self) -> Result<(), KmsRsError> {
+        let symmetric =
+            self.metadata.customer_master_key_spec.as_deref() == Some("SYMMETRIC_DEFAULT");
+        let generated = self.metadata.origin.as_deref() == Some("AWS_KMS");
+        if symmetric && generated {
+            Ok(())
+        } else {
+            Err(KmsRsError::UnsupportedOperation(format!(
+                "{} does not support automatic key rotation.",
+                self.arn()
+            )))
+        }
+    }
+
+    fn set_state(&mut self, key_state: &str) {
+        self.metadata.enabled = Some(key_state == "Enabled");
+        self.metadata.key_state = Some(key_state.to_string());
+    }
+}
+
+/// Binds a symmetric ciphertext to its CMK and encryption context.
+fn blob_aad(key_id: &str, encryption_context: &Option<HashMap<String, String>>) -> Vec<u8> {
+    let context: BTreeMap<&String, &String> = encryption_context.iter().flatten().collect();
+    let mut aad = key_id.as_bytes().to_vec();
+    aad.extend(serde_json::to_vec(&context).unwrap_or_default());
+    aad
+}
+
+/// The position of a list request. Markers are the index of the first item of the next page.
+struct Page {
+    start: usize,
+    limit: usize,
+}
+
+impl Page {
+    fn new(
+        limit: Option<i64>,
+        default_limit: i64,
+        max_limit: i64,
+        marker: &Option<String>,
+    ) -> Result<Page, KmsRsError> {
+        let limit = limit.unwrap_or(default_limit);
+        if !(1..=max_limit).contains(&limit) {
+            return Err(KmsRsError::Validation(format!(
+                "Limit must be between 1 and {}, got {}",
+                max_limit, limit
+            )));
+        }
+        let start = match marker {
+            Some(marker) => marker
+                .parse::<usize>()
+                .map_err(|_| KmsRsError::InvalidMarker(format!("Invalid marker {}", marker)))?,
+            None => 0,
+        };
+        Ok(Page {
+            start,
+            limit: limit as usize,
+        })
+    }
+
+    /// The items on this page, and the marker of the next page if there is one.
+    fn of<'a, T>(&self, items: &'a [T]) -> (&'a [T], Option<String>) {
+        let end = items.len().min(self.start + self.limit);
+        let next_marker = Some(end.to_string()).filter(|_| end < items.len());
+        (items.get(self.start..end).unwrap_or_default(), next_marker)
+    }
+}
+
+/// The alias name in an alias name or alias ARN, or `None` for a key ID or key ARN.
+fn alias_name(key_id: &str) -> Option<&str> {
+    if key_id.starts_with("alias/") {
+        Some(key_id)
+    } else if key_id.starts_with("arn:") {
+        key_id.find(":alias/").map(|index| &key_id[index + 1..])
+    } else {
+        None
+    }
+}
+
+/// Alias names are `alias/` followed by 1 to 250 letters, digits, `/`, `_` or `-`, and must not use
+/// the `alias/aws/` prefix reserved for AWS managed CMKs.
+fn check_alias_name(alias_name: &str) -> Result<(), KmsRsError> {
+    let valid = alias_name
+        .strip_prefix("alias/")
+        .filter(|name| !name.is_empty() && alias_name.len() <= 256)
+        .is_some_and(|name| {
+            name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-'))
+        });
+    if !valid {
+        return Err(KmsRsError::InvalidAliasName(format!(
+            "Alias must start with the prefix \"alias/\" and contain only alphanumeric characters, '/', '_' or '-': {}",
+            alias_name
+        )));
+    }
+    if alias_name.starts_with("alias/aws/") {
+        return Err(KmsRsError::InvalidAliasName(format!(
+            "Alias {} must not begin with alias/aws/, which is reserved for AWS managed keys",
+            alias_name
+        )));
+    }
+    Ok(())
+}
+
+/// Key policies must be valid JSON documents of at most 32 KB. Their content is not enforced.
+fn check_policy(policy: &str) -> Result<(), KmsRsError> {
+    check_length("Policy", policy.len(), 32 * 1024)?;
+    KeyPolicy::from_json(policy)
+        .map(|_| ())
+        .map_err(|err| KmsRsError::MalformedPolicyDocument(err.to_string()))
+}
+
+/// Tag keys are 1 to 128 characters and values up to 256; the `aws:` prefix is reserved for AWS.
+fn check_tags(tags: &[Tag]) -> Result<(), KmsRsError> {
+    for tag in tags {
+        if tag.tag_key.is_empty() || tag.tag_key.chars().count() > 128 {
+            return Err(KmsRsError::Tag(format!(
+                "Tag key must be between 1 and 128 characters: {}",
+                tag.tag_key
+            )));
+        }
+        if tag.tag_value.chars().count() > 256 {
+            return Err(KmsRsError::Tag(format!(
+                "Tag value of {} must be at most 256 characters",
+                tag.tag_key
+            )));
+        }
+        if tag.tag_key.starts_with("aws:") {
+            return Err(KmsRsError::Tag(format!(
+                "Tag key {} must not begin with aws:, which is reserved for AWS",
+                tag.tag_key
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Every CMK has exactly one key policy, named `default`.
+fn check_policy_name(policy_name: &str) -> Result<(), KmsRsError> {
+    if policy_name == DEFAULT_POLICY_NAME {
+        Ok(())
+    } else {
+        Err(KmsRsError::NotFound(format!(
+            "Policy {} does not exist",
+            policy_name
+        )))
+    }
+}
+
+/// Aliases must point at a CMK, given by key ID or key ARN, not at another alias.
+fn check_alias_target(target_key_id: &str) -> Result<(), KmsRsError> {
+    match alias_name(target_key_id) {
+        Some(_) => Err(KmsRsError::Validation(format!(
+            "TargetKeyId {} must be a key ID or key ARN, not an alias",
+            target_key_id
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn check_length(field: &str, len: usize, max: usize) -> Result<(), KmsRsError> {
+    if len == 0 || len > max {
+        return Err(KmsRsError::Validation(format!(
+            "{} must be between 1 and {} bytes, got {}",
+            field, max, len
+        )));
+    }
+    Ok(())
+}
+
+fn now() -> f64 {
+    Utc::now().timestamp_millis() as f64 / 1000.0
+}
+
+/// A random key ID in the UUID format KMS uses, with the `mrk-` prefix for multi-Region keys.
+fn new_key_id(multi_region: bool) -> String {
+    let hex = random_hex(16);
+    if multi_region {
+        format!("mrk-{}", hex)
+    } else {
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+}
+
+/// The password of the `kmsuser` crypto user of a CloudHSM cluster is 7 to 32 characters.
+fn check_key_store_password(key_store_password: &str) -> Result<(), KmsRsError> {
+    if !(7..=32).contains(&key_store_password.len()) {
+        return Err(KmsRsError::Validation(
+            "KeyStorePassword must be between 7 and 32 characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// `len` random bytes, hex encoded.
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[async_trait]
+impl KmsBackend for InMemoryKms {
+    fn for_region(&self, region: &Region) -> Result<Arc<dyn KmsBackend>, KmsRsError> {
+        Ok(Arc::new(self.in_region(region.name())))
+    }
+
+    async fn describe_key(
+        &self,
+        request: DescribeKeyRequest,
+    ) -> Result<DescribeKeyResponse, KmsRsError> {
+        let store = self.store();
+        let key = self.find(&store, &request.key_id)?;
+        Ok(DescribeKeyResponse {
+            key_metadata: Some(key.metadata.clone()),
+        })
+    }
+
+    async fn list_keys(&self, request: ListKeysRequest) -> Result<ListKeysResponse, KmsRsError> {
+        let page = Page::new(request.limit, 100, 1000, &request.marker)?;
+
+        let store = self.store();
+        let (keys, next_marker) = page.of(&store.keys);
+        Ok(ListKeysResponse {
+            keys: Some(
+                keys.iter()
+                    .map(|key| KeyListEntry {
+                        key_arn: key.metadata.arn.clone(),
+                        key_id: Some(key.metadata.key_id.clone()),
+                    })
+                    .collect(),
+            ),
+            truncated: Some(next_marker.is_some()),
+            next_marker,
+        })
+    }
+
+    async fn create_key(&self, request: CreateKeyRequest) -> Result<CreateKeyResponse, KmsRsError> {
+        let key_usage = request
+            .key_usage
+            .unwrap_or_else(|| "ENCRYPT_DECRYPT".to_string());
+        let key_spec = request
+            .customer_master_key_spec
+            .unwrap_or_else(|| "SYMMETRIC_DEFAULT".to_string());
+        let origin = request.origin.unwrap_or_else(|| "AWS_KMS".to_string());
+        let usage_supported = match key_spec.as_str() {
+            "SYMMETRIC_DEFAULT" => key_usage == "ENCRYPT_DECRYPT",
+            spec if spec.starts_with("RSA_") => {
+                key_usage == "ENCRYPT_DECRYPT" || key_usage == "SIGN_VERIFY"
+            }
+            _ => key_usage == "SIGN_VERIFY",
+        };
+        if !usage_supported {
+            return Err(KmsRsError::Validation(format!(
+                "Key usage {} is not supported for key spec {}",
+                key_usage, key_spec
+            )));
+        }
+        let cloud_hsm_cluster_id = match (origin.as_str(), &request.custom_key_store_id) {
+            ("AWS_CLOUDHSM", Some(custom_key_store_id)) => {
+                let mut store = self.store();
+                let custom_key_store =
+                    self.find_custom_key_store(&mut store, custom_key_store_id)?;
+                if custom_key_store.entry.connection_state.as_deref() != Some("CONNECTED") {
+                    return Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                        "Custom key store {} is not connected",
+                        custom_key_store_id
+                    )));
+                }
+                custom_key_store.entry.cloud_hsm_cluster_id.clone()
+            }
+            ("AWS_CLOUDHSM", None) => {
+                return Err(KmsRsError::Validation(
+                    "A custom key store ID is required when the origin is AWS_CLOUDHSM".to_string(),
+                ))
+            }
+            (_, Some(_)) => {
+                return Err(KmsRsError::Validation(
+                    "A custom key store ID is only allowed when the origin is AWS_CLOUDHSM"
+                        .to_string(),
+                ))
+            }
+            _ => None,
+        };
+
+        let (material, key_state) = match origin.as_str() {
+            "AWS_KMS" if key_spec == "SYMMETRIC_DEFAULT" => {
+                let mut material = Zeroizing::new(vec![0u8; 32]);
+                OsRng.fill_bytes(&mut material);
+                (KeyMaterial::Symmetric(material), "Enabled")
+            }
+            "AWS_KMS" => (
+                KeyMaterial::Asymmetric(PrivateKey::generate(&key_spec)?),
+                "Enabled",
+            ),
+            "EXTERNAL" if key_spec == "SYMMETRIC_DEFAULT" => (KeyMaterial::None, "PendingImport"),
+            "AWS_CLOUDHSM" if key_spec == "SYMMETRIC_DEFAULT" => {
+                let mut material = Zeroizing::new(vec![0u8; 32]);
+                OsRng.fill_bytes(&mut material);
+                (KeyMaterial::Symmetric(material), "Enabled")
+            }
+            _ => {
+                return Err(KmsRsError::UnsupportedOperation(format!(
+                    "Origin {} is not supported for key spec {}",
+                    origin, key_spec
+                )))
+            }
+        };
+
+        let tags = request.tags.unwrap_or_default();
+        check_tags(&tags)?;
+        let policy = match request.policy {
+            Some(policy) => {
+                check_policy(&policy)?;
+                policy
+            }
+            None => KeyPolicy::default_for_account(&self.account_id).to_json(),
+        };
+
+        let multi_region = request.multi_region.unwrap_or(false);
+        let key_id = new_key_id(multi_region);
+        let (encryption_algorithms, signing_algorithms) = if key_usage == "SIGN_VERIFY" {
+            (None, Some(crypto::signing_algorithms(&key_spec)))
+        } else {
+            (Some(crypto::encryption_algorithms(&key_spec)), None)
+        };
+        let arn = self.key_arn(&key_id);
+        let multi_region_configuration = Some(MultiRegionConfiguration {
+            multi_region_key_type: Some("PRIMARY".to_string()),
+            primary_key: Some(MultiRegionKey {
+                arn: Some(arn.clone()),
+                region: Some(self.region.clone()),
+            }),
+            replica_keys: Some(Vec::new()),
+        })
+        .filter(|_| multi_region);
+        let metadata = KeyMetadata {
+            arn: Some(arn),
+            aws_account_id: Some(self.account_id.clone()),
+            cloud_hsm_cluster_id,
+            creation_date: Some(now()),
+            custom_key_store_id: request.custom_key_store_id,
+            customer_master_key_spec: Some(key_spec),
+            description: Some(request.description.unwrap_or_default()),
+            enabled: Some(key_state == "Enabled"),
+            encryption_algorithms,
+            key_id,
+            key_manager: Some("CUSTOMER".to_string()),
+            key_state: Some(key_state.to_string()),
+            key_usage: Some(key_usage),
+            multi_region: Some(multi_region),
+            multi_region_configuration,
+            origin: Some(origin),
+            signing_algorithms,
+            ..KeyMetadata::default()
+        };
+
+        self.store().keys.push(StoredKey {
+            metadata: metadata.clone(),
+            material,
+            policy,
+            tags,
+            rotation_enabled: false,
+            imported_digest: None,
+        });
+        Ok(CreateKeyResponse {
+            key_metadata: Some(metadata),
+        })
+    }
+
+    async fn create_alias(&self, request: CreateAliasRequest) -> Result<(), KmsRsError> {
+        check_alias_name(&request.alias_name)?;
+        check_alias_target(&request.target_key_id)?;
+
+        let mut store = self.store();
+        let key = self.find(&store, &request.target_key_id)?;
+        key.check_not_pending_deletion()?;
+        let target_key_id = key.metadata.key_id.clone();
+        if self.find_alias(&store, &request.alias_name).is_some() {
+            return Err(KmsRsError::AlreadyExists(format!(
+                "An alias with the name {} already exists",
+                self.alias_arn(&request.alias_name)
+            )));
+        }
+        let creation_date = now();
+        store.aliases.push(StoredAlias {
+            alias_name: request.alias_name,
+            target_key_id,
+            creation_date,
+            last_updated_date: creation_date,
+        });
+        Ok(())
+    }
+
+    async fn update_alias(&self, request: UpdateAliasRequest) -> Result<(), KmsRsError> {
+        check_alias_target(&request.target_key_id)?;
+
+        let mut store = self.store();
+        let current = self.find(&store, &request.alias_name)?;
+        let key = self.find(&store, &request.target_key_id)?;
+        key.check_not_pending_deletion()?;
+        // KMS only moves an alias between CMKs of the same type and key usage.
+        let symmetric = |key: &StoredKey| {
+            key.metadata.customer_master_key_spec.as_deref() == Some("SYMMETRIC_DEFAULT")
+        };
+        if symmetric(current) != symmetric(key)
+            || current.metadata.key_usage != key.metadata.key_usage
+        {
+            return Err(KmsRsError::Validation(format!(
+                "{} and {} are not the same key type or key usage",
+                current.arn(),
+                key.arn()
+            )));
+        }
+        let target_key_id = key.metadata.key_id.clone();
+        let alias = store
+            .aliases
+            .iter_mut()
+            .find(|alias| alias.alias_name == request.alias_name)
+            .ok_or_else(|| self.alias_not_found(&request.alias_name))?;
+        alias.target_key_id = target_key_id;
+        alias.last_updated_date = now();
+        Ok(())
+    }
+
+    async fn delete_alias(&self, request: DeleteAliasRequest) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let count = store.aliases.len();
+        store
+            .aliases
+            .retain(|alias| alias.alias_name != request.alias_name);
+        if store.aliases.len() == count {
+            return Err(self.alias_not_found(&request.alias_name));
+        }
+        Ok(())
+    }
+
+    async fn list_aliases(
+        &self,
+        request: ListAliasesRequest,
+    ) -> Result<ListAliasesResponse, KmsRsError> {
+        let page = Page::new(request.limit, 50, 100, &request.marker)?;
+
+        let store = self.store();
+        let target_key_id = match &request.key_id {
+            Some(key_id) if alias_name(key_id).is_some() => {
+                return Err(KmsRsError::InvalidArn(format!(
+                    "{} is not a key ID or key ARN",
+                    key_id
+                )))
+            }
+            Some(key_id) => Some(self.find(&store, key_id)?.metadata.key_id.as_str()),
+            None => None,
+        };
+        let aliases: Vec<&StoredAlias> = store
+            .aliases
+            .iter()
+            .filter(|alias| target_key_id.is_none_or(|key_id| alias.target_key_id == key_id))
+            .collect();
+        let (aliases, next_marker) = page.of(&aliases);
+        Ok(ListAliasesResponse {
+            aliases: Some(
+                aliases
+                    .iter()
+                    .map(|alias| AliasListEntry {
+                        alias_arn: Some(self.alias_arn(&alias.alias_name)),
+                        alias_name: Some(alias.alias_name.clone()),
+                        creation_date: Some(alias.creation_date),
+                        last_updated_date: Some(alias.last_updated_date),
+                        target_key_id: Some(alias.target_key_id.clone()),
+                    })
+                    .collect(),
+            ),
+            truncated: Some(next_marker.is_some()),
+            next_marker,
+        })
+    }
+
+    async fn create_grant(
+        &self,
+        mut request: CreateGrantRequest,
+    ) -> Result<CreateGrantResponse, KmsRsError> {
+        if request.operations.is_empty() {
+            return Err(KmsRsError::Validation(
+                "Operations must contain at least one operation".to_string(),
+            ));
+        }
+        if let Some(operation) = request
+            .operations
+            .iter()
+            .find(|operation| !GRANT_OPERATIONS.contains(&operation.as_str()))
+        {
+            return Err(KmsRsError::Validation(format!(
+                "Operation {} is not a valid grant operation",
+                operation
+            )));
+        }
+        if let Some(constraints) = &request.constraints {
+            if constraints.encryption_context_equals.is_some()
+                && constraints.encryption_context_subset.is_some()
+            {
+                return Err(KmsRsError::Validation(
+                    "Constraints can only contain one of EncryptionContextEquals and EncryptionContextSubset"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let mut store = self.store();
+        if alias_name(&request.key_id).is_some() {
+            return Err(KmsRsError::InvalidArn(format!(
+                "{} is not a key ID or key ARN",
+                request.key_id
+            )));
+        }
+        let key = self.find(&store, &request.key_id)?;
+        key.check_not_pending_deletion()?;
+        let key_id = key.metadata.key_id.clone();
+        // The grant tokens of the request are only for authorization, which InMemoryKms does not model.
+        request.grant_tokens = None;
+        request.key_id = key_id.clone();
+
+        // Like KMS, retrying a named grant with the same parameters returns the existing grant.
+        if request.name.is_some() {
+            if let Some(grant) = store.grants.iter().find(|grant| grant.request == request) {
+                return Ok(CreateGrantResponse {
+                    grant_id: Some(grant.grant_id.clone()),
+                    grant_token: Some(grant.grant_token.clone()),
+                });
+            }
+        }
+        let grant = StoredGrant {
+            grant_id: random_hex(32),
+            grant_token: random_hex(64),
+            key_id,
+            request,
+            creation_date: now(),
+        };
+        let response = CreateGrantResponse {
+            grant_id: Some(grant.grant_id.clone()),
+            grant_token: Some(grant.grant_token.clone()),
+        };
+        store.grants.push(grant);
+        Ok(response)
+    }
+
+    async fn list_grants(
+        &self,
+        request: ListGrantsRequest,
+    ) -> Result<ListGrantsResponse, KmsRsError> {
+        let page = Page::new(request.limit, 50, 100, &request.marker)?;
+
+        let store = self.store();
+        let key_id = &self.find(&store, &request.key_id)?.metadata.key_id;
+        let grants: Vec<&StoredGrant> = store
+            .grants
+            .iter()
+            .filter(|grant| &grant.key_id == key_id)
+            .filter(|grant| {
+                request
+                    .grant_id
+                    .as_ref()
+                    .is_none_or(|id| &grant.grant_id == id)
+            })
+            .filter(|grant| {
+                request
+                    .grantee_principal
+                    .as_ref()
+                    .is_none_or(|grantee| &grant.request.grantee_principal == grantee)
+            })
+            .collect();
+        let (grants, next_marker) = page.of(&grants);
+        Ok(ListGrantsResponse {
+            grants: Some(
+                grants
+                    .iter()
+                    .map(|grant| self.grant_list_entry(grant))
+                    .collect(),
+            ),
+            truncated: Some(next_marker.is_some()),
+            next_marker,
+        })
+    }
+
+    async fn list_retirable_grants(
+        &self,
+        request: ListRetirableGrantsRequest,
+    ) -> Result<ListGrantsResponse, KmsRsError> {
+        let page = Page::new(request.limit, 50, 100, &request.marker)?;
+
+        let store = self.store();
+        let grants: Vec<&StoredGrant> = store
+            .grants
+            .iter()
+            .filter(|grant| {
+                grant.request.retiring_principal.as_ref() == Some(&request.retiring_principal)
+            })
+            .collect();
+        let (grants, next_marker) = page.of(&grants);
+        Ok(ListGrantsResponse {
+            grants: Some(
+                grants
+                    .iter()
+                    .map(|grant| self.grant_list_entry(grant))
+                    .collect(),
+            ),
+            truncated: Some(next_marker.is_some()),
+            next_marker,
+        })
+    }
+
+    async fn retire_grant(&self, request: RetireGrantRequest) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let index = match (&request.grant_token, &request.key_id, &request.grant_id) {
+            (Some(grant_token), _, _) => store
+                .grants
+                .iter()
+                .position(|grant| &grant.grant_token == grant_token)
+                .ok_or_else(|| KmsRsError::InvalidGrantToken("Invalid grant token".to_string()))?,
+            (None, Some(key_id), Some(grant_id)) => {
+                let key_id = self.find(&store, key_id)?.metadata.key_id.clone();
+                store
+                    .grants
+                    .iter()
+                    .position(|grant| grant.key_id == key_id && &grant.grant_id == grant_id)
+                    .ok_or_else(|| InMemoryKms::grant_not_found(grant_id))?
+            }
+            _ => {
+                return Err(KmsRsError::Validation(
+                    "Either GrantToken, or both KeyId and GrantId, must be specified".to_string(),
+                ))
+            }
+        };
+        store.grants.remove(index);
+        Ok(())
+    }
+
+    async fn revoke_grant(&self, request: RevokeGrantRequest) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let key_id = self.find(&store, &request.key_id)?.metadata.key_id.clone();
+        let index = store
+            .grants
+            .iter()
+            .position(|grant| grant.key_id == key_id && grant.grant_id == request.grant_id)
+            .ok_or_else(|| InMemoryKms::grant_not_found(&request.grant_id))?;
+        store.grants.remove(index);
+        Ok(())
+    }
+
+    async fn get_key_policy(
+        &self,
+        request: GetKeyPolicyRequest,
+    ) -> Result<GetKeyPolicyResponse, KmsRsError> {
+        check_policy_name(&request.policy_name)?;
+        let store = self.store();
+        let key = self.find(&store, &request.key_id)?;
+        Ok(GetKeyPolicyResponse {
+            policy: Some(key.policy.clone()),
+        })
+    }
+
+    async fn put_key_policy(&self, request: PutKeyPolicyRequest) -> Result<(), KmsRsError> {
+        check_policy_name(&request.policy_name)?;
+        check_policy(&request.policy)?;
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_not_pending_deletion()?;
+        key.policy = request.policy;
+        Ok(())
+    }
+
+    async fn list_key_policies(
+        &self,
+        request: ListKeyPoliciesRequest,
+    ) -> Result<ListKeyPoliciesResponse, KmsRsError> {
+        let page = Page::new(request.limit, 100, 1000, &request.marker)?;
+        let store = self.store();
+        self.find(&store, &request.key_id)?;
+        let (policy_names, next_marker) = page.of(&[DEFAULT_POLICY_NAME]);
+        Ok(ListKeyPoliciesResponse {
+            policy_names: Some(policy_names.iter().map(|name| name.to_string()).collect()),
+            truncated: Some(next_marker.is_some()),
+            next_marker,
+        })
+    }
+
+    async fn tag_resource(&self, request: TagResourceRequest) -> Result<(), KmsRsError> {
+        check_tags(&request.tags)?;
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_not_pending_deletion()?;
+        let mut tags = key.tags.clone();
+        for tag in request.tags {
+            match tags
+                .iter_mut()
+                .find(|existing| existing.tag_key == tag.tag_key)
+            {
+                Some(existing) => existing.tag_value = tag.tag_value,
+                None => tags.push(tag),
+            }
+        }
+        if tags.len() > MAX_TAGS {
+            return Err(KmsRsError::LimitExceeded(format!(
+                "A CMK can have at most {} tags",
+                MAX_TAGS
+            )));
+        }
+        key.tags = tags;
+        Ok(())
+    }
+
+    async fn untag_resource(&self, request: UntagResourceRequest) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_not_pending_deletion()?;
+        key.tags
+            .retain(|tag| !request.tag_keys.contains(&tag.tag_key));
+        Ok(())
+    }
+
+    async fn list_resource_tags(
+        &self,
+        request: ListResourceTagsRequest,
+    ) -> Result<ListResourceTagsResponse, KmsRsError> {
+        let page = Page::new(request.limit, 50, 50, &request.marker)?;
+        let store = self.store();
+        let key = self.find(&store, &request.key_id)?;
+        let (tags, next_marker) = page.of(&key.tags);
+        Ok(ListResourceTagsResponse {
+            tags: Some(tags.to_vec()),
+            truncated: Some(next_marker.is_some()),
+            next_marker,
+        })
+    }
+
+    async fn schedule_key_deletion(
+        &self,
+        request: ScheduleKeyDeletionRequest,
+    ) -> Result<ScheduleKeyDeletionResponse, KmsRsError> {
+        let pending_window_in_days = request.pending_window_in_days.unwrap_or(30);
+        if !(7..=30).contains(&pending_window_in_days) {
+            return Err(KmsRsError::Validation(format!(
+                "PendingWindowInDays must be between 7 and 30, got {}",
+                pending_window_in_days
+            )));
+        }
+
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        if key.metadata.key_state.as_deref() == Some("PendingDeletion") {
+            return Err(KmsRsError::KmsInvalidState(format!(
+                "{} is pending deletion.",
+                key.arn()
+            )));
+        }
+        let deletion_date = now() + (pending_window_in_days * 24 * 60 * 60) as f64;
+        key.set_state("PendingDeletion");
+        key.metadata.deletion_date = Some(deletion_date);
+        key.metadata.pending_deletion_window_in_days = Some(pending_window_in_days);
+        Ok(ScheduleKeyDeletionResponse {
+            deletion_date: Some(deletion_date),
+            key_id: Some(key.arn()),
+            key_state: key.metadata.key_state.clone(),
+            pending_window_in_days: Some(pending_window_in_days),
+        })
+    }
+
+    async fn cancel_key_deletion(
+        &self,
+        request: CancelKeyDeletionRequest,
+    ) -> Result<CancelKeyDeletionResponse, KmsRsError> {
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        if key.metadata.key_state.as_deref() != Some("PendingDeletion") {
+            return Err(KmsRsError::KmsInvalidState(format!(
+                "{} is not pending deletion.",
+                key.arn()
+            )));
+        }
+        // Like KMS, a CMK whose deletion is cancelled stays disabled until it is enabled again.
+        key.set_state("Disabled");
+        key.metadata.deletion_date = None;
+        key.metadata.pending_deletion_window_in_days = None;
+        Ok(CancelKeyDeletionResponse {
+            key_id: Some(key.arn()),
+        })
+    }
+
+    async fn replicate_key(
+        &self,
+        request: ReplicateKeyRequest,
+    ) -> Result<ReplicateKeyResponse, KmsRsError> {
+        let tags = request.tags.unwrap_or_default();
+        check_tags(&tags)?;
+        let policy = match request.policy {
+            Some(policy) => {
+                check_policy(&policy)?;
+                policy
+            }
+            None => KeyPolicy::default_for_account(&self.account_id).to_json(),
+        };
+        if request.replica_region == self.region {
+            return Err(KmsRsError::Validation(format!(
+                "The replica Region {} must differ from the Region of the primary key",
+                request.replica_region
+            )));
+        }
+
+        let (primary, material, rotation_enabled) = {
+            let store = self.store();
+            let key = self.find(&store, &request.key_id)?;
+            let key_type = key
+                .metadata
+                .multi_region_configuration
+                .as_ref()
+                .and_then(|configuration| configuration.multi_region_key_type.as_deref());
+            if key_type != Some("PRIMARY") {
+                return Err(KmsRsError::UnsupportedOperation(format!(
+                    "{} is not a multi-Region primary key.",
+                    key.arn()
+                )));
+            }
+            key.check_usable()?;
+            (
+                key.metadata.clone(),
+                key.material.clone(),
+                key.rotation_enabled,
+            )
+        };
+
+        let replica_kms = self.in_region(&request.replica_region);
+        let replica_arn = replica_kms.key_arn(&primary.key_id);
+        let metadata = KeyMetadata {
+            arn: Some(replica_arn.clone()),
+            creation_date: Some(now()),
+            description: request.description.or(primary.description.clone()),
+            ..primary.clone()
+        };
+        {
+            let mut store = replica_kms.store();
+            if replica_kms.find(&store, &primary.key_id).is_ok() {
+                return Err(KmsRsError::AlreadyExists(format!(
+                    "{} already exists",
+                    replica_arn
+                )));
+            }
+            store.keys.push(StoredKey {
+                metadata,
+                material,
+                policy: policy.clone(),
+                tags: tags.clone(),
+                rotation_enabled,
+                imported_digest: None,
+            });
+        }
+
+        let configuration = primary.multi_region_configuration.unwrap_or_default();
+        let mut replicas = configuration.replica_keys.unwrap_or_default();
+        replicas.push(MultiRegionKey {
+            arn: Some(replica_arn),
+            region: Some(request.replica_region),
+        });
+        self.set_multi_region_configuration(
+            &primary.key_id,
+            &configuration.primary_key.unwrap_or_default(),
+            &replicas,
+        );
+
+        let store = replica_kms.store();
+        Ok(ReplicateKeyResponse {
+            replica_key_metadata: Some(replica_kms.find(&store, &primary.key_id)?.metadata.clone()),
+            replica_policy: Some(policy),
+            replica_tags: Some(tags),
+        })
+    }
+
+    async fn update_primary_region(
+        &self,
+        request: UpdatePrimaryRegionRequest,
+    ) -> Result<(), KmsRsError> {
+        let (key_id, configuration) = {
+            let store = self.store();
+            let key = self.find(&store, &request.key_id)?;
+            key.check_not_pending_deletion()?;
+            let configuration = key
+                .metadata
+                .multi_region_configuration
+                .clone()
+                .filter(|configuration| {
+                    configuration.multi_region_key_type.as_deref() == Some("PRIMARY")
+                })
+                .ok_or_else(|| {
+                    KmsRsError::UnsupportedOperation(format!(
+                        "{} is not a multi-Region primary key.",
+                        key.arn()
+                    ))
+                })?;
+            (key.metadata.key_id.clone(), configuration)
+        };
+
+        let mut replicas = configuration.replica_keys.unwrap_or_default();
+        let index = replicas
+            .iter()
+            .position(|replica| replica.region.as_deref() == Some(&request.primary_region))
+            .ok_or_else(|| {
+                KmsRsError::NotFound(format!(
+                    "{} has no replica in Region {}",
+                    self.key_arn(&key_id),
+                    request.primary_region
+                ))
+            })?;
+        let primary = replicas.remove(index);
+        replicas.push(configuration.primary_key.unwrap_or_default());
+        self.set_multi_region_configuration(&key_id, &primary, &replicas);
+        Ok(())
+    }
+
+    async fn enable_key(&self, request: EnableKeyRequest) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_not_pending()?;
+        key.set_state("Enabled");
+        Ok(())
+    }
+
+    async fn disable_key(&self, request: DisableKeyRequest) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_not_pending()?;
+        key.set_state("Disabled");
+        Ok(())
+    }
+
+    async fn get_parameters_for_import(
+        &self,
+        request: GetParametersForImportRequest,
+    ) -> Result<GetParametersForImportResponse, KmsRsError> {
+        match request.wrapping_algorithm.as_str() {
+            "RSAES_OAEP_SHA_1" | "RSAES_OAEP_SHA_256" => (),
+            algorithm => {
+                return Err(KmsRsError::UnsupportedOperation(format!(
+                    "InMemoryKms does not support the wrapping algorithm {}",
+                    algorithm
+                )))
+            }
+        }
+        if request.wrapping_key_spec != "RSA_2048" {
+            return Err(KmsRsError::Validation(format!(
+                "Unsupported wrapping key spec {}",
+                request.wrapping_key_spec
+            )));
+        }
+
+        let mut store = self.store();
+        let key = self.find(&store, &request.key_id)?;
+        key.check_external()?;
+        key.check_not_pending_deletion()?;
+        let key_id = key.metadata.key_id.clone();
+        let arn = key.arn();
+
+        let wrapping_key = PrivateKey::generate(&request.wrapping_key_spec)?;
+        let public_key = wrapping_key.public_key().to_der()?;
+        let mut import_token = vec![0u8; 32];
+        OsRng.fill_bytes(&mut import_token);
+        let now = now();
+        let valid_to = now + IMPORT_PARAMETERS_VALIDITY;
+
+        store.imports.retain(|import| import.valid_to > now);
+        store.imports.push(PendingImport {
+            import_token: import_token.clone(),
+            key_id,
+            wrapping_algorithm: request.wrapping_algorithm,
+            wrapping_key,
+            valid_to,
+        });
+        Ok(GetParametersForImportResponse {
+            import_token: Some(Bytes::from(import_token)),
+            key_id: Some(arn),
+            parameters_valid_to: Some(valid_to),
+            public_key: Some(Bytes::from(public_key)),
+        })
+    }
+
+    async fn import_key_material(
+        &self,
+        request: ImportKeyMaterialRequest,
+    ) -> Result<(), KmsRsError> {
+        let expiration_model = request
+            .expiration_model
+            .unwrap_or_else(|| "KEY_MATERIAL_EXPIRES".to_string());
+        match (expiration_model.as_str(), request.valid_to) {
+            ("KEY_MATERIAL_EXPIRES", Some(valid_to)) if valid_to > now() => (),
+            ("KEY_MATERIAL_EXPIRES", _) => {
+                return Err(KmsRsError::Validation(
+                    "ValidTo must be in the future when key material expires".to_string(),
+                ))
+            }
+            ("KEY_MATERIAL_DOES_NOT_EXPIRE", None) => (),
+            _ => {
+                return Err(KmsRsError::Validation(format!(
+                    "Invalid expiration model {} with ValidTo {:?}",
+                    expiration_model, request.valid_to
+                )))
+            }
+        }
+
+        let mut store = self.store();
+        let key_index = self.key_index(&store, &request.key_id)?;
+        let key = &store.keys[key_index];
+        key.check_external()?;
+        key.check_not_pending_deletion()?;
+
+        let invalid_token =
+            || KmsRsError::InvalidImportToken("Import token is not valid for this CMK".to_string());
+        let import_token = request.import_token.as_ref();
+        let import = store
+            .imports
+            .iter()
+            .find(|import| import.import_token == import_token)
+            .ok_or_else(invalid_token)?;
+        if import.key_id != key.metadata.key_id {
+            return Err(invalid_token());
+        }
+        if import.valid_to <= now() {
+            return Err(KmsRsError::ExpiredImportToken(
+                "Import token has expired".to_string(),
+            ));
+        }
+        let material = import
+            .wrapping_key
+            .decrypt(&import.wrapping_algorithm, &request.encrypted_key_material)
+            .map_err(|_| KmsRsError::InvalidCiphertext(String::new()))?;
+        if material.len() != 32 {
+            return Err(KmsRsError::IncorrectKeyMaterial(
+                "Key material must be 256 bits".to_string(),
+            ));
+        }
+        let digest = Sha256::digest(&material).to_vec();
+        if key
+            .imported_digest
+            .as_ref()
+            .is_some_and(|imported| *imported != digest)
+        {
+            return Err(KmsRsError::IncorrectKeyMaterial(
+                "Key material is different from the key material previously imported into this CMK"
+                    .to_string(),
+            ));
+        }
+
+        let key = &mut store.keys[key_index];
+        key.material = KeyMaterial::Symmetric(material);
+        key.imported_digest = Some(digest);
+        if key.metadata.key_state.as_deref() == Some("PendingImport") {
+            key.set_state("Enabled");
+        }
+        key.metadata.expiration_model = Some(expiration_model);
+        key.metadata.valid_to = request.valid_to;
+        Ok(())
+    }
+
+    async fn delete_imported_key_material(
+        &self,
+        request: DeleteImportedKeyMaterialRequest,
+    ) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_external()?;
+        key.check_not_pending_deletion()?;
+        key.material = KeyMaterial::None;
+        key.set_state("PendingImport");
+        key.metadata.expiration_model = None;
+        key.metadata.valid_to = None;
+        Ok(())
+    }
+
+    async fn enable_key_rotation(
+        &self,
+        request: EnableKeyRotationRequest,
+    ) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_rotation_supported()?;
+        key.check_usable()?;
+        key.rotation_enabled = true;
+        Ok(())
+    }
+
+    async fn disable_key_rotation(
+        &self,
+        request: DisableKeyRotationRequest,
+    ) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let key = self.find_mut(&mut store, &request.key_id)?;
+        key.check_rotation_supported()?;
+        key.check_usable()?;
+        key.rotation_enabled = false;
+        Ok(())
+    }
+
+    async fn get_key_rotation_status(
+        &self,
+        request: GetKeyRotationStatusRequest,
+    ) -> Result<GetKeyRotationStatusResponse, KmsRsError> {
+        let store = self.store();
+        let key = self.find(&store, &request.key_id)?;
+        key.check_rotation_supported()?;
+        // KMS reports rotation as disabled while the CMK is pending deletion.
+        let pending_deletion = key.metadata.key_state.as_deref() == Some("PendingDeletion");
+        Ok(GetKeyRotationStatusResponse {
+            key_rotation_enabled: Some(key.rotation_enabled && !pending_deletion),
+        })
+    }
+
+    async fn generate_data_key(
+        &self,
+        request: GenerateDataKeyRequest,
+    ) -> Result<GenerateDataKeyResponse, KmsRsError> {
+        let (key_id, ciphertext_blob, plaintext) = self.data_key(
+            &request.key_id,
+            request.key_spec.as_deref(),
+            request.number_of_bytes,
+            &request.encryption_context,
+        )?;
+        Ok(GenerateDataKeyResponse {
+            ciphertext_blob: Some(ciphertext_blob),
+            key_id: Some(key_id),
+            plaintext: Some(plaintext),
+        })
+    }
+
+    async fn generate_data_key_without_plaintext(
+        &self,
+        request: GenerateDataKeyWithoutPlaintextRequest,
+    ) -> Result<GenerateDataKeyWithoutPlaintextResponse, KmsRsError> {
+        let (key_id, ciphertext_blob, _) = self.data_key(
+            &request.key_id,
+            request.key_spec.as_deref(),
+            request.number_of_bytes,
+            &request.encryption_context,
+        )?;
+        Ok(GenerateDataKeyWithoutPlaintextResponse {
+            ciphertext_blob: Some(ciphertext_blob),
+            key_id: Some(key_id),
+        })
+    }
+
+    async fn generate_data_key_pair(
+        &self,
+        request: GenerateDataKeyPairRequest,
+    ) -> Result<GenerateDataKeyPairResponse, KmsRsError> {
+        let pair = self.data_key_pair(
+            &request.key_id,
+            &request.key_pair_spec,
+            &request.encryption_context,
+        )?;
+        Ok(GenerateDataKeyPairResponse {
+            key_id: Some(pair.key_id),
+            key_pair_spec: Some(request.key_pair_spec),
+            private_key_ciphertext_blob: Some(pair.private_key_ciphertext_blob),
+            private_key_plaintext: Some(Bytes::copy_from_slice(&pair.private_key_plaintext)),
+            public_key: Some(pair.public_key),
+        })
+    }
+
+    async fn generate_data_key_pair_without_plaintext(
+        &self,
+        request: GenerateDataKeyPairWithoutPlaintextRequest,
+    ) -> Result<GenerateDataKeyPairWithoutPlaintextResponse, KmsRsError> {
+        let pair = self.data_key_pair(
+            &request.key_id,
+            &request.key_pair_spec,
+            &request.encryption_context,
+        )?;
+        Ok(GenerateDataKeyPairWithoutPlaintextResponse {
+            key_id: Some(pair.key_id),
+            key_pair_spec: Some(request.key_pair_spec),
+            private_key_ciphertext_blob: Some(pair.private_key_ciphertext_blob),
+            public_key: Some(pair.public_key),
+        })
+    }
+
+    async fn encrypt(&self, request: EncryptRequest) -> Result<EncryptResponse, KmsRsError> {
+        let encryption_algorithm = request
+            .encryption_algorithm
+            .unwrap_or_else(|| "SYMMETRIC_DEFAULT".to_string());
+
+        let store = self.store();
+        let (key_id, ciphertext_blob) = self.encrypt_with(
+            &store,
+            &request.key_id,
+            &request.plaintext,
+            &request.encryption_context,
+            &encryption_algorithm,
+        )?;
+        Ok(EncryptResponse {
+            ciphertext_blob: Some(ciphertext_blob),
+            encryption_algorithm: Some(encryption_algorithm),
+            key_id: Some(key_id),
+        })
+    }
+
+    async fn decrypt(&self, request: DecryptRequest) -> Result<DecryptResponse, KmsRsError> {
+        let encryption_algorithm = request
+            .encryption_algorithm
+            .unwrap_or_else(|| "SYMMETRIC_DEFAULT".to_string());
+
+        let store = self.store();
+        let (key_id, plaintext) = self.decrypt_with(
+            &store,
+            request.key_id.as_deref(),
+            &request.ciphertext_blob,
+            &request.encryption_context,
+            &encryption_algorithm,
+        )?;
+        Ok(DecryptResponse {
+            encryption_algorithm: Some(encryption_algorithm),
+            key_id: Some(key_id),
+            plaintext: Some(Bytes::copy_from_slice(&plaintext)),
+        })
+    }
+
+    async fn re_encrypt(&self, request: ReEncryptRequest) -> Result<ReEncryptResponse, KmsRsError> {
+        let source_encryption_algorithm = request
+            .source_encryption_algorithm
+            .unwrap_or_else(|| "SYMMETRIC_DEFAULT".to_string());
+        let destination_encryption_algorithm = request
+            .destination_encryption_algorithm
+            .unwrap_or_else(|| "SYMMETRIC_DEFAULT".to_string());
+
+        // The plaintext never leaves this call, as it never leaves KMS.
+        let store = self.store();
+        let (source_key_id, plaintext) = self.decrypt_with(
+            &store,
+            request.source_key_id.as_deref(),
+            &request.ciphertext_blob,
+            &request.source_encryption_context,
+            &source_encryption_algorithm,
+        )?;
+        let (key_id, ciphertext_blob) = self.encrypt_with(
+            &store,
+            &request.destination_key_id,
+            &plaintext,
+            &request.destination_encryption_context,
+            &destination_encryption_algorithm,
+        )?;
+        Ok(ReEncryptResponse {
+            ciphertext_blob: Some(ciphertext_blob),
+            destination_encryption_algorithm: Some(destination_encryption_algorithm),
+            key_id: Some(key_id),
+            source_encryption_algorithm: Some(source_encryption_algorithm),
+            source_key_id: Some(source_key_id),
+        })
+    }
+
+    async fn sign(&self, request: SignRequest) -> Result<SignResponse, KmsRsError> {
+        let store = self.store();
+        let (key, digest) = self.signing_digest(
+            &store,
+            &request.key_id,
+            &request.message,
+            request.message_type.as_deref(),
+            &request.signing_algorithm,
+        )?;
+        let signature = key
+            .private_key("Sign")?
+            .sign_digest(&request.signing_algorithm, &digest)?;
+        Ok(SignResponse {
+            key_id: Some(key.arn()),
+            signature: Some(Bytes::from(signature)),
+            signing_algorithm: Some(request.signing_algorithm),
+        })
+    }
+
+    async fn verify(&self, request: VerifyRequest) -> Result<VerifyResponse, KmsRsError> {
+        let store = self.store();
+        let (key, digest) = self.signing_digest(
+            &store,
+            &request.key_id,
+            &request.message,
+            request.message_type.as_deref(),
+            &request.signing_algorithm,
+        )?;
+        let public_key: PublicKey = key.private_key("Verify")?.public_key();
+        if !public_key.verify_digest(&request.signing_algorithm, &digest, &request.signature)? {
+            return Err(KmsRsError::KmsInvalidSignature(String::new()));
+        }
+        Ok(VerifyResponse {
+            key_id: Some(key.arn()),
+            signature_valid: Some(true),
+            signing_algorithm: Some(request.signing_algorithm),
+        })
+    }
+
+    async fn get_public_key(
+        &self,
+        request: GetPublicKeyRequest,
+    ) -> Result<GetPublicKeyResponse, KmsRsError> {
+        let store = self.store();
+        let key = self.find(&store, &request.key_id)?;
+        key.check_usable()?;
+        let private_key = match &key.material {
+            KeyMaterial::Asymmetric(private_key) => private_key,
+            _ => {
+                return Err(KmsRsError::UnsupportedOperation(format!(
+                    "{} key spec is SYMMETRIC_DEFAULT which is not valid for GetPublicKey.",
+                    key.arn()
+                )))
+            }
+        };
+        Ok(GetPublicKeyResponse {
+            customer_master_key_spec: key.metadata.customer_master_key_spec.clone(),
+            encryption_algorithms: key.metadata.encryption_algorithms.clone(),
+            key_id: Some(key.arn()),
+            key_usage: key.metadata.key_usage.clone(),
+            public_key: Some(Bytes::from(private_key.public_key().to_der()?)),
+            signing_algorithms: key.metadata.signing_algorithms.clone(),
+        })
+    }
+
+    async fn generate_random(
+        &self,
+        request: GenerateRandomRequest,
+    ) -> Result<GenerateRandomResponse, KmsRsError> {
+        if let Some(custom_key_store_id) = request.custom_key_store_id {
+            let mut store = self.store();
+            let custom_key_store = self.find_custom_key_store(&mut store, &custom_key_store_id)?;
+            if custom_key_store.entry.connection_state.as_deref() != Some("CONNECTED") {
+                return Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                    "Custom key store {} is not connected",
+                    custom_key_store_id
+                )));
+            }
+        }
+        let number_of_bytes = request.number_of_bytes.unwrap_or(0).max(0) as usize;
+        check_length("NumberOfBytes", number_of_bytes, 1024)?;
+        let mut plaintext = vec![0u8; number_of_bytes];
+        OsRng.fill_bytes(&mut plaintext);
+        Ok(GenerateRandomResponse {
+            plaintext: Some(Bytes::from(plaintext)),
+        })
+    }
+
+    async fn create_custom_key_store(
+        &self,
+        request: CreateCustomKeyStoreRequest,
+    ) -> Result<CreateCustomKeyStoreResponse, KmsRsError> {
+        check_key_store_password(&request.key_store_password)?;
+        if !request.cloud_hsm_cluster_id.starts_with("cluster-") {
+            return Err(KmsRsError::CloudHsmClusterNotFound(format!(
+                "CloudHSM cluster {} does not exist",
+                request.cloud_hsm_cluster_id
+            )));
+        }
+        if !request
+            .trust_anchor_certificate
+            .contains("-----BEGIN CERTIFICATE-----")
+        {
+            return Err(KmsRsError::IncorrectTrustAnchor(
+                "The trust anchor certificate is not a PEM-encoded certificate".to_string(),
+            ));
+        }
+
+        let mut store = self.store();
+        for custom_key_store in &store.custom_key_stores {
+            let entry = &custom_key_store.entry;
+            if entry.custom_key_store_name.as_deref() == Some(&request.custom_key_store_name) {
+                return Err(KmsRsError::CustomKeyStoreNameInUse(format!(
+                    "Custom key store name {} is already in use",
+                    request.custom_key_store_name
+                )));
+            }
+            if entry.cloud_hsm_cluster_id.as_deref() == Some(&request.cloud_hsm_cluster_id) {
+                return Err(KmsRsError::CloudHsmClusterInUse(format!(
+                    "CloudHSM cluster {} is already associated with a custom key store",
+                    request.cloud_hsm_cluster_id
+                )));
+            }
+        }
+
+        let custom_key_store_id = format!("cks-{}", &random_hex(9)[..17]);
+        store.custom_key_stores.push(StoredCustomKeyStore {
+            entry: CustomKeyStoresListEntry {
+                cloud_hsm_cluster_id: Some(request.cloud_hsm_cluster_id),
+                connection_error_code: None,
+                connection_state: Some("DISCONNECTED".to_string()),
+                creation_date: Some(now()),
+                custom_key_store_id: Some(custom_key_store_id.clone()),
+                custom_key_store_name: Some(request.custom_key_store_name),
+                trust_anchor_certificate: Some(request.trust_anchor_certificate),
+            },
+            key_store_password: request.key_store_password,
+        });
+        Ok(CreateCustomKeyStoreResponse {
+            custom_key_store_id: Some(custom_key_store_id),
+        })
+    }
+
+    async fn describe_custom_key_stores(
+        &self,
+        request: DescribeCustomKeyStoresRequest,
+    ) -> Result<DescribeCustomKeyStoresResponse, KmsRsError> {
+        let page = Page::new(request.limit, 100, 1000, &request.marker)?;
+
+        let mut store = self.store();
+        let entries: Vec<CustomKeyStoresListEntry> = store
+            .custom_key_stores
+            .iter()
+            .map(|custom_key_store| custom_key_store.entry.clone())
+            .filter(|entry| {
+                request
+                    .custom_key_store_id
+                    .as_ref()
+                    .is_none_or(|id| entry.custom_key_store_id.as_ref() == Some(id))
+                    && request
+                        .custom_key_store_name
+                        .as_ref()
+                        .is_none_or(|name| entry.custom_key_store_name.as_ref() == Some(name))
+            })
+            .collect();
+        if entries.is_empty() {
+            if let Some(name) = request
+                .custom_key_store_id
+                .as_ref()
+                .or(request.custom_key_store_name.as_ref())
+            {
+                return Err(KmsRsError::CustomKeyStoreNotFound(format!(
+                    "Custom key store {} does not exist",
+                    name
+                )));
+            }
+        }
+        let (entries, next_marker) = page.of(&entries);
+        let entries = entries.to_vec();
+
+        // The simulated cluster connects as soon as it has been seen connecting.
+        for entry in &entries {
+            if entry.connection_state.as_deref() == Some("CONNECTING") {
+                let custom_key_store_id = entry.custom_key_store_id.clone().unwrap_or_default();
+                self.set_connection_state(&mut store, &custom_key_store_id, "CONNECTED");
+            }
+        }
+        Ok(DescribeCustomKeyStoresResponse {
+            custom_key_stores: Some(entries),
+            truncated: Some(next_marker.is_some()),
+            next_marker,
+        })
+    }
+
+    async fn connect_custom_key_store(
+        &self,
+        request: ConnectCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let custom_key_store =
+            self.find_custom_key_store(&mut store, &request.custom_key_store_id)?;
+        match custom_key_store.entry.connection_state.as_deref() {
+            Some("DISCONNECTED") | Some("FAILED") => {
+                custom_key_store.entry.connection_error_code = None;
+                custom_key_store.entry.connection_state = Some("CONNECTING".to_string());
+                Ok(())
+            }
+            Some("CONNECTED") | Some("CONNECTING") => Ok(()),
+            state => Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                "Custom key store {} is {}",
+                request.custom_key_store_id,
+                state.unwrap_or_default()
+            ))),
+        }
+    }
+
+    async fn disconnect_custom_key_store(
+        &self,
+        request: DisconnectCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        self.find_custom_key_store(&mut store, &request.custom_key_store_id)?;
+        self.set_connection_state(&mut store, &request.custom_key_store_id, "DISCONNECTED");
+        Ok(())
+    }
+
+    async fn update_custom_key_store(
+        &self,
+        request: UpdateCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        if let Some(key_store_password) = &request.key_store_password {
+            check_key_store_password(key_store_password)?;
+        }
+        let mut store = self.store();
+        if let Some(name) = &request.new_custom_key_store_name {
+            let in_use = store.custom_key_stores.iter().any(|custom_key_store| {
+                custom_key_store.entry.custom_key_store_name.as_ref() == Some(name)
+                    && custom_key_store.entry.custom_key_store_id.as_ref()
+                        != Some(&request.custom_key_store_id)
+            });
+            if in_use {
+                return Err(KmsRsError::CustomKeyStoreNameInUse(format!(
+                    "Custom key store name {} is already in use",
+                    name
+                )));
+            }
+        }
+        let custom_key_store =
+            self.find_custom_key_store(&mut store, &request.custom_key_store_id)?;
+        if custom_key_store.entry.connection_state.as_deref() != Some("DISCONNECTED") {
+            return Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                "Custom key store {} must be disconnected to be updated",
+                request.custom_key_store_id
+            )));
+        }
+        if let Some(name) = request.new_custom_key_store_name {
+            custom_key_store.entry.custom_key_store_name = Some(name);
+        }
+        if let Some(key_store_password) = request.key_store_password {
+            custom_key_store.key_store_password = key_store_password;
+        }
+        if let Some(cloud_hsm_cluster_id) = request.cloud_hsm_cluster_id {
+            custom_key_store.entry.cloud_hsm_cluster_id = Some(cloud_hsm_cluster_id);
+        }
+        Ok(())
+    }
+
+    async fn delete_custom_key_store(
+        &self,
+        request: DeleteCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        let mut store = self.store();
+        let custom_key_store =
+            self.find_custom_key_store(&mut store, &request.custom_key_store_id)?;
+        if custom_key_store.entry.connection_state.as_deref() != Some("DISCONNECTED") {
+            return Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                "Custom key store {} must be disconnected to be deleted",
+                request.custom_key_store_id
+            )));
+        }
+        let has_keys = store.keys.iter().any(|key| {
+            key.metadata.custom_key_store_id.as_ref() == Some(&request.custom_key_store_id)
+        });
+        if has_keys {
+            return Err(KmsRsError::CustomKeyStoreHasCMKs(format!(
+                "Custom key store {} still has CMKs",
+                request.custom_key_store_id
+            )));
+        }
+        store.custom_key_stores.retain(|custom_key_store| {
+            custom_key_store.entry.custom_key_store_id.as_ref()
+                != Some(&request.custom_key_store_id)
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kms::KmsRs;
+    use crate::options::{
+        CreateKeyOptions, KeyFilter, ReEncryptOptions, UpdateCustomKeyStoreOptions,
+    };
+    use crate::policy::{PolicyLinter, Statement};
+    use futures::TryStreamExt;
+
+    fn context(value: &str) -> Option<HashMap<String, String>> {
+        let mut context = HashMap::new();
+        context.insert("Purpose".to_string(), value.to_string());
+        Some(context)
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_and_decrypt_with_context() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        let encrypted = kms
+            .encrypt(
+                key.key_id.clone(),
+                Bytes::from_static(b"secret"),
+                context("test"),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(encrypted.key_id, key.arn);
+
+        let decrypted = kms
+            .decrypt(
+                None,
+                encrypted.ciphertext_blob.clone(),
+                context("test"),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(decrypted.plaintext, Bytes::from_static(b"secret"));
+
+        match kms
+            .decrypt(
+                None,
+                encrypted.ciphertext_blob,
+                context("other"),
+                None,
+                None,
+            )
+            .await
+        {
+            Err(KmsRsError::InvalidCiphertext(_)) => (),
+            other => panic!("Expected an InvalidCiphertext error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_key_states() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_id = kms
+            .create_key(CreateKeyOptions::new())
+            .await
+            .unwrap()
+            .key_id;
+        let encrypt = || kms.encrypt(key_id.clone(), Bytes::from_static(b"x"), None, None, None);
+
+        kms.disable_key(&key_id).await.unwrap();
+        assert!(!kms.describe_key(&key_id).await.unwrap().enabled);
+        match encrypt().await {
+            Err(KmsRsError::Disabled(_)) => (),
+            other => panic!("Expected a Disabled error, got {:?}", other),
+        }
+
+        let scheduled = kms.schedule_key_deletion(key_id.clone(), 7).await.unwrap();
+        assert_eq!(scheduled.pending_window_in_days, Some(7));
+        assert!(kms
+            .describe_key(&key_id)
+            .await
+            .unwrap()
+            .is_pending_deletion());
+        match encrypt().await {
+            Err(KmsRsError::KmsInvalidState(_)) => (),
+            other => panic!("Expected a KmsInvalidState error, got {:?}", other),
+        }
+        match kms.enable_key(&key_id).await {
+            Err(KmsRsError::KmsInvalidState(_)) => (),
+            other => panic!("Expected a KmsInvalidState error, got {:?}", other),
+        }
+
+        kms.cancel_key_deletion(key_id.clone()).await.unwrap();
+        assert_eq!(
+            kms.describe_key(&key_id)
+                .await
+                .unwrap()
+                .key_state
+                .as_deref(),
+            Some("Disabled")
+        );
+        kms.enable_key(&key_id).await.unwrap();
+        encrypt().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_data_keys() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_id = kms
+            .create_key(CreateKeyOptions::new())
+            .await
+            .unwrap()
+            .key_id;
+        let data_key = kms
+            .generate_data_key(&key_id, Some("AES_256".to_string()), None, None, None)
+            .await
+            .unwrap();
+        let decrypted = kms
+            .decrypt(
+                Some(key_id.clone()),
+                data_key.ciphertext_blob,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(Some(decrypted.plaintext), data_key.plaintext);
+
+        let pair = kms
+            .generate_data_key_pair(&key_id, "ECC_NIST_P256".to_string(), None, None)
+            .await
+            .unwrap();
+        let private_key = kms
+            .decrypt(None, pair.private_key_ciphertext_blob, None, None, None)
+            .await
+            .unwrap()
+            .plaintext;
+        assert_eq!(Some(private_key), pair.private_key_plaintext);
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_id = kms
+            .create_key(
+                CreateKeyOptions::new()
+                    .key_usage("SIGN_VERIFY")
+                    .customer_master_key_spec("ECC_NIST_P256"),
+            )
+            .await
+            .unwrap()
+            .key_id;
+        let message = Bytes::from_static(b"message");
+        let signed = kms
+            .sign(
+                key_id.clone(),
+                message.clone(),
+                None,
+                "ECDSA_SHA_256".to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        let verified = kms
+            .verify(
+                key_id.clone(),
+                message,
+                None,
+                signed.signature.clone(),
+                "ECDSA_SHA_256".to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(verified.signature_valid);
+
+        match kms
+            .verify(
+                key_id.clone(),
+                Bytes::from_static(b"tampered"),
+                None,
+                signed.signature,
+                "ECDSA_SHA_256".to_string(),
+                None,
+            )
+            .await
+        {
+            Err(KmsRsError::KmsInvalidSignature(_)) => (),
+            other => panic!("Expected a KmsInvalidSignature error, got {:?}", other),
+        }
+
+        match kms
+            .encrypt(key_id, Bytes::from_static(b"x"), None, None, None)
+            .await
+        {
+            Err(KmsRsError::InvalidKeyUsage(_)) => (),
+            other => panic!("Expected an InvalidKeyUsage error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_keys_pages() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        for _ in 0..3 {
+            kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        }
+        let keys: Vec<_> = kms.key_stream(Some(2)).try_collect().await.unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(kms.list_keys().await.unwrap().keys, keys);
+    }
+
+    #[tokio::test]
+    async fn test_aliases() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let first = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        let second = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        kms.create_alias("alias/payments", &first.key_id)
+            .await
+            .unwrap();
+
+        let encrypted = kms
+            .encrypt(
+                "alias/payments".to_string(),
+                Bytes::from_static(b"secret"),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(encrypted.key_id, first.arn);
+        assert_eq!(kms.resolve_alias("payments").await.unwrap(), first);
+        assert_eq!(
+            kms.describe_key("arn:aws:kms:us-east-1:111122223333:alias/payments")
+                .await
+                .unwrap(),
+            first
+        );
+
+        kms.update_alias("alias/payments", &second.arn)
+            .await
+            .unwrap();
+        assert_eq!(kms.resolve_alias("payments").await.unwrap(), second);
+        assert!(kms
+            .list_aliases(Some(&first.key_id))
+            .await
+            .unwrap()
+            .aliases
+            .is_empty());
+        let aliases = kms
+            .list_aliases(Some(&second.key_id))
+            .await
+            .unwrap()
+            .aliases;
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(
+            aliases[0].alias_arn,
+            "arn:aws:kms:us-east-1:111122223333:alias/payments"
+        );
+        assert_eq!(
+            aliases[0].target_key_id.as_deref(),
+            Some(second.key_id.as_str())
+        );
+
+        match kms.create_alias("alias/payments", &first.key_id).await {
+            Err(KmsRsError::AlreadyExists(_)) => (),
+            other => panic!("Expected an AlreadyExists error, got {:?}", other),
+        }
+        match kms.create_alias("alias/aws/payments", &first.key_id).await {
+            Err(KmsRsError::InvalidAliasName(_)) => (),
+            other => panic!("Expected an InvalidAliasName error, got {:?}", other),
+        }
+        match kms.create_alias("payments", &first.key_id).await {
+            Err(KmsRsError::InvalidAliasName(_)) => (),
+            other => panic!("Expected an InvalidAliasName error, got {:?}", other),
+        }
+
+        kms.delete_alias("alias/payments").await.unwrap();
+        match kms.resolve_alias("payments").await {
+            Err(KmsRsError::NotFound(message)) => assert_eq!(
+                message,
+                "Alias arn:aws:kms:us-east-1:111122223333:alias/payments is not found."
+            ),
+            other => panic!("Expected a NotFound error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_key_policy() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        assert_eq!(
+            kms.get_key_policy(&key.key_id).await.unwrap(),
+            KeyPolicy::default_for_account("111122223333")
+        );
+        assert_eq!(
+            kms.list_key_policies(&key.key_id)
+                .await
+                .unwrap()
+                .policy_names,
+            vec!["default"]
+        );
+
+        let policy = KeyPolicy::default_for_account("111122223333").statement(
+            Statement::allow()
+                .principal("arn:aws:iam::111122223333:role/app")
+                .action("kms:Decrypt"),
+        );
+        kms.put_key_policy(&key.key_id, &policy, &PolicyLinter::new())
+            .await
+            .unwrap();
+        assert_eq!(kms.get_key_policy(&key.key_id).await.unwrap(), policy);
+
+        let locked_out = KeyPolicy::new().statement(
+            Statement::allow()
+                .principal("arn:aws:iam::111122223333:role/app")
+                .action("kms:*"),
+        );
+        match kms
+            .put_key_policy(&key.key_id, &locked_out, &PolicyLinter::new())
+            .await
+        {
+            Err(KmsRsError::UnsafePolicy(_)) => (),
+            other => panic!("Expected an UnsafePolicy error, got {:?}", other),
+        }
+        assert_eq!(kms.get_key_policy(&key.key_id).await.unwrap(), policy);
+
+        match kms
+            .create_key(CreateKeyOptions::new().policy("{not json"))
+            .await
+        {
+            Err(KmsRsError::MalformedPolicyDocument(_)) => (),
+            other => panic!("Expected a MalformedPolicyDocument error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tags_and_find_keys() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let tag = |key: &str, value: &str| crate::model::Tag {
+            tag_key: key.to_string(),
+            tag_value: value.to_string(),
+        };
+        let prod = kms
+            .create_key(CreateKeyOptions::new().tag("Environment", "prod"))
+            .await
+            .unwrap();
+        let dev = kms
+            .create_key(CreateKeyOptions::new().tag("Environment", "dev"))
+            .await
+            .unwrap();
+
+        kms.tag_resource(
+            &prod.key_id,
+            vec![tag("Owner", "payments"), tag("Environment", "production")],
+        )
+        .await
+        .unwrap();
+        let mut tags = kms.list_resource_tags(&prod.key_id).await.unwrap().tags;
+        tags.sort_by(|a, b| a.tag_key.cmp(&b.tag_key));
+        assert_eq!(
+            tags,
+            vec![tag("Environment", "production"), tag("Owner", "payments")]
+        );
+
+        kms.untag_resource(&dev.key_id, vec!["Environment".to_string()])
+            .await
+            .unwrap();
+        assert!(kms
+            .list_resource_tags(&dev.key_id)
+            .await
+            .unwrap()
+            .tags
+            .is_empty());
+
+        match kms
+            .tag_resource(&dev.key_id, vec![tag("aws:reserved", "x")])
+            .await
+        {
+            Err(KmsRsError::Tag(_)) => (),
+            other => panic!("Expected a Tag error, got {:?}", other),
+        }
+
+        let found = kms
+            .find_keys(&KeyFilter::new().tag("Environment", "production"))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key_id, prod.key_id);
+
+        let found = kms
+            .find_keys(&KeyFilter::new().without_tag("Environment"))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key_id, dev.key_id);
+
+        kms.disable_key(&dev.key_id).await.unwrap();
+        let found = kms
+            .find_keys(&KeyFilter::new().key_state("Enabled"))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key_id, prod.key_id);
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let rotated = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        let unrotated = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        let imported = kms
+            .create_key(CreateKeyOptions::new().origin("EXTERNAL"))
+            .await
+            .unwrap();
+        let signing = kms
+            .create_key(
+                CreateKeyOptions::new()
+                    .key_usage("SIGN_VERIFY")
+                    .customer_master_key_spec("ECC_NIST_P256"),
+            )
+            .await
+            .unwrap();
+
+        kms.enable_key_rotation(&rotated.key_id).await.unwrap();
+        assert!(
+            kms.get_key_rotation_status(&rotated.key_id)
+                .await
+                .unwrap()
+                .key_rotation_enabled
+        );
+        match kms.enable_key_rotation(&signing.key_id).await {
+            Err(KmsRsError::UnsupportedOperation(_)) => (),
+            other => panic!("Expected an UnsupportedOperation error, got {:?}", other),
+        }
+
+        let mut report: Vec<String> = kms
+            .keys_without_rotation()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|key| key.key_id)
+            .collect();
+        report.sort();
+        let mut expected = vec![unrotated.key_id.clone(), imported.key_id.clone()];
+        expected.sort();
+        assert_eq!(report, expected);
+
+        kms.disable_key(&unrotated.key_id).await.unwrap();
+        match kms.enable_key_rotation(&unrotated.key_id).await {
+            Err(KmsRsError::Disabled(_)) => (),
+            other => panic!("Expected a Disabled error, got {:?}", other),
+        }
+
+        kms.schedule_key_deletion(rotated.key_id.clone(), 7)
+            .await
+            .unwrap();
+        assert!(
+            !kms.get_key_rotation_status(&rotated.key_id)
+                .await
+                .unwrap()
+                .key_rotation_enabled
+        );
+        kms.cancel_key_deletion(rotated.key_id.clone())
+            .await
+            .unwrap();
+        assert!(
+            kms.get_key_rotation_status(&rotated.key_id)
+                .await
+                .unwrap()
+                .key_rotation_enabled
+        );
+    }
+
+    #[tokio::test]
+    async fn test_re_encrypt_all() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let old = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        let new = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+
+        let mut ciphertexts = Vec::new();
+        for plaintext in ["one", "two", "three"] {
+            let encrypted = kms
+                .encrypt(
+                    old.key_id.clone(),
+                    Bytes::from(plaintext),
+                    context("old"),
+                    None,
+                    None,
+                )
+                .await
+                .unwrap();
+            ciphertexts.push(encrypted.ciphertext_blob);
+        }
+        ciphertexts.insert(1, Bytes::from_static(b"not a ciphertext"));
+
+        let options = ReEncryptOptions::new(new.key_id.clone())
+            .source_encryption_context("Purpose", "old")
+            .destination_encryption_context("Purpose", "new");
+        let mut updates = Vec::new();
+        let results = kms
+            .re_encrypt_all(ciphertexts, &options, 2, |progress| updates.push(progress))
+            .await;
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(updates.len(), 4);
+        assert_eq!(updates.last().unwrap().completed, 4);
+        assert_eq!(updates.last().unwrap().failed, 1);
+        match &results[1] {
+            Err(KmsRsError::InvalidCiphertext(_)) => (),
+            other => panic!("Expected an InvalidCiphertext error, got {:?}", other),
+        }
+
+        let output = results[2].as_ref().unwrap();
+        assert_eq!(output.source_key_id, old.arn);
+        assert_eq!(output.key_id, new.arn);
+        let decrypted = kms
+            .decrypt(
+                None,
+                output.ciphertext_blob.clone(),
+                context("new"),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(decrypted.key_id, new.arn);
+        assert_eq!(decrypted.plaintext, Bytes::from_static(b"two"));
+
+        let wrong_source = ReEncryptOptions::new(new.key_id.clone())
+            .source_key_id(old.key_id.clone())
+            .source_encryption_context("Purpose", "new");
+        match kms
+            .re_encrypt(
+                results[0].as_ref().unwrap().ciphertext_blob.clone(),
+                &wrong_source,
+            )
+            .await
+        {
+            Err(KmsRsError::IncorrectKey(_)) => (),
+            other => panic!("Expected an IncorrectKey error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_key_material() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key = kms
+            .create_key(CreateKeyOptions::new().origin("EXTERNAL"))
+            .await
+            .unwrap();
+        assert_eq!(key.key_state.as_deref(), Some("PendingImport"));
+
+        let material = [7u8; 32];
+        kms.import_key_material(&key.key_id, &material, None)
+            .await
+            .unwrap();
+        let metadata = kms.describe_key(&key.key_id).await.unwrap();
+        assert_eq!(metadata.key_state.as_deref(), Some("Enabled"));
+        assert_eq!(
+            metadata.expiration_model.as_deref(),
+            Some("KEY_MATERIAL_DOES_NOT_EXPIRE")
+        );
+        let encrypted = kms
+            .encrypt(
+                key.key_id.clone(),
+                Bytes::from_static(b"secret"),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        kms.delete_imported_key_material(&key.key_id).await.unwrap();
+        match kms
+            .decrypt(None, encrypted.ciphertext_blob.clone(), None, None, None)
+            .await
+        {
+            Err(KmsRsError::KmsInvalidState(_)) => (),
+            other => panic!("Expected a KmsInvalidState error, got {:?}", other),
+        }
+
+        match kms.import_key_material(&key.key_id, &[8u8; 32], None).await {
+            Err(KmsRsError::IncorrectKeyMaterial(_)) => (),
+            other => panic!("Expected an IncorrectKeyMaterial error, got {:?}", other),
+        }
+        let expiration = Utc::now() + chrono::Duration::days(30);
+        kms.import_key_material(&key.key_id, &material, Some(expiration))
+            .await
+            .unwrap();
+        let metadata = kms.describe_key(&key.key_id).await.unwrap();
+        assert_eq!(
+            metadata.valid_to.map(|valid_to| valid_to.timestamp()),
+            Some(expiration.timestamp())
+        );
+        let decrypted = kms
+            .decrypt(None, encrypted.ciphertext_blob, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted.plaintext, Bytes::from_static(b"secret"));
+
+        let generated = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        match kms
+            .import_key_material(&generated.key_id, &material, None)
+            .await
+        {
+            Err(KmsRsError::UnsupportedOperation(_)) => (),
+            other => panic!("Expected an UnsupportedOperation error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_region_keys() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let primary = kms
+            .create_key(CreateKeyOptions::new().multi_region(true))
+            .await
+            .unwrap();
+        assert!(primary.key_id.starts_with("mrk-"));
+        assert!(primary.is_multi_region_primary());
+
+        let replica = kms
+            .replicate_key(&primary.key_id, &Region::EuWest1, None, Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(replica.key_id, primary.key_id);
+        assert!(replica.is_multi_region_replica());
+        assert_eq!(
+            replica.arn,
+            primary.arn.replace("us-east-1", "eu-west-1").as_str()
+        );
+        match kms
+            .replicate_key(&primary.key_id, &Region::EuWest1, None, Vec::new())
+            .await
+        {
+            Err(KmsRsError::AlreadyExists(_)) => (),
+            other => panic!("Expected an AlreadyExists error, got {:?}", other),
+        }
+
+        // The replica shares the key material of the primary key.
+        let eu_west_1 = kms.for_region(Region::EuWest1).unwrap();
+        let encrypted = kms
+            .encrypt(
+                primary.key_id.clone(),
+                Bytes::from_static(b"secret"),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let decrypted = eu_west_1
+            .decrypt(None, encrypted.ciphertext_blob, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted.plaintext, Bytes::from_static(b"secret"));
+
+        let replicas = kms.list_replicas(&primary.key_id).await.unwrap();
+        assert_eq!(
+            replicas,
+            vec![eu_west_1.describe_key(&primary.key_id).await.unwrap()]
+        );
+        let configuration = kms
+            .describe_key(&primary.key_id)
+            .await
+            .unwrap()
+            .multi_region_configuration
+            .unwrap();
+        assert_eq!(configuration.replica_keys.len(), 1);
+        assert_eq!(configuration.replica_keys[0].region, "eu-west-1");
+
+        kms.update_primary_region(&primary.key_id, &Region::EuWest1)
+            .await
+            .unwrap();
+        assert!(kms
+            .describe_key(&primary.key_id)
+            .await
+            .unwrap()
+            .is_multi_region_replica());
+        assert!(eu_west_1
+            .describe_key(&primary.key_id)
+            .await
+            .unwrap()
+            .is_multi_region_primary());
+        let replicas = eu_west_1.list_replicas(&primary.key_id).await.unwrap();
+        assert_eq!(replicas.len(), 1);
+        assert_eq!(replicas[0].arn, primary.arn);
+
+        let single_region = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        match kms
+            .replicate_key(&single_region.key_id, &Region::EuWest1, None, Vec::new())
+            .await
+        {
+            Err(KmsRsError::UnsupportedOperation(_)) => (),
+            other => panic!("Expected an UnsupportedOperation error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_key_stores() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let trust_anchor = "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+        let custom_key_store_id = kms
+            .create_custom_key_store(
+                "ExampleKeyStore",
+                "cluster-1a23b4cdefg",
+                trust_anchor,
+                "kmsPswd",
+            )
+            .await
+            .unwrap();
+        match kms
+            .create_custom_key_store("ExampleKeyStore", "cluster-2", trust_anchor, "kmsPswd")
+            .await
+        {
+            Err(KmsRsError::CustomKeyStoreNameInUse(_)) => (),
+            other => panic!("Expected a CustomKeyStoreNameInUse error, got {:?}", other),
+        }
+        let cloud_hsm_key = CreateKeyOptions::new()
+            .origin("AWS_CLOUDHSM")
+            .custom_key_store_id(custom_key_store_id.clone());
+        match kms.create_key(cloud_hsm_key.clone()).await {
+            Err(KmsRsError::CustomKeyStoreInvalidState(_)) => (),
+            other => panic!(
+                "Expected a CustomKeyStoreInvalidState error, got {:?}",
+                other
+            ),
+        }
+
+        kms.connect_custom_key_store(&custom_key_store_id)
+            .await
+            .unwrap();
+        let custom_key_store = kms
+            .wait_for_custom_key_store(
+                &custom_key_store_id,
+                std::time::Duration::from_millis(1),
+                std::time::Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+        assert!(custom_key_store.is_connected());
+        assert_eq!(
+            custom_key_store.cloud_hsm_cluster_id.as_deref(),
+            Some("cluster-1a23b4cdefg")
+        );
+
+        let key = kms.create_key(cloud_hsm_key).await.unwrap();
+        assert_eq!(key.origin.as_deref(), Some("AWS_CLOUDHSM"));
+        assert_eq!(
+            key.custom_key_store_id.as_deref(),
+            Some(custom_key_store_id.as_str())
+        );
+        assert_eq!(
+            kms.generate_random(16, Some(custom_key_store_id.clone()))
+                .await
+                .unwrap()
+                .len(),
+            16
+        );
+
+        kms.disconnect_custom_key_store(&custom_key_store_id)
+            .await
+            .unwrap();
+        let metadata = kms.describe_key(&key.key_id).await.unwrap();
+        assert_eq!(metadata.key_state.as_deref(), Some("Unavailable"));
+        kms.update_custom_key_store(
+            &custom_key_store_id,
+            UpdateCustomKeyStoreOptions::new().new_custom_key_store_name("Renamed"),
+        )
+        .await
+        .unwrap();
+        let stores = kms.describe_custom_key_stores().await.unwrap();
+        assert_eq!(stores.custom_key_stores.len(), 1);
+        assert_eq!(stores.custom_key_stores[0].custom_key_store_name, "Renamed");
+
+        match kms.delete_custom_key_store(&custom_key_store_id).await {
+            Err(KmsRsError::CustomKeyStoreHasCMKs(_)) => (),
+            other => panic!("Expected a CustomKeyStoreHasCMKs error, got {:?}", other),
+        }
+
+        let empty_store_id = kms
+            .create_custom_key_store("EmptyKeyStore", "cluster-2", trust_anchor, "kmsPswd")
+            .await
+            .unwrap();
+        kms.delete_custom_key_store(&empty_store_id).await.unwrap();
+        match kms.describe_custom_key_store(&empty_store_id).await {
+            Err(KmsRsError::CustomKeyStoreNotFound(_)) => (),
+            other => panic!("Expected a CustomKeyStoreNotFound error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_key() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        match kms.describe_key("1234abcd-12ab-34cd-56ef-1234567890ab").await {
+            Err(KmsRsError::NotFound(message)) => assert_eq!(
+                message,
+                "Key 'arn:aws:kms:us-east-1:111122223333:key/1234abcd-12ab-34cd-56ef-1234567890ab' does not exist"
+            ),
+            other => panic!("Expected a NotFound error, got {:?}", other),
+        }
+    }
+}
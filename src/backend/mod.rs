@@ -0,0 +1,241 @@
+//! The transport behind [`crate::KmsRs`]: every KMS operation the crate exposes, as a trait.
+//!
+//! [`KmsRs::new`](crate::KmsRs::new) and the builder use the rusoto `KmsClient`, which calls AWS.
+//! [`InMemoryKms`] implements the operations locally so code built on `kms_rs` can be tested offline,
+//! and any other implementation can be plugged in with [`KmsRsBuilder::backend`](crate::KmsRsBuilder::backend).
+//!
+//! Requests and responses are the `rusoto_kms` types, i.e. the KMS wire format.
+
+use async_trait::async_trait;
+use rusoto_core::Region;
+use rusoto_kms::{
+    CancelKeyDeletionRequest, CancelKeyDeletionResponse, ConnectCustomKeyStoreRequest,
+    CreateAliasRequest, CreateCustomKeyStoreRequest, CreateCustomKeyStoreResponse,
+    CreateGrantRequest, CreateGrantResponse, CreateKeyRequest, CreateKeyResponse, DecryptRequest,
+    DecryptResponse, DeleteAliasRequest, DeleteCustomKeyStoreRequest,
+    DeleteImportedKeyMaterialRequest, DescribeCustomKeyStoresRequest,
+    DescribeCustomKeyStoresResponse, DescribeKeyRequest, DescribeKeyResponse, DisableKeyRequest,
+    DisableKeyRotationRequest, DisconnectCustomKeyStoreRequest, EnableKeyRequest,
+    EnableKeyRotationRequest, EncryptRequest, EncryptResponse, GenerateDataKeyPairRequest,
+    GenerateDataKeyPairResponse, GenerateDataKeyPairWithoutPlaintextRequest,
+    GenerateDataKeyPairWithoutPlaintextResponse, GenerateDataKeyRequest, GenerateDataKeyResponse,
+    GenerateDataKeyWithoutPlaintextRequest, GenerateDataKeyWithoutPlaintextResponse,
+    GenerateRandomRequest, GenerateRandomResponse, GetKeyPolicyRequest, GetKeyPolicyResponse,
+    GetKeyRotationStatusRequest, GetKeyRotationStatusResponse, GetParametersForImportRequest,
+    GetParametersForImportResponse, GetPublicKeyRequest, GetPublicKeyResponse,
+    ImportKeyMaterialRequest, ListAliasesRequest, ListAliasesResponse, ListGrantsRequest,
+    ListGrantsResponse, ListKeyPoliciesRequest, ListKeyPoliciesResponse, ListKeysRequest,
+    ListKeysResponse, ListResourceTagsRequest, ListResourceTagsResponse,
+    ListRetirableGrantsRequest, PutKeyPolicyRequest, ReEncryptRequest, ReEncryptResponse,
+    ReplicateKeyRequest, ReplicateKeyResponse, RetireGrantRequest, RevokeGrantRequest,
+    ScheduleKeyDeletionRequest, ScheduleKeyDeletionResponse, SignRequest, SignResponse,
+    TagResourceRequest, UntagResourceRequest, UpdateAliasRequest, UpdateCustomKeyStoreRequest,
+    UpdatePrimaryRegionRequest, VerifyRequest, VerifyResponse,
+};
+
+use std::sync::Arc;
+
+use crate::error::KmsRsError;
+
+mod memory;
+mod rusoto;
+
+pub use memory::InMemoryKms;
+
+/// The KMS operations used by [`crate::KmsRs`]. Implementations fail with the [`KmsRsError`] variant
+/// matching the exception KMS would return.
+#[async_trait]
+pub trait KmsBackend: Send + Sync {
+    /// The same KMS in another Region, used by [`crate::KmsRs::for_region`]. Backends bound to a single
+    /// Region keep the default, which fails with [`KmsRsError::UnsupportedOperation`].
+    fn for_region(&self, region: &Region) -> Result<Arc<dyn KmsBackend>, KmsRsError> {
+        Err(KmsRsError::UnsupportedOperation(format!(
+            "This backend can't switch to Region {}",
+            region.name()
+        )))
+    }
+
+    async fn describe_key(
+        &self,
+        request: DescribeKeyRequest,
+    ) -> Result<DescribeKeyResponse, KmsRsError>;
+
+    async fn list_keys(&self, request: ListKeysRequest) -> Result<ListKeysResponse, KmsRsError>;
+
+    async fn create_key(&self, request: CreateKeyRequest) -> Result<CreateKeyResponse, KmsRsError>;
+
+    async fn create_alias(&self, request: CreateAliasRequest) -> Result<(), KmsRsError>;
+
+    async fn update_alias(&self, request: UpdateAliasRequest) -> Result<(), KmsRsError>;
+
+    async fn delete_alias(&self, request: DeleteAliasRequest) -> Result<(), KmsRsError>;
+
+    async fn list_aliases(
+        &self,
+        request: ListAliasesRequest,
+    ) -> Result<ListAliasesResponse, KmsRsError>;
+
+    async fn create_grant(
+        &self,
+        request: CreateGrantRequest,
+    ) -> Result<CreateGrantResponse, KmsRsError>;
+
+    async fn list_grants(
+        &self,
+        request: ListGrantsRequest,
+    ) -> Result<ListGrantsResponse, KmsRsError>;
+
+    async fn list_retirable_grants(
+        &self,
+        request: ListRetirableGrantsRequest,
+    ) -> Result<ListGrantsResponse, KmsRsError>;
+
+    async fn retire_grant(&self, request: RetireGrantRequest) -> Result<(), KmsRsError>;
+
+    async fn revoke_grant(&self, request: RevokeGrantRequest) -> Result<(), KmsRsError>;
+
+    async fn get_key_policy(
+        &self,
+        request: GetKeyPolicyRequest,
+    ) -> Result<GetKeyPolicyResponse, KmsRsError>;
+
+    async fn put_key_policy(&self, request: PutKeyPolicyRequest) -> Result<(), KmsRsError>;
+
+    async fn list_key_policies(
+        &self,
+        request: ListKeyPoliciesRequest,
+    ) -> Result<ListKeyPoliciesResponse, KmsRsError>;
+
+    async fn tag_resource(&self, request: TagResourceRequest) -> Result<(), KmsRsError>;
+
+    async fn untag_resource(&self, request: UntagResourceRequest) -> Result<(), KmsRsError>;
+
+    async fn list_resource_tags(
+        &self,
+        request: ListResourceTagsRequest,
+    ) -> Result<ListResourceTagsResponse, KmsRsError>;
+
+    async fn schedule_key_deletion(
+        &self,
+        request: ScheduleKeyDeletionRequest,
+    ) -> Result<ScheduleKeyDeletionResponse, KmsRsError>;
+
+    async fn cancel_key_deletion(
+        &self,
+        request: CancelKeyDeletionRequest,
+    ) -> Result<CancelKeyDeletionResponse, KmsRsError>;
+
+    async fn replicate_key(
+        &self,
+        request: ReplicateKeyRequest,
+    ) -> Result<ReplicateKeyResponse, KmsRsError>;
+
+    async fn update_primary_region(
+        &self,
+        request: UpdatePrimaryRegionRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn enable_key(&self, request: EnableKeyRequest) -> Result<(), KmsRsError>;
+
+    async fn disable_key(&self, request: DisableKeyRequest) -> Result<(), KmsRsError>;
+
+    async fn get_parameters_for_import(
+        &self,
+        request: GetParametersForImportRequest,
+    ) -> Result<GetParametersForImportResponse, KmsRsError>;
+
+    async fn import_key_material(
+        &self,
+        request: ImportKeyMaterialRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn delete_imported_key_material(
+        &self,
+        request: DeleteImportedKeyMaterialRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn enable_key_rotation(
+        &self,
+        request: EnableKeyRotationRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn disable_key_rotation(
+        &self,
+        request: DisableKeyRotationRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn get_key_rotation_status(
+        &self,
+        request: GetKeyRotationStatusRequest,
+    ) -> Result<GetKeyRotationStatusResponse, KmsRsError>;
+
+    async fn generate_data_key(
+        &self,
+        request: GenerateDataKeyRequest,
+    ) -> Result<GenerateDataKeyResponse, KmsRsError>;
+
+    async fn generate_data_key_without_plaintext(
+        &self,
+        request: GenerateDataKeyWithoutPlaintextRequest,
+    ) -> Result<GenerateDataKeyWithoutPlaintextResponse, KmsRsError>;
+
+    async fn generate_data_key_pair(
+        &self,
+        request: GenerateDataKeyPairRequest,
+    ) -> Result<GenerateDataKeyPairResponse, KmsRsError>;
+
+    async fn generate_data_key_pair_without_plaintext(
+        &self,
+        request: GenerateDataKeyPairWithoutPlaintextRequest,
+    ) -> Result<GenerateDataKeyPairWithoutPlaintextResponse, KmsRsError>;
+
+    async fn encrypt(&self, request: EncryptRequest) -> Result<EncryptResponse, KmsRsError>;
+
+    async fn decrypt(&self, request: DecryptRequest) -> Result<DecryptResponse, KmsRsError>;
+
+    async fn re_encrypt(&self, request: ReEncryptRequest) -> Result<ReEncryptResponse, KmsRsError>;
+
+    async fn sign(&self, request: SignRequest) -> Result<SignResponse, KmsRsError>;
+
+    async fn verify(&self, request: VerifyRequest) -> Result<VerifyResponse, KmsRsError>;
+
+    async fn get_public_key(
+        &self,
+        request: GetPublicKeyRequest,
+    ) -> Result<GetPublicKeyResponse, KmsRsError>;
+
+    async fn generate_random(
+        &self,
+        request: GenerateRandomRequest,
+    ) -> Result<GenerateRandomResponse, KmsRsError>;
+
+    async fn create_custom_key_store(
+        &self,
+        request: CreateCustomKeyStoreRequest,
+    ) -> Result<CreateCustomKeyStoreResponse, KmsRsError>;
+
+    async fn describe_custom_key_stores(
+        &self,
+        request: DescribeCustomKeyStoresRequest,
+    ) -> Result<DescribeCustomKeyStoresResponse, KmsRsError>;
+
+    async fn connect_custom_key_store(
+        &self,
+        request: ConnectCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn disconnect_custom_key_store(
+        &self,
+        request: DisconnectCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn update_custom_key_store(
+        &self,
+        request: UpdateCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError>;
+
+    async fn delete_custom_key_store(
+        &self,
+        request: DeleteCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError>;
+}
@@ -0,0 +1,316 @@
+//! The default backend: AWS KMS through rusoto.
+
+use async_trait::async_trait;
+use rusoto_kms::{
+    CancelKeyDeletionRequest, CancelKeyDeletionResponse, ConnectCustomKeyStoreRequest,
+    CreateAliasRequest, CreateCustomKeyStoreRequest, CreateCustomKeyStoreResponse,
+    CreateGrantRequest, CreateGrantResponse, CreateKeyRequest, CreateKeyResponse, DecryptRequest,
+    DecryptResponse, DeleteAliasRequest, DeleteCustomKeyStoreRequest,
+    DeleteImportedKeyMaterialRequest, DescribeCustomKeyStoresRequest,
+    DescribeCustomKeyStoresResponse, DescribeKeyRequest, DescribeKeyResponse, DisableKeyRequest,
+    DisableKeyRotationRequest, DisconnectCustomKeyStoreRequest, EnableKeyRequest,
+    EnableKeyRotationRequest, EncryptRequest, EncryptResponse, GenerateDataKeyPairRequest,
+    GenerateDataKeyPairResponse, GenerateDataKeyPairWithoutPlaintextRequest,
+    GenerateDataKeyPairWithoutPlaintextResponse, GenerateDataKeyRequest, GenerateDataKeyResponse,
+    GenerateDataKeyWithoutPlaintextRequest, GenerateDataKeyWithoutPlaintextResponse,
+    GenerateRandomRequest, GenerateRandomResponse, GetKeyPolicyRequest, GetKeyPolicyResponse,
+    GetKeyRotationStatusRequest, GetKeyRotationStatusResponse, GetParametersForImportRequest,
+    GetParametersForImportResponse, GetPublicKeyRequest, GetPublicKeyResponse,
+    ImportKeyMaterialRequest, Kms, KmsClient, ListAliasesRequest, ListAliasesResponse,
+    ListGrantsRequest, ListGrantsResponse, ListKeyPoliciesRequest, ListKeyPoliciesResponse,
+    ListKeysRequest, ListKeysResponse, ListResourceTagsRequest, ListResourceTagsResponse,
+    ListRetirableGrantsRequest, PutKeyPolicyRequest, ReEncryptRequest, ReEncryptResponse,
+    ReplicateKeyRequest, ReplicateKeyResponse, RetireGrantRequest, RevokeGrantRequest,
+    ScheduleKeyDeletionRequest, ScheduleKeyDeletionResponse, SignRequest, SignResponse,
+    TagResourceRequest, UntagResourceRequest, UpdateAliasRequest, UpdateCustomKeyStoreRequest,
+    UpdatePrimaryRegionRequest, VerifyRequest, VerifyResponse,
+};
+
+use super::KmsBackend;
+use crate::error::KmsRsError;
+
+#[async_trait]
+impl KmsBackend for KmsClient {
+    async fn describe_key(
+        &self,
+        request: DescribeKeyRequest,
+    ) -> Result<DescribeKeyResponse, KmsRsError> {
+        Ok(Kms::describe_key(self, request).await?)
+    }
+
+    async fn list_keys(&self, request: ListKeysRequest) -> Result<ListKeysResponse, KmsRsError> {
+        Ok(Kms::list_keys(self, request).await?)
+    }
+
+    async fn create_key(&self, request: CreateKeyRequest) -> Result<CreateKeyResponse, KmsRsError> {
+        Ok(Kms::create_key(self, request).await?)
+    }
+
+    async fn create_alias(&self, request: CreateAliasRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::create_alias(self, request).await?)
+    }
+
+    async fn update_alias(&self, request: UpdateAliasRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::update_alias(self, request).await?)
+    }
+
+    async fn delete_alias(&self, request: DeleteAliasRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::delete_alias(self, request).await?)
+    }
+
+    async fn list_aliases(
+        &self,
+        request: ListAliasesRequest,
+    ) -> Result<ListAliasesResponse, KmsRsError> {
+        Ok(Kms::list_aliases(self, request).await?)
+    }
+
+    async fn create_grant(
+        &self,
+        request: CreateGrantRequest,
+    ) -> Result<CreateGrantResponse, KmsRsError> {
+        Ok(Kms::create_grant(self, request).await?)
+    }
+
+    async fn list_grants(
+        &self,
+        request: ListGrantsRequest,
+    ) -> Result<ListGrantsResponse, KmsRsError> {
+        Ok(Kms::list_grants(self, request).await?)
+    }
+
+    async fn list_retirable_grants(
+        &self,
+        request: ListRetirableGrantsRequest,
+    ) -> Result<ListGrantsResponse, KmsRsError> {
+        Ok(Kms::list_retirable_grants(self, request).await?)
+    }
+
+    async fn retire_grant(&self, request: RetireGrantRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::retire_grant(self, request).await?)
+    }
+
+    async fn revoke_grant(&self, request: RevokeGrantRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::revoke_grant(self, request).await?)
+    }
+
+    async fn get_key_policy(
+        &self,
+        request: GetKeyPolicyRequest,
+    ) -> Result<GetKeyPolicyResponse, KmsRsError> {
+        Ok(Kms::get_key_policy(self, request).await?)
+    }
+
+    async fn put_key_policy(&self, request: PutKeyPolicyRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::put_key_policy(self, request).await?)
+    }
+
+    async fn list_key_policies(
+        &self,
+        request: ListKeyPoliciesRequest,
+    ) -> Result<ListKeyPoliciesResponse, KmsRsError> {
+        Ok(Kms::list_key_policies(self, request).await?)
+    }
+
+    async fn tag_resource(&self, request: TagResourceRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::tag_resource(self, request).await?)
+    }
+
+    async fn untag_resource(&self, request: UntagResourceRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::untag_resource(self, request).await?)
+    }
+
+    async fn list_resource_tags(
+        &self,
+        request: ListResourceTagsRequest,
+    ) -> Result<ListResourceTagsResponse, KmsRsError> {
+        Ok(Kms::list_resource_tags(self, request).await?)
+    }
+
+    async fn schedule_key_deletion(
+        &self,
+        request: ScheduleKeyDeletionRequest,
+    ) -> Result<ScheduleKeyDeletionResponse, KmsRsError> {
+        Ok(Kms::schedule_key_deletion(self, request).await?)
+    }
+
+    async fn cancel_key_deletion(
+        &self,
+        request: CancelKeyDeletionRequest,
+    ) -> Result<CancelKeyDeletionResponse, KmsRsError> {
+        Ok(Kms::cancel_key_deletion(self, request).await?)
+    }
+
+    async fn replicate_key(
+        &self,
+        request: ReplicateKeyRequest,
+    ) -> Result<ReplicateKeyResponse, KmsRsError> {
+        Ok(Kms::replicate_key(self, request).await?)
+    }
+
+    async fn update_primary_region(
+        &self,
+        request: UpdatePrimaryRegionRequest,
+    ) -> Result<(), KmsRsError> {
+        Ok(Kms::update_primary_region(self, request).await?)
+    }
+
+    async fn enable_key(&self, request: EnableKeyRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::enable_key(self, request).await?)
+    }
+
+    async fn disable_key(&self, request: DisableKeyRequest) -> Result<(), KmsRsError> {
+        Ok(Kms::disable_key(self, request).await?)
+    }
+
+    async fn get_parameters_for_import(
+        &self,
+        request: GetParametersForImportRequest,
+    ) -> Result<GetParametersForImportResponse, KmsRsError> {
+        Ok(Kms::get_parameters_for_import(self, request).await?)
+    }
+
+    async fn import_key_material(
+        &self,
+        request: ImportKeyMaterialRequest,
+    ) -> Result<(), KmsRsError> {
+        Kms::import_key_material(self, request).await?; // AWS gives an empty response
+        Ok(())
+    }
+
+    async fn delete_imported_key_material(
+        &self,
+        request: DeleteImportedKeyMaterialRequest,
+    ) -> Result<(), KmsRsError> {
+        Ok(Kms::delete_imported_key_material(self, request).await?)
+    }
+
+    async fn enable_key_rotation(
+        &self,
+        request: EnableKeyRotationRequest,
+    ) -> Result<(), KmsRsError> {
+        Ok(Kms::enable_key_rotation(self, request).await?)
+    }
+
+    async fn disable_key_rotation(
+        &self,
+        request: DisableKeyRotationRequest,
+    ) -> Result<(), KmsRsError> {
+        Ok(Kms::disable_key_rotation(self, request).await?)
+    }
+
+    async fn get_key_rotation_status(
+        &self,
+        request: GetKeyRotationStatusRequest,
+    ) -> Result<GetKeyRotationStatusResponse, KmsRsError> {
+        Ok(Kms::get_key_rotation_status(self, request).await?)
+    }
+
+    async fn generate_data_key(
+        &self,
+        request: GenerateDataKeyRequest,
+    ) -> Result<GenerateDataKeyResponse, KmsRsError> {
+        Ok(Kms::generate_data_key(self, request).await?)
+    }
+
+    async fn generate_data_key_without_plaintext(
+        &self,
+        request: GenerateDataKeyWithoutPlaintextRequest,
+    ) -> Result<GenerateDataKeyWithoutPlaintextResponse, KmsRsError> {
+        Ok(Kms::generate_data_key_without_plaintext(self, request).await?)
+    }
+
+    async fn generate_data_key_pair(
+        &self,
+        request: GenerateDataKeyPairRequest,
+    ) -> Result<GenerateDataKeyPairResponse, KmsRsError> {
+        Ok(Kms::generate_data_key_pair(self, request).await?)
+    }
+
+    async fn generate_data_key_pair_without_plaintext(
+        &self,
+        request: GenerateDataKeyPairWithoutPlaintextRequest,
+    ) -> Result<GenerateDataKeyPairWithoutPlaintextResponse, KmsRsError> {
+        Ok(Kms::generate_data_key_pair_without_plaintext(self, request).await?)
+    }
+
+    async fn encrypt(&self, request: EncryptRequest) -> Result<EncryptResponse, KmsRsError> {
+        Ok(Kms::encrypt(self, request).await?)
+    }
+
+    async fn decrypt(&self, request: DecryptRequest) -> Result<DecryptResponse, KmsRsError> {
+        Ok(Kms::decrypt(self, request).await?)
+    }
+
+    async fn re_encrypt(&self, request: ReEncryptRequest) -> Result<ReEncryptResponse, KmsRsError> {
+        Ok(Kms::re_encrypt(self, request).await?)
+    }
+
+    async fn sign(&self, request: SignRequest) -> Result<SignResponse, KmsRsError> {
+        Ok(Kms::sign(self, request).await?)
+    }
+
+    async fn verify(&self, request: VerifyRequest) -> Result<VerifyResponse, KmsRsError> {
+        Ok(Kms::verify(self, request).await?)
+    }
+
+    async fn get_public_key(
+        &self,
+        request: GetPublicKeyRequest,
+    ) -> Result<GetPublicKeyResponse, KmsRsError> {
+        Ok(Kms::get_public_key(self, request).await?)
+    }
+
+    async fn generate_random(
+        &self,
+        request: GenerateRandomRequest,
+    ) -> Result<GenerateRandomResponse, KmsRsError> {
+        Ok(Kms::generate_random(self, request).await?)
+    }
+
+    async fn create_custom_key_store(
+        &self,
+        request: CreateCustomKeyStoreRequest,
+    ) -> Result<CreateCustomKeyStoreResponse, KmsRsError> {
+        Ok(Kms::create_custom_key_store(self, request).await?)
+    }
+
+    async fn describe_custom_key_stores(
+        &self,
+        request: DescribeCustomKeyStoresRequest,
+    ) -> Result<DescribeCustomKeyStoresResponse, KmsRsError> {
+        Ok(Kms::describe_custom_key_stores(self, request).await?)
+    }
+
+    async fn connect_custom_key_store(
+        &self,
+        request: ConnectCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        Kms::connect_custom_key_store(self, request).await?;
+        Ok(())
+    }
+
+    async fn disconnect_custom_key_store(
+        &self,
+        request: DisconnectCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        Kms::disconnect_custom_key_store(self, request).await?;
+        Ok(())
+    }
+
+    async fn update_custom_key_store(
+        &self,
+        request: UpdateCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        Kms::update_custom_key_store(self, request).await?;
+        Ok(())
+    }
+
+    async fn delete_custom_key_store(
+        &self,
+        request: DeleteCustomKeyStoreRequest,
+    ) -> Result<(), KmsRsError> {
+        Kms::delete_custom_key_store(self, request).await?;
+        Ok(())
+    }
+}
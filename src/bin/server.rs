@@ -0,0 +1,43 @@
+//! Runs a local AWS KMS-compatible server backed by an in-memory keystore.
+//!
+//! ```text
+//! kms-rs-server [--bind <address>]
+//! ```
+//!
+//! Listens on 127.0.0.1:4599 by default. Keys only live as long as the process.
+
+use kms_rs::server::KmsServer;
+use kms_rs::{InMemoryKms, Region};
+use std::net::SocketAddr;
+use std::process;
+
+#[tokio::main]
+async fn main() {
+    let mut addr: SocketAddr = ([127, 0, 0, 1], 4599).into();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--bind", Some(value)) => match value.parse() {
+                Ok(value) => addr = value,
+                Err(err) => exit(&format!("Invalid address {}: {}", value, err)),
+            },
+            _ => exit("Usage: kms-rs-server [--bind <address>]"),
+        }
+    }
+
+    let keystore = InMemoryKms::with_account(Region::default(), "111122223333");
+    match KmsServer::new(keystore).bind(addr) {
+        Ok((addr, server)) => {
+            println!("Listening on http://{}", addr);
+            if let Err(err) = server.await {
+                exit(&err.to_string());
+            }
+        }
+        Err(err) => exit(&err.to_string()),
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
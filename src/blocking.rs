@@ -0,0 +1,1186 @@
+//! Blocking wrappers around the async API, for callers that are not running inside a tokio runtime.
+//!
+//! Every call is driven on a single multi-threaded runtime that is created lazily on first use and then
+//! reused. These functions must not be called from within an async context (they would block the
+//! executor and panic); use the async methods on [`crate::KmsRs`] there instead.
+//!
+//! The free functions use a `KmsRs::default()` client, created on first use.
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::{Stream, StreamExt};
+use rusoto_core::Region;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Read;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::runtime::Runtime;
+
+use crate::error::KmsRsError;
+use crate::grant::GrantGuard;
+use crate::model::{
+    AliasListEntry, CancelKeyDeletionOutput, CreateGrantOutput, CustomKeyStore, DataKey,
+    DataKeyPair, DecryptOutput, DescribeCustomKeyStoresOutput, EncryptOutput,
+    GetKeyRotationStatusOutput, KeyListEntry, KeyMetadata, ListAliasesOutput, ListGrantsOutput,
+    ListKeyPoliciesOutput, ListKeysOutput, ListResourceTagsOutput, PublicKey, ReEncryptOutput,
+    ReEncryptProgress, ScheduleKeyDeletionOutput, SignOutput, Tag, VerifyOutput,
+};
+use crate::options::{
+    CreateGrantOptions, CreateKeyOptions, KeyFilter, ReEncryptOptions, UpdateCustomKeyStoreOptions,
+};
+use crate::policy::{KeyPolicy, PolicyLinter};
+
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| Runtime::new().expect("Failed to create Tokio runtime"))
+        .block_on(future)
+}
+
+fn default_client() -> &'static KmsRs {
+    static CLIENT: OnceLock<KmsRs> = OnceLock::new();
+    CLIENT.get_or_init(KmsRs::default)
+}
+
+/// A blocking [`crate::KmsRs`]: the same operations, each driven to completion on the shared runtime.
+#[derive(Clone, Default)]
+pub struct KmsRs {
+    inner: crate::KmsRs,
+}
+
+impl KmsRs {
+    /// Wraps a configured async client.
+    pub fn new(inner: crate::KmsRs) -> KmsRs {
+        KmsRs { inner }
+    }
+
+    /// The async client this wrapper drives.
+    pub fn get_ref(&self) -> &crate::KmsRs {
+        &self.inner
+    }
+
+    /// A client for another Region, configured like this one.
+    pub fn for_region(&self, region: Region) -> Result<KmsRs, KmsRsError> {
+        Ok(KmsRs::new(self.inner.for_region(region)?))
+    }
+
+    /// Gets the list of all Customer Master Keys (CMKs) in current AWS account and Region.
+    pub fn list_keys(&self) -> Result<ListKeysOutput, KmsRsError> {
+        block_on(self.inner.list_keys())
+    }
+
+    /// Iterates over every Customer Master Key (CMK) in current AWS account and Region, requesting `page_size` keys (between 1 and 1000, defaults to 100) at a time.
+    pub fn keys(&self, page_size: Option<i64>) -> Keys {
+        Keys {
+            stream: Box::pin(self.inner.key_stream(page_size)),
+        }
+    }
+
+    /// Provides detailed information about a customer master key (CMK).
+    pub fn describe_key(&self, key_id: &str) -> Result<KeyMetadata, KmsRsError> {
+        block_on(self.inner.describe_key(key_id))
+    }
+
+    /// Creates a friendly name (e.g. `alias/prod/payments`) for a customer master key (CMK).
+    pub fn create_alias(&self, alias_name: &str, target_key_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.create_alias(alias_name, target_key_id))
+    }
+
+    /// Associates an existing alias with a different customer master key (CMK).
+    pub fn update_alias(&self, alias_name: &str, target_key_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.update_alias(alias_name, target_key_id))
+    }
+
+    /// Deletes an alias. The customer master key (CMK) it refers to is not affected.
+    pub fn delete_alias(&self, alias_name: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.delete_alias(alias_name))
+    }
+
+    /// Gets the list of all aliases in current AWS account and Region, or only those of the CMK `key_id`.
+    pub fn list_aliases(&self, key_id: Option<&str>) -> Result<ListAliasesOutput, KmsRsError> {
+        block_on(self.inner.list_aliases(key_id))
+    }
+
+    /// Iterates over every alias in current AWS account and Region, or only those of the CMK `key_id`, requesting `page_size` aliases (between 1 and 100, defaults to 50) at a time.
+    pub fn aliases(&self, key_id: Option<String>, page_size: Option<i64>) -> Aliases {
+        Aliases {
+            stream: Box::pin(self.inner.alias_stream(key_id, page_size)),
+        }
+    }
+
+    /// Provides detailed information about the customer master key (CMK) an alias refers to. The `alias/` prefix is optional.
+    pub fn resolve_alias(&self, alias_name: &str) -> Result<KeyMetadata, KmsRsError> {
+        block_on(self.inner.resolve_alias(alias_name))
+    }
+
+    /// Allows the principal in `options` to use the customer master key (CMK) `key_id` for the given operations.
+    pub fn create_grant(
+        &self,
+        key_id: &str,
+        options: CreateGrantOptions,
+    ) -> Result<CreateGrantOutput, KmsRsError> {
+        block_on(self.inner.create_grant(key_id, options))
+    }
+
+    /// Creates a grant that is retired when the returned guard is dropped.
+    pub fn grant_guard(
+        &self,
+        key_id: &str,
+        options: CreateGrantOptions,
+    ) -> Result<GrantGuard, KmsRsError> {
+        block_on(GrantGuard::create(&self.inner, key_id, options))
+    }
+
+    /// Gets the list of all grants on the customer master key (CMK) `key_id`.
+    pub fn list_grants(&self, key_id: &str) -> Result<ListGrantsOutput, KmsRsError> {
+        block_on(self.inner.list_grants(key_id))
+    }
+
+    /// Gets the list of all grants, on any CMK, that `retiring_principal` can retire.
+    pub fn list_retiring_grants(
+        &self,
+        retiring_principal: &str,
+    ) -> Result<ListGrantsOutput, KmsRsError> {
+        block_on(self.inner.list_retiring_grants(retiring_principal))
+    }
+
+    /// Retires the grant identified by `grant_token`.
+    pub fn retire_grant(&self, grant_token: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.retire_grant(grant_token))
+    }
+
+    /// Revokes the grant `grant_id` on the customer master key (CMK) `key_id`.
+    pub fn revoke_grant(&self, key_id: &str, grant_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.revoke_grant(key_id, grant_id))
+    }
+
+    /// Gets the key policy of a customer master key (CMK).
+    pub fn get_key_policy(&self, key_id: &str) -> Result<KeyPolicy, KmsRsError> {
+        block_on(self.inner.get_key_policy(key_id))
+    }
+
+    /// Replaces the key policy of a customer master key (CMK), unless `linter` reports a finding.
+    pub fn put_key_policy(
+        &self,
+        key_id: &str,
+        policy: &KeyPolicy,
+        linter: &PolicyLinter,
+    ) -> Result<(), KmsRsError> {
+        block_on(self.inner.put_key_policy(key_id, policy, linter))
+    }
+
+    /// Gets the names of the key policies of a customer master key (CMK).
+    pub fn list_key_policies(&self, key_id: &str) -> Result<ListKeyPoliciesOutput, KmsRsError> {
+        block_on(self.inner.list_key_policies(key_id))
+    }
+
+    /// Adds or overwrites tags on a customer master key (CMK).
+    pub fn tag_resource(&self, key_id: &str, tags: Vec<Tag>) -> Result<(), KmsRsError> {
+        block_on(self.inner.tag_resource(key_id, tags))
+    }
+
+    /// Removes the tags with the given keys from a customer master key (CMK).
+    pub fn untag_resource(&self, key_id: &str, tag_keys: Vec<String>) -> Result<(), KmsRsError> {
+        block_on(self.inner.untag_resource(key_id, tag_keys))
+    }
+
+    /// Gets every tag on a customer master key (CMK).
+    pub fn list_resource_tags(&self, key_id: &str) -> Result<ListResourceTagsOutput, KmsRsError> {
+        block_on(self.inner.list_resource_tags(key_id))
+    }
+
+    /// Finds the customer master keys (CMKs) in current AWS account and Region matching `filter`.
+    pub fn find_keys(&self, filter: &KeyFilter) -> Result<Vec<KeyMetadata>, KmsRsError> {
+        block_on(self.inner.find_keys(filter))
+    }
+
+    /// Creates a unique customer managed customer master key (CMK) in your AWS account and Region.
+    pub fn create_key(&self, options: CreateKeyOptions) -> Result<KeyMetadata, KmsRsError> {
+        block_on(self.inner.create_key(options))
+    }
+
+    /// Imports 256-bit symmetric key material into a CMK created with `Origin=EXTERNAL`, wrapping it locally.
+    /// KMS deletes it at `expiration`, or never if `None`.
+    pub fn import_key_material(
+        &self,
+        key_id: &str,
+        material: &[u8],
+        expiration: Option<DateTime<Utc>>,
+    ) -> Result<(), KmsRsError> {
+        block_on(self.inner.import_key_material(key_id, material, expiration))
+    }
+
+    /// Deletes the key material imported into a CMK.
+    pub fn delete_imported_key_material(&self, key_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.delete_imported_key_material(key_id))
+    }
+
+    /// Replicates a multi-Region primary key into `replica_region`, with the given description and tags.
+    pub fn replicate_key(
+        &self,
+        key_id: &str,
+        replica_region: &Region,
+        description: Option<String>,
+        tags: Vec<Tag>,
+    ) -> Result<KeyMetadata, KmsRsError> {
+        block_on(
+            self.inner
+                .replicate_key(key_id, replica_region, description, tags),
+        )
+    }
+
+    /// Makes the replica of a multi-Region key in `primary_region` the primary key.
+    pub fn update_primary_region(
+        &self,
+        key_id: &str,
+        primary_region: &Region,
+    ) -> Result<(), KmsRsError> {
+        block_on(self.inner.update_primary_region(key_id, primary_region))
+    }
+
+    /// Describes every replica of a multi-Region key, each through a client for its Region.
+    pub fn list_replicas(&self, key_id: &str) -> Result<Vec<KeyMetadata>, KmsRsError> {
+        block_on(self.inner.list_replicas(key_id))
+    }
+
+    /// Schedules the deletion of a customer master key (CMK). You may provide a waiting period, specified in days, before deletion occurs.
+    pub fn schedule_key_deletion(
+        &self,
+        key_id: String,
+        pending_window_in_days: i64,
+    ) -> Result<ScheduleKeyDeletionOutput, KmsRsError> {
+        block_on(
+            self.inner
+                .schedule_key_deletion(key_id, pending_window_in_days),
+        )
+    }
+
+    /// Cancels the deletion of a customer master key (CMK). When this operation succeeds, the key state of the CMK is Disabled.
+    pub fn cancel_key_deletion(
+        &self,
+        key_id: String,
+    ) -> Result<CancelKeyDeletionOutput, KmsRsError> {
+        block_on(self.inner.cancel_key_deletion(key_id))
+    }
+
+    /// Sets the key state to disabled of a customer master key (CMK) to enabled.
+    pub fn disable_key(&self, key_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.disable_key(key_id))
+    }
+
+    /// Turns on automatic rotation of the key material of a symmetric customer managed CMK, every year.
+    pub fn enable_key_rotation(&self, key_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.enable_key_rotation(key_id))
+    }
+
+    /// Turns off automatic rotation of the key material of a symmetric customer managed CMK.
+    pub fn disable_key_rotation(&self, key_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.disable_key_rotation(key_id))
+    }
+
+    /// Gets whether automatic rotation of the key material is enabled for a symmetric CMK.
+    pub fn get_key_rotation_status(
+        &self,
+        key_id: &str,
+    ) -> Result<GetKeyRotationStatusOutput, KmsRsError> {
+        block_on(self.inner.get_key_rotation_status(key_id))
+    }
+
+    /// Lists the symmetric customer managed CMKs whose key material is not rotated automatically.
+    pub fn keys_without_rotation(&self) -> Result<Vec<KeyMetadata>, KmsRsError> {
+        block_on(self.inner.keys_without_rotation())
+    }
+
+    /// Sets the key state to enabled of a customer master key (CMK) to enabled.
+    pub fn enable_key(&self, key_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.enable_key(key_id))
+    }
+
+    /// Generates a unique symmetric data key for client-side encryption. This operation returns a plaintext copy of the data key and a copy that is encrypted under a customer master key (CMK) that you specify.
+    pub fn generate_data_key(
+        &self,
+        key_id: &str,
+        key_spec: Option<String>,
+        bytes: Option<i64>,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKey, KmsRsError> {
+        block_on(self.inner.generate_data_key(
+            key_id,
+            key_spec,
+            bytes,
+            encryption_context,
+            grant_tokens,
+        ))
+    }
+
+    /// Generates a unique symmetric data key. This operation returns a data key that is encrypted under a customer master key (CMK) that you specify.
+    pub fn generate_data_key_without_plaintext(
+        &self,
+        key_id: &str,
+        key_spec: Option<String>,
+        bytes: Option<i64>,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKey, KmsRsError> {
+        block_on(self.inner.generate_data_key_without_plaintext(
+            key_id,
+            key_spec,
+            bytes,
+            encryption_context,
+            grant_tokens,
+        ))
+    }
+
+    /// Generates a unique asymmetric data key pair. The GenerateDataKeyPair operation returns a plaintext public key, a plaintext private key, and a copy of the private key that is encrypted under the symmetric CMK you specify.
+    pub fn generate_data_key_pair(
+        &self,
+        key_id: &str,
+        key_pair_spec: String,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKeyPair, KmsRsError> {
+        block_on(self.inner.generate_data_key_pair(
+            key_id,
+            key_pair_spec,
+            encryption_context,
+            grant_tokens,
+        ))
+    }
+
+    /// Generates a unique asymmetric data key pair. The GenerateDataKeyPair-WithoutPlaintext operation returns a plaintext public key and a copy of the private key that is encrypted under the symmetric CMK you specify.
+    pub fn generate_data_key_pair_without_plaintext(
+        &self,
+        key_id: &str,
+        key_pair_spec: String,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKeyPair, KmsRsError> {
+        block_on(self.inner.generate_data_key_pair_without_plaintext(
+            key_id,
+            key_pair_spec,
+            encryption_context,
+            grant_tokens,
+        ))
+    }
+
+    /// Encrypts  plaintext  into  ciphertext  by  using  a customer master key (CMK).
+    pub fn encrypt(
+        &self,
+        key_id: String,
+        plaintext: Bytes,
+        encryption_context: Option<HashMap<String, String>>,
+        encryption_algorithm: Option<String>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<EncryptOutput, KmsRsError> {
+        block_on(self.inner.encrypt(
+            key_id,
+            plaintext,
+            encryption_context,
+            encryption_algorithm,
+            grant_tokens,
+        ))
+    }
+
+    /// Decrypts ciphertext that was encrypted by a AWS KMS customer master key (CMK) using any of the following operations: Encrypt, GenerateDataKey, GenerateDataKeyPair, GenerateDataKeyWithoutPlaintext, GenerateDataKeyPairWithoutPlaintext
+    pub fn decrypt(
+        &self,
+        key_id: Option<String>,
+        ciphertext_blob: Bytes,
+        encryption_context: Option<HashMap<String, String>>,
+        encryption_algorithm: Option<String>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DecryptOutput, KmsRsError> {
+        block_on(self.inner.decrypt(
+            key_id,
+            ciphertext_blob,
+            encryption_context,
+            encryption_algorithm,
+            grant_tokens,
+        ))
+    }
+
+    /// Decrypts ciphertext and encrypts it again under another CMK, without exposing the plaintext.
+    pub fn re_encrypt(
+        &self,
+        ciphertext_blob: Bytes,
+        options: &ReEncryptOptions,
+    ) -> Result<ReEncryptOutput, KmsRsError> {
+        block_on(self.inner.re_encrypt(ciphertext_blob, options))
+    }
+
+    /// Re-encrypts every ciphertext of `ciphertext_blobs`, running up to `concurrency` requests at a time.
+    /// Returns one result per ciphertext, in input order.
+    pub fn re_encrypt_all<I, F>(
+        &self,
+        ciphertext_blobs: I,
+        options: &ReEncryptOptions,
+        concurrency: usize,
+        progress: F,
+    ) -> Vec<Result<ReEncryptOutput, KmsRsError>>
+    where
+        I: IntoIterator<Item = Bytes>,
+        F: FnMut(ReEncryptProgress),
+    {
+        block_on(
+            self.inner
+                .re_encrypt_all(ciphertext_blobs, options, concurrency, progress),
+        )
+    }
+
+    /// Creates a digital signature for a message or message digest by using the private key in an asymmetric CMK. To verify the signature, use the Verify operation, or use the public key in the same asymmetric CMK outside of AWS KMS.
+    pub fn sign(
+        &self,
+        key_id: String,
+        message: Bytes,
+        message_type: Option<String>,
+        signing_algorithm: String,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<SignOutput, KmsRsError> {
+        block_on(self.inner.sign(
+            key_id,
+            message,
+            message_type,
+            signing_algorithm,
+            grant_tokens,
+        ))
+    }
+
+    /// Verifies a digital signature that was generated by the Sign operation.
+    pub fn verify(
+        &self,
+        key_id: String,
+        message: Bytes,
+        message_type: Option<String>,
+        signature: Bytes,
+        signing_algorithm: String,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<VerifyOutput, KmsRsError> {
+        block_on(self.inner.verify(
+            key_id,
+            message,
+            message_type,
+            signature,
+            signing_algorithm,
+            grant_tokens,
+        ))
+    }
+
+    /// Signs a message of any size read from `reader`: it is hashed locally with the digest of `signing_algorithm` and only the digest is sent to Sign, with `MessageType=DIGEST`.
+    pub fn sign_reader<R: Read>(
+        &self,
+        key_id: &str,
+        reader: R,
+        signing_algorithm: &str,
+    ) -> Result<SignOutput, KmsRsError> {
+        block_on(
+            self.inner
+                .sign_reader(key_id, BlockingReader(reader), signing_algorithm),
+        )
+    }
+
+    /// Verifies a signature made by `sign_reader`, hashing the message read from `reader` locally and calling Verify with `MessageType=DIGEST`.
+    pub fn verify_reader<R: Read>(
+        &self,
+        key_id: &str,
+        reader: R,
+        signature: Bytes,
+        signing_algorithm: &str,
+    ) -> Result<VerifyOutput, KmsRsError> {
+        block_on(self.inner.verify_reader(
+            key_id,
+            BlockingReader(reader),
+            signature,
+            signing_algorithm,
+        ))
+    }
+
+    /// Returns the public key of an asymmetric CMK. To quickly create a key to test with outside of this lib, run: `aws kms create-key --key-usage ENCRYPT_DECRYPT --customer-master-key-spec RSA_2048`
+    pub fn get_public_key(
+        &self,
+        key_id: String,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<PublicKey, KmsRsError> {
+        block_on(self.inner.get_public_key(key_id, grant_tokens))
+    }
+
+    /// Encrypts `plaintext` locally with the public key of an RSA `ENCRYPT_DECRYPT` CMK, fetched through GetPublicKey. Decrypt accepts the ciphertext given the CMK and the same `encryption_algorithm`.
+    pub fn encrypt_with_public_key(
+        &self,
+        key_id: &str,
+        plaintext: &[u8],
+        encryption_algorithm: &str,
+    ) -> Result<EncryptOutput, KmsRsError> {
+        block_on(
+            self.inner
+                .encrypt_with_public_key(key_id, plaintext, encryption_algorithm),
+        )
+    }
+
+    /// Creates a custom key store associated with an AWS CloudHSM cluster and returns its ID.
+    pub fn create_custom_key_store(
+        &self,
+        custom_key_store_name: &str,
+        cloud_hsm_cluster_id: &str,
+        trust_anchor_certificate: &str,
+        key_store_password: &str,
+    ) -> Result<String, KmsRsError> {
+        block_on(self.inner.create_custom_key_store(
+            custom_key_store_name,
+            cloud_hsm_cluster_id,
+            trust_anchor_certificate,
+            key_store_password,
+        ))
+    }
+
+    /// Gets every custom key store in current AWS account and Region.
+    pub fn describe_custom_key_stores(&self) -> Result<DescribeCustomKeyStoresOutput, KmsRsError> {
+        block_on(self.inner.describe_custom_key_stores())
+    }
+
+    /// Provides detailed information about a custom key store, including its connection state.
+    pub fn describe_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+    ) -> Result<CustomKeyStore, KmsRsError> {
+        block_on(self.inner.describe_custom_key_store(custom_key_store_id))
+    }
+
+    /// Starts connecting a custom key store to its CloudHSM cluster.
+    pub fn connect_custom_key_store(&self, custom_key_store_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.connect_custom_key_store(custom_key_store_id))
+    }
+
+    /// Disconnects a custom key store from its CloudHSM cluster.
+    pub fn disconnect_custom_key_store(&self, custom_key_store_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.disconnect_custom_key_store(custom_key_store_id))
+    }
+
+    /// Renames a disconnected custom key store, updates its `kmsuser` password or changes its cluster.
+    pub fn update_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+        options: UpdateCustomKeyStoreOptions,
+    ) -> Result<(), KmsRsError> {
+        block_on(
+            self.inner
+                .update_custom_key_store(custom_key_store_id, options),
+        )
+    }
+
+    /// Deletes a disconnected custom key store that has no CMKs.
+    pub fn delete_custom_key_store(&self, custom_key_store_id: &str) -> Result<(), KmsRsError> {
+        block_on(self.inner.delete_custom_key_store(custom_key_store_id))
+    }
+
+    /// Waits until a custom key store is `CONNECTED`, polling every `poll_interval`.
+    pub fn wait_for_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<CustomKeyStore, KmsRsError> {
+        block_on(
+            self.inner
+                .wait_for_custom_key_store(custom_key_store_id, poll_interval, timeout),
+        )
+    }
+}
+
+/// Blocking iterator over the CMKs in an account, returned by [`KmsRs::keys`] and [`keys`].
+pub struct Keys {
+    stream: Pin<Box<dyn Stream<Item = Result<KeyListEntry, KmsRsError>> + Send>>,
+}
+
+impl Iterator for Keys {
+    type Item = Result<KeyListEntry, KmsRsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(self.stream.next())
+    }
+}
+
+/// Blocking iterator over the aliases in an account, returned by [`KmsRs::aliases`] and [`aliases`].
+pub struct Aliases {
+    stream: Pin<Box<dyn Stream<Item = Result<AliasListEntry, KmsRsError>> + Send>>,
+}
+
+impl Iterator for Aliases {
+    type Item = Result<AliasListEntry, KmsRsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(self.stream.next())
+    }
+}
+
+impl From<crate::KmsRs> for KmsRs {
+    fn from(inner: crate::KmsRs) -> KmsRs {
+        KmsRs::new(inner)
+    }
+}
+
+/// Gets the list of all Customer Master Keys (CMKs) in current AWS account and Region.
+pub fn list_keys() -> Result<ListKeysOutput, KmsRsError> {
+    default_client().list_keys()
+}
+
+/// Iterates over every Customer Master Key (CMK) in current AWS account and Region, requesting `page_size` keys (between 1 and 1000, defaults to 100) at a time.
+pub fn keys(page_size: Option<i64>) -> Keys {
+    default_client().keys(page_size)
+}
+
+/// Provides detailed information about a customer master key (CMK).
+pub fn describe_key(key_id: &str) -> Result<KeyMetadata, KmsRsError> {
+    default_client().describe_key(key_id)
+}
+
+/// Creates a friendly name (e.g. `alias/prod/payments`) for a customer master key (CMK).
+pub fn create_alias(alias_name: &str, target_key_id: &str) -> Result<(), KmsRsError> {
+    default_client().create_alias(alias_name, target_key_id)
+}
+
+/// Associates an existing alias with a different customer master key (CMK).
+pub fn update_alias(alias_name: &str, target_key_id: &str) -> Result<(), KmsRsError> {
+    default_client().update_alias(alias_name, target_key_id)
+}
+
+/// Deletes an alias. The customer master key (CMK) it refers to is not affected.
+pub fn delete_alias(alias_name: &str) -> Result<(), KmsRsError> {
+    default_client().delete_alias(alias_name)
+}
+
+/// Gets the list of all aliases in current AWS account and Region, or only those of the CMK `key_id`.
+pub fn list_aliases(key_id: Option<&str>) -> Result<ListAliasesOutput, KmsRsError> {
+    default_client().list_aliases(key_id)
+}
+
+/// Iterates over every alias in current AWS account and Region, or only those of the CMK `key_id`, requesting `page_size` aliases (between 1 and 100, defaults to 50) at a time.
+pub fn aliases(key_id: Option<String>, page_size: Option<i64>) -> Aliases {
+    default_client().aliases(key_id, page_size)
+}
+
+/// Provides detailed information about the customer master key (CMK) an alias refers to. The `alias/` prefix is optional.
+pub fn resolve_alias(alias_name: &str) -> Result<KeyMetadata, KmsRsError> {
+    default_client().resolve_alias(alias_name)
+}
+
+/// Allows the principal in `options` to use the customer master key (CMK) `key_id` for the given operations.
+pub fn create_grant(
+    key_id: &str,
+    options: CreateGrantOptions,
+) -> Result<CreateGrantOutput, KmsRsError> {
+    default_client().create_grant(key_id, options)
+}
+
+/// Creates a grant that is retired when the returned guard is dropped.
+pub fn grant_guard(key_id: &str, options: CreateGrantOptions) -> Result<GrantGuard, KmsRsError> {
+    default_client().grant_guard(key_id, options)
+}
+
+/// Gets the list of all grants on the customer master key (CMK) `key_id`.
+pub fn list_grants(key_id: &str) -> Result<ListGrantsOutput, KmsRsError> {
+    default_client().list_grants(key_id)
+}
+
+/// Gets the list of all grants, on any CMK, that `retiring_principal` can retire.
+pub fn list_retiring_grants(retiring_principal: &str) -> Result<ListGrantsOutput, KmsRsError> {
+    default_client().list_retiring_grants(retiring_principal)
+}
+
+/// Retires the grant identified by `grant_token`.
+pub fn retire_grant(grant_token: &str) -> Result<(), KmsRsError> {
+    default_client().retire_grant(grant_token)
+}
+
+/// Revokes the grant `grant_id` on the customer master key (CMK) `key_id`.
+pub fn revoke_grant(key_id: &str, grant_id: &str) -> Result<(), KmsRsError> {
+    default_client().revoke_grant(key_id, grant_id)
+}
+
+/// Gets the key policy of a customer master key (CMK).
+pub fn get_key_policy(key_id: &str) -> Result<KeyPolicy, KmsRsError> {
+    default_client().get_key_policy(key_id)
+}
+
+/// Replaces the key policy of a customer master key (CMK), unless `linter` reports a finding.
+pub fn put_key_policy(
+    key_id: &str,
+    policy: &KeyPolicy,
+    linter: &PolicyLinter,
+) -> Result<(), KmsRsError> {
+    default_client().put_key_policy(key_id, policy, linter)
+}
+
+/// Gets the names of the key policies of a customer master key (CMK).
+pub fn list_key_policies(key_id: &str) -> Result<ListKeyPoliciesOutput, KmsRsError> {
+    default_client().list_key_policies(key_id)
+}
+
+/// Adds or overwrites tags on a customer master key (CMK).
+pub fn tag_resource(key_id: &str, tags: Vec<Tag>) -> Result<(), KmsRsError> {
+    default_client().tag_resource(key_id, tags)
+}
+
+/// Removes the tags with the given keys from a customer master key (CMK).
+pub fn untag_resource(key_id: &str, tag_keys: Vec<String>) -> Result<(), KmsRsError> {
+    default_client().untag_resource(key_id, tag_keys)
+}
+
+/// Gets every tag on a customer master key (CMK).
+pub fn list_resource_tags(key_id: &str) -> Result<ListResourceTagsOutput, KmsRsError> {
+    default_client().list_resource_tags(key_id)
+}
+
+/// Finds the customer master keys (CMKs) in current AWS account and Region matching `filter`.
+pub fn find_keys(filter: &KeyFilter) -> Result<Vec<KeyMetadata>, KmsRsError> {
+    default_client().find_keys(filter)
+}
+
+/// Creates a unique customer managed customer master key (CMK) in your AWS account and Region.
+pub fn create_key(options: CreateKeyOptions) -> Result<KeyMetadata, KmsRsError> {
+    default_client().create_key(options)
+}
+
+/// Imports 256-bit symmetric key material into a CMK created with `Origin=EXTERNAL`, wrapping it locally.
+/// KMS deletes it at `expiration`, or never if `None`.
+pub fn import_key_material(
+    key_id: &str,
+    material: &[u8],
+    expiration: Option<DateTime<Utc>>,
+) -> Result<(), KmsRsError> {
+    default_client().import_key_material(key_id, material, expiration)
+}
+
+/// Deletes the key material imported into a CMK.
+pub fn delete_imported_key_material(key_id: &str) -> Result<(), KmsRsError> {
+    default_client().delete_imported_key_material(key_id)
+}
+
+/// Replicates a multi-Region primary key into `replica_region`, with the given description and tags.
+pub fn replicate_key(
+    key_id: &str,
+    replica_region: &Region,
+    description: Option<String>,
+    tags: Vec<Tag>,
+) -> Result<KeyMetadata, KmsRsError> {
+    default_client().replicate_key(key_id, replica_region, description, tags)
+}
+
+/// Makes the replica of a multi-Region key in `primary_region` the primary key.
+pub fn update_primary_region(key_id: &str, primary_region: &Region) -> Result<(), KmsRsError> {
+    default_client().update_primary_region(key_id, primary_region)
+}
+
+/// Describes every replica of a multi-Region key, each through a client for its Region.
+pub fn list_replicas(key_id: &str) -> Result<Vec<KeyMetadata>, KmsRsError> {
+    default_client().list_replicas(key_id)
+}
+
+/// Schedules the deletion of a customer master key (CMK). You may provide a waiting period, specified in days, before deletion occurs.
+pub fn schedule_key_deletion(
+    key_id: String,
+    pending_window_in_days: i64,
+) -> Result<ScheduleKeyDeletionOutput, KmsRsError> {
+    default_client().schedule_key_deletion(key_id, pending_window_in_days)
+}
+
+/// Cancels the deletion of a customer master key (CMK). When this operation succeeds, the key state of the CMK is Disabled.
+pub fn cancel_key_deletion(key_id: String) -> Result<CancelKeyDeletionOutput, KmsRsError> {
+    default_client().cancel_key_deletion(key_id)
+}
+
+/// Sets the key state to disabled of a customer master key (CMK) to enabled.
+pub fn disable_key(key_id: &str) -> Result<(), KmsRsError> {
+    default_client().disable_key(key_id)
+}
+
+/// Turns on automatic rotation of the key material of a symmetric customer managed CMK, every year.
+pub fn enable_key_rotation(key_id: &str) -> Result<(), KmsRsError> {
+    default_client().enable_key_rotation(key_id)
+}
+
+/// Turns off automatic rotation of the key material of a symmetric customer managed CMK.
+pub fn disable_key_rotation(key_id: &str) -> Result<(), KmsRsError> {
+    default_client().disable_key_rotation(key_id)
+}
+
+/// Gets whether automatic rotation of the key material is enabled for a symmetric CMK.
+pub fn get_key_rotation_status(key_id: &str) -> Result<GetKeyRotationStatusOutput, KmsRsError> {
+    default_client().get_key_rotation_status(key_id)
+}
+
+/// Lists the symmetric customer managed CMKs whose key material is not rotated automatically.
+pub fn keys_without_rotation() -> Result<Vec<KeyMetadata>, KmsRsError> {
+    default_client().keys_without_rotation()
+}
+
+/// Sets the key state to enabled of a customer master key (CMK) to enabled.
+pub fn enable_key(key_id: &str) -> Result<(), KmsRsError> {
+    default_client().enable_key(key_id)
+}
+
+/// Generates a unique symmetric data key for client-side encryption. This operation returns a plaintext copy of the data key and a copy that is encrypted under a customer master key (CMK) that you specify.
+pub fn generate_data_key(
+    key_id: &str,
+    key_spec: Option<String>,
+    bytes: Option<i64>,
+    encryption_context: Option<HashMap<String, String>>,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<DataKey, KmsRsError> {
+    default_client().generate_data_key(key_id, key_spec, bytes, encryption_context, grant_tokens)
+}
+
+/// Generates a unique symmetric data key. This operation returns a data key that is encrypted under a customer master key (CMK) that you specify.
+pub fn generate_data_key_without_plaintext(
+    key_id: &str,
+    key_spec: Option<String>,
+    bytes: Option<i64>,
+    encryption_context: Option<HashMap<String, String>>,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<DataKey, KmsRsError> {
+    default_client().generate_data_key_without_plaintext(
+        key_id,
+        key_spec,
+        bytes,
+        encryption_context,
+        grant_tokens,
+    )
+}
+
+/// Generates a unique asymmetric data key pair. The GenerateDataKeyPair operation returns a plaintext public key, a plaintext private key, and a copy of the private key that is encrypted under the symmetric CMK you specify.
+pub fn generate_data_key_pair(
+    key_id: &str,
+    key_pair_spec: String,
+    encryption_context: Option<HashMap<String, String>>,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<DataKeyPair, KmsRsError> {
+    default_client().generate_data_key_pair(key_id, key_pair_spec, encryption_context, grant_tokens)
+}
+
+/// Generates a unique asymmetric data key pair. The GenerateDataKeyPair-WithoutPlaintext operation returns a plaintext public key and a copy of the private key that is encrypted under the symmetric CMK you specify.
+pub fn generate_data_key_pair_without_plaintext(
+    key_id: &str,
+    key_pair_spec: String,
+    encryption_context: Option<HashMap<String, String>>,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<DataKeyPair, KmsRsError> {
+    default_client().generate_data_key_pair_without_plaintext(
+        key_id,
+        key_pair_spec,
+        encryption_context,
+        grant_tokens,
+    )
+}
+
+/// Encrypts  plaintext  into  ciphertext  by  using  a customer master key (CMK).
+pub fn encrypt(
+    key_id: String,
+    plaintext: Bytes,
+    encryption_context: Option<HashMap<String, String>>,
+    encryption_algorithm: Option<String>,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<EncryptOutput, KmsRsError> {
+    default_client().encrypt(
+        key_id,
+        plaintext,
+        encryption_context,
+        encryption_algorithm,
+        grant_tokens,
+    )
+}
+
+/// Decrypts ciphertext that was encrypted by a AWS KMS customer master key (CMK) using any of the following operations: Encrypt, GenerateDataKey, GenerateDataKeyPair, GenerateDataKeyWithoutPlaintext, GenerateDataKeyPairWithoutPlaintext
+pub fn decrypt(
+    key_id: Option<String>,
+    ciphertext_blob: Bytes,
+    encryption_context: Option<HashMap<String, String>>,
+    encryption_algorithm: Option<String>,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<DecryptOutput, KmsRsError> {
+    default_client().decrypt(
+        key_id,
+        ciphertext_blob,
+        encryption_context,
+        encryption_algorithm,
+        grant_tokens,
+    )
+}
+
+/// Decrypts ciphertext and encrypts it again under another CMK, without exposing the plaintext.
+pub fn re_encrypt(
+    ciphertext_blob: Bytes,
+    options: &ReEncryptOptions,
+) -> Result<ReEncryptOutput, KmsRsError> {
+    default_client().re_encrypt(ciphertext_blob, options)
+}
+
+/// Re-encrypts every ciphertext of `ciphertext_blobs`, running up to `concurrency` requests at a time.
+/// Returns one result per ciphertext, in input order.
+pub fn re_encrypt_all<I, F>(
+    ciphertext_blobs: I,
+    options: &ReEncryptOptions,
+    concurrency: usize,
+    progress: F,
+) -> Vec<Result<ReEncryptOutput, KmsRsError>>
+where
+    I: IntoIterator<Item = Bytes>,
+    F: FnMut(ReEncryptProgress),
+{
+    default_client().re_encrypt_all(ciphertext_blobs, options, concurrency, progress)
+}
+
+/// Creates a digital signature for a message or message digest by using the private key in an asymmetric CMK. To verify the signature, use the Verify operation, or use the public key in the same asymmetric CMK outside of AWS KMS.
+pub fn sign(
+    key_id: String,
+    message: Bytes,
+    message_type: Option<String>,
+    signing_algorithm: String,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<SignOutput, KmsRsError> {
+    default_client().sign(
+        key_id,
+        message,
+        message_type,
+        signing_algorithm,
+        grant_tokens,
+    )
+}
+
+/// Verifies a digital signature that was generated by the Sign operation.
+pub fn verify(
+    key_id: String,
+    message: Bytes,
+    message_type: Option<String>,
+    signature: Bytes,
+    signing_algorithm: String,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<VerifyOutput, KmsRsError> {
+    default_client().verify(
+        key_id,
+        message,
+        message_type,
+        signature,
+        signing_algorithm,
+        grant_tokens,
+    )
+}
+
+/// Signs a message of any size read from `reader`: it is hashed locally with the digest of `signing_algorithm` and only the digest is sent to Sign, with `MessageType=DIGEST`.
+pub fn sign_reader<R: Read>(
+    key_id: &str,
+    reader: R,
+    signing_algorithm: &str,
+) -> Result<SignOutput, KmsRsError> {
+    default_client().sign_reader(key_id, reader, signing_algorithm)
+}
+
+/// Verifies a signature made by `sign_reader`, hashing the message read from `reader` locally and calling Verify with `MessageType=DIGEST`.
+pub fn verify_reader<R: Read>(
+    key_id: &str,
+    reader: R,
+    signature: Bytes,
+    signing_algorithm: &str,
+) -> Result<VerifyOutput, KmsRsError> {
+    default_client().verify_reader(key_id, reader, signature, signing_algorithm)
+}
+
+/// Returns the public key of an asymmetric CMK. To quickly create a key to test with outside of this lib, run: `aws kms create-key --key-usage ENCRYPT_DECRYPT --customer-master-key-spec RSA_2048`
+pub fn get_public_key(
+    key_id: String,
+    grant_tokens: Option<Vec<String>>,
+) -> Result<PublicKey, KmsRsError> {
+    default_client().get_public_key(key_id, grant_tokens)
+}
+
+/// Encrypts `plaintext` locally with the public key of an RSA `ENCRYPT_DECRYPT` CMK, fetched through GetPublicKey. Decrypt accepts the ciphertext given the CMK and the same `encryption_algorithm`.
+pub fn encrypt_with_public_key(
+    key_id: &str,
+    plaintext: &[u8],
+    encryption_algorithm: &str,
+) -> Result<EncryptOutput, KmsRsError> {
+    default_client().encrypt_with_public_key(key_id, plaintext, encryption_algorithm)
+}
+
+/// Creates a custom key store associated with an AWS CloudHSM cluster and returns its ID.
+pub fn create_custom_key_store(
+    custom_key_store_name: &str,
+    cloud_hsm_cluster_id: &str,
+    trust_anchor_certificate: &str,
+    key_store_password: &str,
+) -> Result<String, KmsRsError> {
+    default_client().create_custom_key_store(
+        custom_key_store_name,
+        cloud_hsm_cluster_id,
+        trust_anchor_certificate,
+        key_store_password,
+    )
+}
+
+/// Gets every custom key store in current AWS account and Region.
+pub fn describe_custom_key_stores() -> Result<DescribeCustomKeyStoresOutput, KmsRsError> {
+    default_client().describe_custom_key_stores()
+}
+
+/// Provides detailed information about a custom key store, including its connection state.
+pub fn describe_custom_key_store(custom_key_store_id: &str) -> Result<CustomKeyStore, KmsRsError> {
+    default_client().describe_custom_key_store(custom_key_store_id)
+}
+
+/// Starts connecting a custom key store to its CloudHSM cluster.
+pub fn connect_custom_key_store(custom_key_store_id: &str) -> Result<(), KmsRsError> {
+    default_client().connect_custom_key_store(custom_key_store_id)
+}
+
+/// Disconnects a custom key store from its CloudHSM cluster.
+pub fn disconnect_custom_key_store(custom_key_store_id: &str) -> Result<(), KmsRsError> {
+    default_client().disconnect_custom_key_store(custom_key_store_id)
+}
+
+/// Renames a disconnected custom key store, updates its `kmsuser` password or changes its cluster.
+pub fn update_custom_key_store(
+    custom_key_store_id: &str,
+    options: UpdateCustomKeyStoreOptions,
+) -> Result<(), KmsRsError> {
+    default_client().update_custom_key_store(custom_key_store_id, options)
+}
+
+/// Deletes a disconnected custom key store that has no CMKs.
+pub fn delete_custom_key_store(custom_key_store_id: &str) -> Result<(), KmsRsError> {
+    default_client().delete_custom_key_store(custom_key_store_id)
+}
+
+/// Waits until a custom key store is `CONNECTED`, polling every `poll_interval`.
+pub fn wait_for_custom_key_store(
+    custom_key_store_id: &str,
+    poll_interval: Duration,
+    timeout: Duration,
+) -> Result<CustomKeyStore, KmsRsError> {
+    default_client().wait_for_custom_key_store(custom_key_store_id, poll_interval, timeout)
+}
+
+/// Blocking versions of the [`crate::envelope`] functions. `seal` and `open` use the default client.
+pub mod envelope {
+    use bytes::Bytes;
+    use std::collections::HashMap;
+
+    use super::{block_on, default_client};
+    use crate::cache::DataKeyCache;
+    use crate::envelope::Envelope;
+    use crate::error::KmsRsError;
+
+    /// Encrypts `plaintext` under a new AES-256 data key generated by the CMK `key_id`.
+    pub fn seal(
+        key_id: &str,
+        plaintext: &[u8],
+        encryption_context: HashMap<String, String>,
+    ) -> Result<Envelope, KmsRsError> {
+        block_on(crate::envelope::seal(
+            default_client().get_ref(),
+            key_id,
+            plaintext,
+            encryption_context,
+        ))
+    }
+
+    /// Decrypts an envelope produced by [`seal`].
+    pub fn open(envelope: &Envelope) -> Result<Bytes, KmsRsError> {
+        block_on(crate::envelope::open(default_client().get_ref(), envelope))
+    }
+
+    /// Same as [`seal`], with the data key served by `cache`.
+    pub fn seal_cached(
+        cache: &DataKeyCache,
+        key_id: &str,
+        plaintext: &[u8],
+        encryption_context: HashMap<String, String>,
+    ) -> Result<Envelope, KmsRsError> {
+        block_on(crate::envelope::seal_cached(
+            cache,
+            key_id,
+            plaintext,
+            encryption_context,
+        ))
+    }
+
+    /// Same as [`open`], with the decrypted data key served by `cache`.
+    pub fn open_cached(cache: &DataKeyCache, envelope: &Envelope) -> Result<Bytes, KmsRsError> {
+        block_on(crate::envelope::open_cached(cache, envelope))
+    }
+}
+
+/// Lets the async API read from a [`Read`]. Each read blocks, which is fine on the thread `block_on` drives.
+struct BlockingReader<R>(R);
+
+// The reader is never pinned in place, only borrowed for each read.
+impl<R> Unpin for BlockingReader<R> {}
+
+impl<R: Read> AsyncRead for BlockingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let read = self.get_mut().0.read(buf.initialize_unfilled())?;
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusoto_core::credential::StaticProvider;
+
+    #[test]
+    fn test_runtime_is_reused_across_calls() {
+        let kms = KmsRs::new(
+            crate::KmsRs::builder()
+                .endpoint("http://127.0.0.1:1")
+                .credentials(StaticProvider::new_minimal(
+                    "access_key".to_string(),
+                    "secret_key".to_string(),
+                ))
+                .build()
+                .unwrap(),
+        );
+        for _ in 0..2 {
+            match kms.describe_key("abcd-4321-wxyz") {
+                Err(KmsRsError::HttpDispatch(_)) => (),
+                other => panic!("Expected an HttpDispatch error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_reader() {
+        let kms = KmsRs::new(crate::KmsRs::from_backend(crate::InMemoryKms::new()));
+        let key_id = kms
+            .create_key(
+                CreateKeyOptions::new()
+                    .key_usage("SIGN_VERIFY")
+                    .customer_master_key_spec("ECC_NIST_P256"),
+            )
+            .unwrap()
+            .key_id;
+        // Larger than the read buffer, so `BlockingReader` is read from several times.
+        let tarball: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        let signature = kms
+            .sign_reader(&key_id, &tarball[..], "ECDSA_SHA_256")
+            .unwrap()
+            .signature;
+        assert!(
+            kms.verify_reader(&key_id, &tarball[..], signature.clone(), "ECDSA_SHA_256")
+                .unwrap()
+                .signature_valid
+        );
+        match kms.verify_reader(&key_id, &tarball[1..], signature, "ECDSA_SHA_256") {
+            Err(KmsRsError::KmsInvalidSignature(_)) => (),
+            other => panic!("Expected a KmsInvalidSignature error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,427 @@
+//! A cache of plaintext data keys in front of GenerateDataKey and Decrypt.
+//!
+//! Reusing a data key for several messages trades some security for fewer (billed, rate limited) KMS calls,
+//! so every cached key is bounded by [`CacheLimits`]: its age, the number of messages it encrypted and the
+//! number of bytes it encrypted. Plaintext keys are zeroized once they are evicted and no longer in use.
+
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::secret::into_zeroizing;
+use crate::sync::lock;
+
+/// The limits a cached data key must stay within to be reused.
+///
+/// ```
+/// use kms_rs::cache::CacheLimits;
+/// use std::time::Duration;
+///
+/// let limits = CacheLimits::new()
+///     .max_age(Duration::from_secs(60))
+///     .max_messages(1_000)
+///     .max_bytes(64 * 1024 * 1024);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheLimits {
+    max_age: Duration,
+    max_messages: u64,
+    max_bytes: u64,
+    capacity: usize,
+}
+
+impl Default for CacheLimits {
+    /// Five minutes, 2^32 messages, 2^62 bytes and 1000 entries.
+    fn default() -> CacheLimits {
+        CacheLimits {
+            max_age: Duration::from_secs(300),
+            max_messages: 1 << 32,
+            max_bytes: 1 << 62,
+            capacity: 1000,
+        }
+    }
+}
+
+impl CacheLimits {
+    /// Same as `CacheLimits::default()`.
+    pub fn new() -> CacheLimits {
+        CacheLimits::default()
+    }
+
+    /// How long a data key is reused after it was generated or decrypted.
+    pub fn max_age(mut self, max_age: Duration) -> CacheLimits {
+        self.max_age = max_age;
+        self
+    }
+
+    /// How many messages a data key encrypts before a new one is generated. Decrypted data keys are only
+    /// bounded by their age and the capacity.
+    pub fn max_messages(mut self, max_messages: u64) -> CacheLimits {
+        self.max_messages = max_messages;
+        self
+    }
+
+    /// How many bytes a data key encrypts before a new one is generated.
+    pub fn max_bytes(mut self, max_bytes: u64) -> CacheLimits {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// How many data keys are held at once. The oldest entry is evicted to make room for a new one.
+    pub fn capacity(mut self, capacity: usize) -> CacheLimits {
+        self.capacity = capacity;
+        self
+    }
+}
+
+/// A data key served by a [`DataKeyCache`]. The plaintext is zeroized when the last copy of it is dropped.
+#[derive(Clone)]
+pub struct CachedDataKey {
+    pub key_id: String,
+    pub ciphertext_blob: Bytes,
+    plaintext: Arc<Zeroizing<Vec<u8>>>,
+}
+
+impl CachedDataKey {
+    /// The plaintext data key.
+    pub fn plaintext(&self) -> &[u8] {
+        &self.plaintext
+    }
+}
+
+impl std::fmt::Debug for CachedDataKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedDataKey")
+            .field("key_id", &self.key_id)
+            .field("ciphertext_blob", &self.ciphertext_blob)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Generate {
+        key_id: String,
+        key_spec: String,
+        encryption_context: BTreeMap<String, String>,
+    },
+    Decrypt {
+        key_id: Option<String>,
+        ciphertext_blob: Bytes,
+        encryption_context: BTreeMap<String, String>,
+    },
+}
+
+struct Entry {
+    data_key: CachedDataKey,
+    created: Instant,
+    messages: u64,
+    bytes: u64,
+}
+
+/// Caches plaintext data keys from GenerateDataKey and Decrypt, keyed by CMK (or ciphertext) and
+/// encryption context.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), kms_rs::KmsRsError> {
+/// use kms_rs::cache::{CacheLimits, DataKeyCache};
+/// use kms_rs::{envelope, KmsRs};
+/// use std::collections::HashMap;
+///
+/// let cache = DataKeyCache::new(KmsRs::default(), CacheLimits::new().max_messages(100));
+/// for message in &[&b"first"[..], &b"second"[..]] {
+///     // Only the first message calls GenerateDataKey.
+///     envelope::seal_cached(&cache, "alias/events", message, HashMap::new()).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct DataKeyCache {
+    kms: KmsRs,
+    limits: CacheLimits,
+    entries: Mutex<HashMap<CacheKey, Entry>>,
+}
+
+impl DataKeyCache {
+    /// Creates an empty cache in front of `kms`.
+    pub fn new(kms: KmsRs, limits: CacheLimits) -> DataKeyCache {
+        DataKeyCache {
+            kms,
+            limits,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The client used on cache misses.
+    pub fn kms(&self) -> &KmsRs {
+        &self.kms
+    }
+
+    /// Returns a data key for encrypting one message of `message_bytes` bytes, calling GenerateDataKey
+    /// only if no cached key for the same CMK, key spec and encryption context is within its limits.
+    pub async fn generate_data_key(
+        &self,
+        key_id: &str,
+        key_spec: &str,
+        encryption_context: HashMap<String, String>,
+        message_bytes: u64,
+    ) -> Result<CachedDataKey, KmsRsError> {
+        let cache_key = CacheKey::Generate {
+            key_id: key_id.to_string(),
+            key_spec: key_spec.to_string(),
+            encryption_context: encryption_context.clone().into_iter().collect(),
+        };
+        if let Some(data_key) = self.get(&cache_key, message_bytes) {
+            return Ok(data_key);
+        }
+
+        let data_key = self
+            .kms
+            .generate_data_key(
+                key_id,
+                Some(key_spec.to_string()),
+                None,
+                Some(encryption_context).filter(|context| !context.is_empty()),
+                None,
+            )
+            .await?;
+        let plaintext = data_key.plaintext.ok_or_else(|| {
+            KmsRsError::Parse("GenerateDataKey returned no plaintext data key".to_string())
+        })?;
+        let data_key = CachedDataKey {
+            key_id: data_key.key_id,
+            ciphertext_blob: data_key.ciphertext_blob,
+            plaintext: Arc::new(into_zeroizing(plaintext)),
+        };
+        self.insert(cache_key, data_key.clone(), message_bytes);
+        Ok(data_key)
+    }
+
+    /// Returns the plaintext of an encrypted data key, calling Decrypt only if the same ciphertext and
+    /// encryption context were not decrypted within the maximum age.
+    pub async fn decrypt(
+        &self,
+        key_id: Option<String>,
+        ciphertext_blob: Bytes,
+        encryption_context: HashMap<String, String>,
+    ) -> Result<CachedDataKey, KmsRsError> {
+        let cache_key = CacheKey::Decrypt {
+            key_id: key_id.clone(),
+            ciphertext_blob: ciphertext_blob.clone(),
+            encryption_context: encryption_context.clone().into_iter().collect(),
+        };
+        if let Some(data_key) = self.get(&cache_key, 0) {
+            return Ok(data_key);
+        }
+
+        let output = self
+            .kms
+            .decrypt(
+                key_id,
+                ciphertext_blob.clone(),
+                Some(encryption_context).filter(|context| !context.is_empty()),
+                None,
+                None,
+            )
+            .await?;
+        let data_key = CachedDataKey {
+            key_id: output.key_id,
+            ciphertext_blob,
+            plaintext: Arc::new(into_zeroizing(output.plaintext)),
+        };
+        self.insert(cache_key, data_key.clone(), 0);
+        Ok(data_key)
+    }
+
+    /// The number of data keys currently cached.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Whether no data keys are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Evicts every cached data key.
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<CacheKey, Entry>> {
+        lock(&self.entries)
+    }
+
+    /// Returns the cached data key if using it for one more message of `message_bytes` stays within the
+    /// limits, evicting it otherwise. Decrypted data keys are only bounded by their age: the message and
+    /// byte limits apply to encryption.
+    fn get(&self, cache_key: &CacheKey, message_bytes: u64) -> Option<CachedDataKey> {
+        let mut entries = self.entries();
+        let entry = entries.get_mut(cache_key)?;
+        let encrypts = matches!(cache_key, CacheKey::Generate { .. });
+        let within_limits = entry.created.elapsed() < self.limits.max_age
+            && (!encrypts
+                || (entry.messages < self.limits.max_messages
+                    && entry.bytes.saturating_add(message_bytes) <= self.limits.max_bytes));
+        if !within_limits {
+            entries.remove(cache_key);
+            return None;
+        }
+        if encrypts {
+            entry.messages += 1;
+            entry.bytes += message_bytes;
+        }
+        Some(entry.data_key.clone())
+    }
+
+    fn insert(&self, cache_key: CacheKey, data_key: CachedDataKey, message_bytes: u64) {
+        // Skip keys that could never be served from the cache.
+        let encrypts = matches!(cache_key, CacheKey::Generate { .. });
+        if self.limits.capacity == 0
+            || self.limits.max_age.is_zero()
+            || (encrypts
+                && (self.limits.max_messages <= 1 || message_bytes > self.limits.max_bytes))
+        {
+            return;
+        }
+
+        let mut entries = self.entries();
+        let max_age = self.limits.max_age;
+        entries.retain(|_, entry| entry.created.elapsed() < max_age);
+        if entries.len() >= self.limits.capacity && !entries.contains_key(&cache_key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(cache_key, _)| cache_key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            cache_key,
+            Entry {
+                data_key,
+                created: Instant::now(),
+                messages: u64::from(encrypts),
+                bytes: message_bytes,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::mock_kms;
+
+    const GENERATE_RESPONSE: &str = r#"{
+        "KeyId": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz",
+        "CiphertextBlob": "Zmlyc3Q=",
+        "Plaintext": "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyA="
+    }"#;
+
+    const SECOND_GENERATE_RESPONSE: &str = r#"{
+        "KeyId": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz",
+        "CiphertextBlob": "c2Vjb25k",
+        "Plaintext": "ICEiIyQlJicoKSorLC0uLzAxMjM0NTY3ODk6Ozw9Pj8="
+    }"#;
+
+    fn cache(limits: CacheLimits) -> DataKeyCache {
+        DataKeyCache::new(
+            mock_kms(vec![GENERATE_RESPONSE, SECOND_GENERATE_RESPONSE]),
+            limits,
+        )
+    }
+
+    async fn ciphertext(cache: &DataKeyCache, message_bytes: u64) -> Bytes {
+        cache
+            .generate_data_key("alias/test", "AES_256", HashMap::new(), message_bytes)
+            .await
+            .unwrap()
+            .ciphertext_blob
+    }
+
+    #[tokio::test]
+    async fn test_reuses_data_key_until_max_messages() {
+        let cache = cache(CacheLimits::new().max_messages(2));
+        assert_eq!(ciphertext(&cache, 10).await, Bytes::from_static(b"first"));
+        assert_eq!(ciphertext(&cache, 10).await, Bytes::from_static(b"first"));
+        assert_eq!(ciphertext(&cache, 10).await, Bytes::from_static(b"second"));
+    }
+
+    #[tokio::test]
+    async fn test_reuses_data_key_until_max_bytes() {
+        let cache = cache(CacheLimits::new().max_bytes(100));
+        assert_eq!(ciphertext(&cache, 60).await, Bytes::from_static(b"first"));
+        assert_eq!(ciphertext(&cache, 40).await, Bytes::from_static(b"first"));
+        assert_eq!(ciphertext(&cache, 1).await, Bytes::from_static(b"second"));
+    }
+
+    #[tokio::test]
+    async fn test_caches_first_message_of_max_bytes() {
+        let cache = cache(CacheLimits::new().max_bytes(100));
+        assert_eq!(ciphertext(&cache, 100).await, Bytes::from_static(b"first"));
+        assert_eq!(ciphertext(&cache, 0).await, Bytes::from_static(b"first"));
+        assert_eq!(ciphertext(&cache, 1).await, Bytes::from_static(b"second"));
+    }
+
+    #[tokio::test]
+    async fn test_expires_data_key_after_max_age() {
+        let cache = cache(CacheLimits::new().max_age(Duration::from_millis(0)));
+        assert_eq!(ciphertext(&cache, 1).await, Bytes::from_static(b"first"));
+        assert_eq!(ciphertext(&cache, 1).await, Bytes::from_static(b"second"));
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_keys_by_encryption_context() {
+        let cache = cache(CacheLimits::new());
+        assert_eq!(ciphertext(&cache, 1).await, Bytes::from_static(b"first"));
+        let mut context = HashMap::new();
+        context.insert("Purpose".to_string(), "test".to_string());
+        let data_key = cache
+            .generate_data_key("alias/test", "AES_256", context, 1)
+            .await
+            .unwrap();
+        assert_eq!(data_key.ciphertext_blob, Bytes::from_static(b"second"));
+        assert_eq!(cache.len(), 2);
+    }
+
+    const DECRYPT_RESPONSE: &str = r#"{
+        "KeyId": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz",
+        "Plaintext": "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyA=",
+        "EncryptionAlgorithm": "SYMMETRIC_DEFAULT"
+    }"#;
+
+    #[tokio::test]
+    async fn test_caches_decrypted_data_keys() {
+        // A single mocked response: a second Decrypt call would fail.
+        let cache = DataKeyCache::new(mock_kms(vec![DECRYPT_RESPONSE]), CacheLimits::new());
+        for _ in 0..2 {
+            let data_key = cache
+                .decrypt(None, Bytes::from_static(b"first"), HashMap::new())
+                .await
+                .unwrap();
+            assert_eq!(data_key.plaintext().len(), 32);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decrypt_ignores_max_messages() {
+        let cache = DataKeyCache::new(
+            mock_kms(vec![DECRYPT_RESPONSE]),
+            CacheLimits::new().max_messages(1),
+        );
+        for _ in 0..3 {
+            cache
+                .decrypt(None, Bytes::from_static(b"first"), HashMap::new())
+                .await
+                .unwrap();
+        }
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -1,316 +1,1517 @@
 //! Module responsible for handling the requests and responses.
 
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use rusoto_core::Region;
 use rusoto_kms::{
-    CancelKeyDeletionRequest, CreateKeyRequest, DecryptRequest, DescribeKeyRequest,
-    DisableKeyRequest, EnableKeyRequest, EncryptRequest, GenerateDataKeyPairRequest,
+    CancelKeyDeletionRequest, ConnectCustomKeyStoreRequest, CreateAliasRequest,
+    CreateCustomKeyStoreRequest, DecryptRequest, DeleteAliasRequest, DeleteCustomKeyStoreRequest,
+    DeleteImportedKeyMaterialRequest, DescribeCustomKeyStoresRequest, DescribeKeyRequest,
+    DisableKeyRequest, DisableKeyRotationRequest, DisconnectCustomKeyStoreRequest,
+    EnableKeyRequest, EnableKeyRotationRequest, EncryptRequest, GenerateDataKeyPairRequest,
     GenerateDataKeyPairWithoutPlaintextRequest, GenerateDataKeyRequest,
-    GenerateDataKeyWithoutPlaintextRequest, GenerateRandomRequest, GetPublicKeyRequest, Kms,
-    KmsClient, ListKeysRequest, ScheduleKeyDeletionRequest, SignRequest, VerifyRequest,
-}; // https://docs.rs/rusoto_kms/0.45.0/rusoto_kms/#structs
-use serde_json::json;
-use serde_json::value::Value;
+    GenerateDataKeyWithoutPlaintextRequest, GenerateRandomRequest, GetKeyPolicyRequest,
+    GetKeyRotationStatusRequest, GetParametersForImportRequest, GetPublicKeyRequest,
+    ImportKeyMaterialRequest, ListAliasesRequest, ListAliasesResponse, ListGrantsRequest,
+    ListKeyPoliciesRequest, ListKeysRequest, ListKeysResponse, ListResourceTagsRequest,
+    ListRetirableGrantsRequest, PutKeyPolicyRequest, ReplicateKeyRequest, RetireGrantRequest,
+    RevokeGrantRequest, ScheduleKeyDeletionRequest, SignRequest, TagResourceRequest,
+    UntagResourceRequest, UpdateAliasRequest, UpdatePrimaryRegionRequest, VerifyRequest,
+}; // https://docs.rs/rusoto_kms/0.48.0/rusoto_kms/#structs
 use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::vec::Vec;
+use tokio::io::AsyncRead;
 
+/// The algorithm `import_key_material` wraps key material with, and the spec of the wrapping key.
+const WRAPPING_ALGORITHM: &str = "RSAES_OAEP_SHA_256";
+const WRAPPING_KEY_SPEC: &str = "RSA_2048";
+
+/// How many keys `find_keys` and `keys_without_rotation` inspect at the same time.
+const INSPECT_KEYS_CONCURRENCY: usize = 8;
+
+use crate::crypto;
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::model::{
+    AliasListEntry, CancelKeyDeletionOutput, CreateGrantOutput, CustomKeyStore, DataKey,
+    DataKeyPair, DecryptOutput, DescribeCustomKeyStoresOutput, EncryptOutput,
+    GetKeyRotationStatusOutput, KeyListEntry, KeyMetadata, ListAliasesOutput, ListGrantsOutput,
+    ListKeyPoliciesOutput, ListKeysOutput, ListResourceTagsOutput, PublicKey, ReEncryptOutput,
+    ReEncryptProgress, ScheduleKeyDeletionOutput, SignOutput, Tag, VerifyOutput,
+};
+use crate::options::{
+    CreateGrantOptions, CreateKeyOptions, KeyFilter, ReEncryptOptions, UpdateCustomKeyStoreOptions,
+};
 use crate::parse;
+use crate::policy::{KeyPolicy, PolicyLinter};
 
-fn get_client() -> KmsClient {
-    KmsClient::new(Region::UsEast1)
-}
+/// Every CMK has a single key policy, and this is its name.
+const DEFAULT_POLICY_NAME: &str = "default";
 
-pub async fn get_key(key_id: &str) -> Value {
-    let request = DescribeKeyRequest {
-        grant_tokens: None,
-        key_id: key_id.to_string(),
-    };
+impl KmsRs {
+    /// Provides detailed information about a customer master key (CMK).
+    pub async fn describe_key(&self, key_id: &str) -> Result<KeyMetadata, KmsRsError> {
+        let request = DescribeKeyRequest {
+            grant_tokens: None,
+            key_id: key_id.to_string(),
+        };
 
-    let result = get_client().describe_key(request).await;
+        let response = self.backend.describe_key(request).await?;
 
-    match result {
-        Ok(response) => parse::key_metadata(response.key_metadata.unwrap_or_default()),
-        Err(value) => json!(value.to_string()),
+        Ok(parse::key_metadata(
+            response.key_metadata.unwrap_or_default(),
+        ))
     }
-}
 
-pub async fn get_keys() -> Value {
-    let request = ListKeysRequest::default();
+    /// Gets the list of all Customer Master Keys (CMKs) in current AWS account and Region, following pagination markers until every page has been read.
+    pub async fn list_keys(&self) -> Result<ListKeysOutput, KmsRsError> {
+        let keys = collect_pages(|marker| async move {
+            let response = self.list_keys_page(None, marker).await?;
+            Ok((
+                response.keys.unwrap_or_default(),
+                next_marker(response.next_marker, response.truncated),
+            ))
+        })
+        .await?;
+
+        Ok(parse::key_list_entries(keys))
+    }
 
-    let result = get_client().list_keys(request).await;
+    /// Streams every Customer Master Key (CMK) in current AWS account and Region, requesting the next page only once the previous one has been consumed.
+    /// `page_size` is the number of keys requested per call (between 1 and 1000, defaults to 100).
+    pub fn key_stream(
+        &self,
+        page_size: Option<i64>,
+    ) -> impl Stream<Item = Result<KeyListEntry, KmsRsError>> + Send + 'static {
+        let kms = self.clone();
 
-    match result {
-        Ok(response) => parse::key_list_entries(response.keys.unwrap_or_default()),
-        Err(value) => json!(value.to_string()),
+        paginate(move |marker| {
+            let kms = kms.clone();
+            async move {
+                let response = kms.list_keys_page(page_size, marker).await?;
+                let keys = response.keys.unwrap_or_default();
+
+                Ok((
+                    keys.into_iter().map(parse::key_list_entry).collect(),
+                    next_marker(response.next_marker, response.truncated),
+                ))
+            }
+        })
     }
-}
 
-pub async fn create_key_and_parse() -> Value {
-    let mut request = CreateKeyRequest::default();
-    request.key_usage = Some("ENCRYPT_DECRYPT".to_string()); // default
-    request.customer_master_key_spec = Some("SYMMETRIC_DEFAULT".to_string()); // default
+    async fn list_keys_page(
+        &self,
+        limit: Option<i64>,
+        marker: Option<String>,
+    ) -> Result<ListKeysResponse, KmsRsError> {
+        let request = ListKeysRequest { limit, marker };
+
+        self.backend.list_keys(request).await
+    }
 
-    let result = get_client().create_key(request).await;
+    /// Creates a friendly name (e.g. `alias/prod/payments`) for a customer master key (CMK).
+    pub async fn create_alias(
+        &self,
+        alias_name: &str,
+        target_key_id: &str,
+    ) -> Result<(), KmsRsError> {
+        let request = CreateAliasRequest {
+            alias_name: alias_name.to_string(),
+            target_key_id: target_key_id.to_string(),
+        };
 
-    match result {
-        Ok(response) => parse::key_metadata(response.key_metadata.unwrap_or_default()),
-        Err(value) => json!(value.to_string()),
+        self.backend.create_alias(request).await // AWS gives an empty response
     }
-}
 
-pub async fn schedule_key_deletion_and_parse(key_id: String, pending_window_in_days: i64) -> Value {
-    let request = ScheduleKeyDeletionRequest {
-        key_id,
-        pending_window_in_days: Some(pending_window_in_days),
-    };
+    /// Associates an existing alias with a different customer master key (CMK).
+    pub async fn update_alias(
+        &self,
+        alias_name: &str,
+        target_key_id: &str,
+    ) -> Result<(), KmsRsError> {
+        let request = UpdateAliasRequest {
+            alias_name: alias_name.to_string(),
+            target_key_id: target_key_id.to_string(),
+        };
 
-    let result = get_client().schedule_key_deletion(request).await;
+        self.backend.update_alias(request).await // AWS gives an empty response
+    }
+
+    /// Deletes an alias. The customer master key (CMK) it refers to is not affected.
+    pub async fn delete_alias(&self, alias_name: &str) -> Result<(), KmsRsError> {
+        let request = DeleteAliasRequest {
+            alias_name: alias_name.to_string(),
+        };
 
-    match result {
-        Ok(response) => parse::schedule_deletion_response(response),
-        Err(value) => json!(value.to_string()),
+        self.backend.delete_alias(request).await // AWS gives an empty response
     }
-}
 
-pub async fn cancel_key_deletion_and_parse(key_id: String) -> Value {
-    let request = CancelKeyDeletionRequest { key_id };
+    /// Gets the list of all aliases in current AWS account and Region, or only those of the CMK `key_id`, following pagination markers until every page has been read.
+    pub async fn list_aliases(
+        &self,
+        key_id: Option<&str>,
+    ) -> Result<ListAliasesOutput, KmsRsError> {
+        let aliases = collect_pages(|marker| async move {
+            let response = self
+                .list_aliases_page(key_id.map(str::to_string), None, marker)
+                .await?;
+            Ok((
+                response.aliases.unwrap_or_default(),
+                next_marker(response.next_marker, response.truncated),
+            ))
+        })
+        .await?;
 
-    let result = get_client().cancel_key_deletion(request).await;
+        Ok(parse::alias_list_entries(aliases))
+    }
 
-    match result {
-        Ok(response) => parse::cancel_deletion_response(response),
-        Err(value) => json!(value.to_string()),
+    /// Streams every alias in current AWS account and Region, or only those of the CMK `key_id`, requesting the next page only once the previous one has been consumed.
+    /// `page_size` is the number of aliases requested per call (between 1 and 100, defaults to 50).
+    pub fn alias_stream(
+        &self,
+        key_id: Option<String>,
+        page_size: Option<i64>,
+    ) -> impl Stream<Item = Result<AliasListEntry, KmsRsError>> + Send + 'static {
+        let kms = self.clone();
+
+        paginate(move |marker| {
+            let kms = kms.clone();
+            let key_id = key_id.clone();
+            async move {
+                let response = kms.list_aliases_page(key_id, page_size, marker).await?;
+                let aliases = response.aliases.unwrap_or_default();
+
+                Ok((
+                    aliases.into_iter().map(parse::alias_list_entry).collect(),
+                    next_marker(response.next_marker, response.truncated),
+                ))
+            }
+        })
     }
-}
 
-pub async fn enable_key_and_respond(key_id: &str) -> Option<Value> {
-    let request = EnableKeyRequest {
-        key_id: key_id.to_string(),
-    };
-    let result = get_client().enable_key(request).await;
+    async fn list_aliases_page(
+        &self,
+        key_id: Option<String>,
+        limit: Option<i64>,
+        marker: Option<String>,
+    ) -> Result<ListAliasesResponse, KmsRsError> {
+        let request = ListAliasesRequest {
+            key_id,
+            limit,
+            marker,
+        };
 
-    match result {
-        Ok(()) => None, // AWS gives an empty response
-        Err(value) => Some(json!(value.to_string())),
+        self.backend.list_aliases(request).await
     }
-}
 
-pub async fn disable_key_and_respond(key_id: &str) -> Option<Value> {
-    let request = DisableKeyRequest {
-        key_id: key_id.to_string(),
-    };
-    let result = get_client().disable_key(request).await;
+    /// Provides detailed information about the customer master key (CMK) an alias refers to. The `alias/` prefix is optional.
+    pub async fn resolve_alias(&self, alias_name: &str) -> Result<KeyMetadata, KmsRsError> {
+        if alias_name.starts_with("alias/") {
+            self.describe_key(alias_name).await
+        } else {
+            self.describe_key(&format!("alias/{}", alias_name)).await
+        }
+    }
+
+    /// Allows the principal in `options` to use the customer master key (CMK) `key_id` for the given operations.
+    /// Pass the returned grant token in `grant_tokens` to use the grant before it is eventually consistent.
+    pub async fn create_grant(
+        &self,
+        key_id: &str,
+        options: CreateGrantOptions,
+    ) -> Result<CreateGrantOutput, KmsRsError> {
+        let request = options.into_request(key_id);
 
-    match result {
-        Ok(()) => None, // AWS gives an empty response
-        Err(value) => Some(json!(value.to_string())),
+        let response = self.backend.create_grant(request).await?;
+        Ok(parse::create_grant_response(response))
     }
-}
 
-pub async fn generate_data_key_and_parse(
-    key_id: &str,
-    key_spec: Option<String>,
-    bytes: Option<i64>,
-) -> Value {
-    let request = GenerateDataKeyRequest {
-        encryption_context: None,
-        grant_tokens: None,
-        key_id: key_id.to_string(),
-        key_spec,
-        number_of_bytes: bytes,
-    };
+    /// Gets the list of all grants on the customer master key (CMK) `key_id`, following pagination markers until every page has been read.
+    pub async fn list_grants(&self, key_id: &str) -> Result<ListGrantsOutput, KmsRsError> {
+        let grants = collect_pages(|marker| async move {
+            let request = ListGrantsRequest {
+                key_id: key_id.to_string(),
+                marker,
+                ..ListGrantsRequest::default()
+            };
+            let response = self.backend.list_grants(request).await?;
+            Ok((
+                response.grants.unwrap_or_default(),
+                next_marker(response.next_marker, response.truncated),
+            ))
+        })
+        .await?;
+
+        Ok(parse::grant_list_entries(grants))
+    }
 
-    let result = get_client().generate_data_key(request).await;
+    /// Gets the list of all grants, on any CMK, that `retiring_principal` can retire (the ListRetirableGrants operation), following pagination markers until every page has been read.
+    pub async fn list_retiring_grants(
+        &self,
+        retiring_principal: &str,
+    ) -> Result<ListGrantsOutput, KmsRsError> {
+        let grants = collect_pages(|marker| async move {
+            let request = ListRetirableGrantsRequest {
+                limit: None,
+                marker,
+                retiring_principal: retiring_principal.to_string(),
+            };
+            let response = self.backend.list_retirable_grants(request).await?;
+            Ok((
+                response.grants.unwrap_or_default(),
+                next_marker(response.next_marker, response.truncated),
+            ))
+        })
+        .await?;
 
-    match result {
-        Ok(response) => parse::data_key_response(response),
-        Err(value) => json!(value.to_string()),
+        Ok(parse::grant_list_entries(grants))
     }
-}
 
-pub async fn generate_data_key_without_plaintext_and_parse(
-    key_id: &str,
-    key_spec: Option<String>,
-    bytes: Option<i64>,
-) -> Value {
-    let request = GenerateDataKeyWithoutPlaintextRequest {
-        encryption_context: None,
-        grant_tokens: None,
-        key_id: key_id.to_string(),
-        key_spec,
-        number_of_bytes: bytes,
-    };
+    /// Retires the grant identified by `grant_token`. Called by the retiring principal, or by the grantee when the grant allows `RetireGrant`.
+    pub async fn retire_grant(&self, grant_token: &str) -> Result<(), KmsRsError> {
+        let request = RetireGrantRequest {
+            grant_token: Some(grant_token.to_string()),
+            ..RetireGrantRequest::default()
+        };
+
+        self.backend.retire_grant(request).await // AWS gives an empty response
+    }
 
-    let result = get_client()
-        .generate_data_key_without_plaintext(request)
-        .await;
+    /// Revokes the grant `grant_id` on the customer master key (CMK) `key_id`, denying every operation that depends on it.
+    pub async fn revoke_grant(&self, key_id: &str, grant_id: &str) -> Result<(), KmsRsError> {
+        let request = RevokeGrantRequest {
+            grant_id: grant_id.to_string(),
+            key_id: key_id.to_string(),
+        };
 
-    match result {
-        Ok(response) => parse::data_key_without_plaintext_response(response),
-        Err(value) => json!(value.to_string()),
+        self.backend.revoke_grant(request).await // AWS gives an empty response
     }
-}
 
-pub async fn generate_data_key_pair_and_parse(
-    key_id: &str,
-    key_pair_spec: String,
-    encryption_context: Option<HashMap<String, String>>,
-    grant_tokens: Option<Vec<String>>,
-) -> Value {
-    let request = GenerateDataKeyPairRequest {
-        encryption_context,
-        grant_tokens,
-        key_id: key_id.to_string(),
-        key_pair_spec,
-    };
+    /// Gets the key policy of a customer master key (CMK).
+    pub async fn get_key_policy(&self, key_id: &str) -> Result<KeyPolicy, KmsRsError> {
+        let request = GetKeyPolicyRequest {
+            key_id: key_id.to_string(),
+            policy_name: DEFAULT_POLICY_NAME.to_string(),
+        };
+
+        let response = self.backend.get_key_policy(request).await?;
+        KeyPolicy::from_json(&response.policy.unwrap_or_default())
+    }
 
-    let result = get_client().generate_data_key_pair(request).await;
+    /// Replaces the key policy of a customer master key (CMK). The policy is checked by `linter` first, and is
+    /// not sent to AWS (failing with [`KmsRsError::UnsafePolicy`]) if any finding is reported.
+    pub async fn put_key_policy(
+        &self,
+        key_id: &str,
+        policy: &KeyPolicy,
+        linter: &PolicyLinter,
+    ) -> Result<(), KmsRsError> {
+        let findings = linter.lint(policy);
+        if !findings.is_empty() {
+            let messages: Vec<String> = findings
+                .into_iter()
+                .map(|finding| finding.message)
+                .collect();
+            return Err(KmsRsError::UnsafePolicy(messages.join("; ")));
+        }
+        let request = PutKeyPolicyRequest {
+            bypass_policy_lockout_safety_check: None,
+            key_id: key_id.to_string(),
+            policy: policy.to_json(),
+            policy_name: DEFAULT_POLICY_NAME.to_string(),
+        };
 
-    match result {
-        Ok(response) => parse::data_key_pair_response(response),
-        Err(value) => json!(value.to_string()),
+        self.backend.put_key_policy(request).await // AWS gives an empty response
     }
-}
 
-pub async fn generate_data_key_pair_without_plaintext_and_parse(
-    key_id: &str,
-    key_pair_spec: String,
-    encryption_context: Option<HashMap<String, String>>,
-    grant_tokens: Option<Vec<String>>,
-) -> Value {
-    let request = GenerateDataKeyPairWithoutPlaintextRequest {
-        encryption_context,
-        grant_tokens,
-        key_id: key_id.to_string(),
-        key_pair_spec,
-    };
+    /// Gets the names of the key policies of a customer master key (CMK), following pagination markers until every page has been read.
+    pub async fn list_key_policies(
+        &self,
+        key_id: &str,
+    ) -> Result<ListKeyPoliciesOutput, KmsRsError> {
+        let policy_names = collect_pages(|marker| async move {
+            let request = ListKeyPoliciesRequest {
+                key_id: key_id.to_string(),
+                limit: None,
+                marker,
+            };
+            let response = self.backend.list_key_policies(request).await?;
+            Ok((
+                response.policy_names.unwrap_or_default(),
+                next_marker(response.next_marker, response.truncated),
+            ))
+        })
+        .await?;
 
-    let result = get_client()
-        .generate_data_key_pair_without_plaintext(request)
-        .await;
+        Ok(ListKeyPoliciesOutput { policy_names })
+    }
+
+    /// Adds or overwrites tags on a customer master key (CMK). Tag keys can't begin with `aws:`.
+    pub async fn tag_resource(&self, key_id: &str, tags: Vec<Tag>) -> Result<(), KmsRsError> {
+        let request = TagResourceRequest {
+            key_id: key_id.to_string(),
+            tags: tags
+                .into_iter()
+                .map(|tag| rusoto_kms::Tag {
+                    tag_key: tag.tag_key,
+                    tag_value: tag.tag_value,
+                })
+                .collect(),
+        };
 
-    match result {
-        Ok(response) => parse::data_key_pair_without_plaintext_response(response),
-        Err(value) => json!(value.to_string()),
+        self.backend.tag_resource(request).await
     }
-}
 
-pub async fn encrypt(
-    key_id: String,
-    plaintext: Bytes,
-    encryption_context: Option<HashMap<String, String>>,
-    encryption_algorithm: Option<String>,
-    grant_tokens: Option<Vec<String>>,
-) -> Value {
-    let request = EncryptRequest {
-        key_id,
-        plaintext,
-        encryption_context,
-        encryption_algorithm,
-        grant_tokens,
-    };
+    /// Removes the tags with the given keys from a customer master key (CMK).
+    pub async fn untag_resource(
+        &self,
+        key_id: &str,
+        tag_keys: Vec<String>,
+    ) -> Result<(), KmsRsError> {
+        let request = UntagResourceRequest {
+            key_id: key_id.to_string(),
+            tag_keys,
+        };
 
-    let result = get_client().encrypt(request).await;
+        self.backend.untag_resource(request).await
+    }
+
+    /// Gets every tag on a customer master key (CMK).
+    pub async fn list_resource_tags(
+        &self,
+        key_id: &str,
+    ) -> Result<ListResourceTagsOutput, KmsRsError> {
+        let tags = collect_pages(|marker| async move {
+            let request = ListResourceTagsRequest {
+                key_id: key_id.to_string(),
+                limit: None,
+                marker,
+            };
+            let response = self.backend.list_resource_tags(request).await?;
+            Ok((
+                response.tags.unwrap_or_default(),
+                next_marker(response.next_marker, response.truncated),
+            ))
+        })
+        .await?;
 
-    match result {
-        Ok(response) => parse::encrypt_response(response),
-        Err(value) => json!(value.to_string()),
+        Ok(parse::tags(tags))
     }
-}
 
-pub async fn decrypt(
-    key_id: Option<String>,
-    ciphertext_blob: Bytes,
-    encryption_context: Option<HashMap<String, String>>,
-    encryption_algorithm: Option<String>,
-    grant_tokens: Option<Vec<String>>,
-) -> Value {
-    let request = DecryptRequest {
-        key_id,
-        ciphertext_blob,
-        encryption_context,
-        encryption_algorithm,
-        grant_tokens,
-    };
+    /// Finds the customer master keys (CMKs) in current AWS account and Region matching `filter`. Every key is
+    /// described, and its tags are only listed when the filter has tag conditions; AWS managed keys can't be
+    /// tagged so they are treated as having no tags.
+    pub async fn find_keys(&self, filter: &KeyFilter) -> Result<Vec<KeyMetadata>, KmsRsError> {
+        self.key_stream(None)
+            .map_ok(|key| self.match_key(key.key_id, filter))
+            .try_buffered(INSPECT_KEYS_CONCURRENCY)
+            .try_filter_map(|metadata| async move { Ok(metadata) })
+            .try_collect()
+            .await
+    }
+
+    async fn match_key(
+        &self,
+        key_id: String,
+        filter: &KeyFilter,
+    ) -> Result<Option<KeyMetadata>, KmsRsError> {
+        let metadata = self.describe_key(&key_id).await?;
+        if !filter.matches_metadata(&metadata) {
+            return Ok(None);
+        }
 
-    let result = get_client().decrypt(request).await;
+        if filter.has_tag_predicates() {
+            let tags = if metadata.is_aws_managed() {
+                Vec::new()
+            } else {
+                self.list_resource_tags(&key_id).await?.tags
+            };
+            if !filter.matches_tags(&tags) {
+                return Ok(None);
+            }
+        }
 
-    match result {
-        Ok(response) => parse::decrypt_response(response),
-        Err(value) => json!(value.to_string()),
+        Ok(Some(metadata))
     }
-}
 
-pub async fn sign(
-    key_id: String,
-    message: Bytes,
-    message_type: Option<String>,
-    signing_algorithm: String,
-    grant_tokens: Option<Vec<String>>,
-) -> Value {
-    let request = SignRequest {
-        key_id,
-        message,
-        message_type,
-        signing_algorithm,
-        grant_tokens,
-    };
+    /// Creates a unique customer managed customer master key (CMK) in your AWS account and Region. Use
+    /// `CreateKeyOptions::default()` for a symmetric encryption CMK, or configure an asymmetric key spec,
+    /// key usage, description, policy, tags or origin.
+    pub async fn create_key(&self, options: CreateKeyOptions) -> Result<KeyMetadata, KmsRsError> {
+        let request = options.into_request();
 
-    let result = get_client().sign(request).await;
+        let response = self.backend.create_key(request).await?;
 
-    match result {
-        Ok(response) => parse::sign_response(response),
-        Err(value) => json!(value.to_string()),
+        Ok(parse::key_metadata(
+            response.key_metadata.unwrap_or_default(),
+        ))
     }
-}
 
-pub async fn verify(
-    key_id: String,
-    message: Bytes,
-    message_type: Option<String>,
-    signature: Bytes,
-    signing_algorithm: String,
-    grant_tokens: Option<Vec<String>>,
-) -> Value {
-    let request = VerifyRequest {
-        key_id,
-        message,
-        message_type,
-        signature,
-        signing_algorithm,
-        grant_tokens,
-    };
+    /// Imports 256-bit symmetric key material into a CMK created with `Origin=EXTERNAL` (see
+    /// [`CreateKeyOptions::origin`]). The material is wrapped locally with RSAES_OAEP_SHA_256 under the public
+    /// key returned by GetParametersForImport, so it never leaves this process in plaintext. KMS deletes it at
+    /// `expiration`, or never if `None`.
+    ///
+    /// Material can be imported again after it expires or is deleted, but only the same material.
+    pub async fn import_key_material(
+        &self,
+        key_id: &str,
+        material: &[u8],
+        expiration: Option<DateTime<Utc>>,
+    ) -> Result<(), KmsRsError> {
+        let request = GetParametersForImportRequest {
+            key_id: key_id.to_string(),
+            wrapping_algorithm: WRAPPING_ALGORITHM.to_string(),
+            wrapping_key_spec: WRAPPING_KEY_SPEC.to_string(),
+        };
+        let parameters = self.backend.get_parameters_for_import(request).await?;
 
-    let result = get_client().verify(request).await;
+        let wrapping_key = crypto::PublicKey::from_der(&parameters.public_key.unwrap_or_default())?;
+        let encrypted_key_material = wrapping_key.encrypt(WRAPPING_ALGORITHM, material)?;
+        let (expiration_model, valid_to) = match expiration {
+            Some(expiration) => (
+                "KEY_MATERIAL_EXPIRES",
+                Some(expiration.timestamp_millis() as f64 / 1000.0),
+            ),
+            None => ("KEY_MATERIAL_DOES_NOT_EXPIRE", None),
+        };
+        let request = ImportKeyMaterialRequest {
+            encrypted_key_material: Bytes::from(encrypted_key_material),
+            expiration_model: Some(expiration_model.to_string()),
+            import_token: parameters.import_token.unwrap_or_default(),
+            key_id: key_id.to_string(),
+            valid_to,
+        };
+        self.backend.import_key_material(request).await?; // AWS gives an empty response
 
-    match result {
-        Ok(response) => parse::verify_response(response),
-        Err(value) => json!(value.to_string()),
+        Ok(())
     }
-}
 
-pub async fn get_public_key(key_id: String, grant_tokens: Option<Vec<String>>) -> Value {
-    let request = GetPublicKeyRequest {
-        key_id,
-        grant_tokens,
-    };
+    /// Deletes the key material imported into a CMK, which becomes unusable (`PendingImport`) until the same
+    /// material is imported again.
+    pub async fn delete_imported_key_material(&self, key_id: &str) -> Result<(), KmsRsError> {
+        let request = DeleteImportedKeyMaterialRequest {
+            key_id: key_id.to_string(),
+        };
+        self.backend.delete_imported_key_material(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Replicates a multi-Region primary key (see [`CreateKeyOptions::multi_region`]) into `replica_region`,
+    /// where the replica gets the same key ID and key material, the default key policy and the given
+    /// description and tags. Call it on a client for the Region of the primary key; use
+    /// [`KmsRs::for_region`] to work with the replica.
+    pub async fn replicate_key(
+        &self,
+        key_id: &str,
+        replica_region: &Region,
+        description: Option<String>,
+        tags: Vec<Tag>,
+    ) -> Result<KeyMetadata, KmsRsError> {
+        let tags = if tags.is_empty() {
+            None
+        } else {
+            Some(
+                tags.into_iter()
+                    .map(|tag| rusoto_kms::Tag {
+                        tag_key: tag.tag_key,
+                        tag_value: tag.tag_value,
+                    })
+                    .collect(),
+            )
+        };
+        let request = ReplicateKeyRequest {
+            bypass_policy_lockout_safety_check: None,
+            description,
+            key_id: key_id.to_string(),
+            policy: None,
+            replica_region: replica_region.name().to_string(),
+            tags,
+        };
+
+        let response = self.backend.replicate_key(request).await?;
+
+        Ok(parse::key_metadata(
+            response.replica_key_metadata.unwrap_or_default(),
+        ))
+    }
+
+    /// Makes the replica of a multi-Region key in `primary_region` the primary key, and the current primary a
+    /// replica. Call it on a client for the Region of the current primary key.
+    pub async fn update_primary_region(
+        &self,
+        key_id: &str,
+        primary_region: &Region,
+    ) -> Result<(), KmsRsError> {
+        let request = UpdatePrimaryRegionRequest {
+            key_id: key_id.to_string(),
+            primary_region: primary_region.name().to_string(),
+        };
+        self.backend.update_primary_region(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Describes every replica of a multi-Region key, each through a client for its Region (see
+    /// [`KmsRs::for_region`]). `key_id` may identify the primary key or any replica in this client's Region.
+    /// Fails with [`KmsRsError::UnsupportedOperation`] if the CMK is not a multi-Region key.
+    pub async fn list_replicas(&self, key_id: &str) -> Result<Vec<KeyMetadata>, KmsRsError> {
+        let metadata = self.describe_key(key_id).await?;
+        let arn = metadata.arn;
+        let configuration = metadata.multi_region_configuration.ok_or_else(|| {
+            KmsRsError::UnsupportedOperation(format!("{} is not a multi-Region key.", arn))
+        })?;
+
+        let mut replicas = Vec::new();
+        for replica in configuration.replica_keys {
+            let region = Region::from_str(&replica.region)
+                .map_err(|err| KmsRsError::Parse(format!("{}: {}", replica.region, err)))?;
+            let kms = self.for_region(region)?;
+            replicas.push(kms.describe_key(&replica.arn).await?);
+        }
+        Ok(replicas)
+    }
+
+    /// Schedules the deletion of a customer master key (CMK). You may provide a waiting period, specified in days, before deletion occurs.
+    pub async fn schedule_key_deletion(
+        &self,
+        key_id: String,
+        pending_window_in_days: i64,
+    ) -> Result<ScheduleKeyDeletionOutput, KmsRsError> {
+        let request = ScheduleKeyDeletionRequest {
+            key_id,
+            pending_window_in_days: Some(pending_window_in_days),
+        };
+
+        let response = self.backend.schedule_key_deletion(request).await?;
+
+        Ok(parse::schedule_deletion_response(response))
+    }
+
+    /// Cancels the deletion of a customer master key (CMK). When this operation succeeds, the key state of the CMK is Disabled.
+    pub async fn cancel_key_deletion(
+        &self,
+        key_id: String,
+    ) -> Result<CancelKeyDeletionOutput, KmsRsError> {
+        let request = CancelKeyDeletionRequest { key_id };
+
+        let response = self.backend.cancel_key_deletion(request).await?;
+
+        Ok(parse::cancel_deletion_response(response))
+    }
+
+    /// Sets the key state to enabled of a customer master key (CMK) to enabled.
+    pub async fn enable_key(&self, key_id: &str) -> Result<(), KmsRsError> {
+        let request = EnableKeyRequest {
+            key_id: key_id.to_string(),
+        };
+        self.backend.enable_key(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Sets the key state to disabled of a customer master key (CMK) to enabled.
+    pub async fn disable_key(&self, key_id: &str) -> Result<(), KmsRsError> {
+        let request = DisableKeyRequest {
+            key_id: key_id.to_string(),
+        };
+        self.backend.disable_key(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Turns on automatic rotation of the key material of a symmetric customer managed CMK, every year.
+    pub async fn enable_key_rotation(&self, key_id: &str) -> Result<(), KmsRsError> {
+        let request = EnableKeyRotationRequest {
+            key_id: key_id.to_string(),
+        };
+        self.backend.enable_key_rotation(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Turns off automatic rotation of the key material of a symmetric customer managed CMK.
+    pub async fn disable_key_rotation(&self, key_id: &str) -> Result<(), KmsRsError> {
+        let request = DisableKeyRotationRequest {
+            key_id: key_id.to_string(),
+        };
+        self.backend.disable_key_rotation(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Gets whether automatic rotation of the key material is enabled for a symmetric CMK. It is reported as
+    /// disabled while the CMK is pending deletion.
+    pub async fn get_key_rotation_status(
+        &self,
+        key_id: &str,
+    ) -> Result<GetKeyRotationStatusOutput, KmsRsError> {
+        let request = GetKeyRotationStatusRequest {
+            key_id: key_id.to_string(),
+        };
+
+        let response = self.backend.get_key_rotation_status(request).await?;
+
+        Ok(GetKeyRotationStatusOutput {
+            key_rotation_enabled: response.key_rotation_enabled.unwrap_or_default(),
+        })
+    }
+
+    /// Lists the symmetric customer managed CMKs in current AWS account and Region whose key material is not
+    /// rotated automatically, skipping those pending deletion. CMKs with imported key material or in a custom
+    /// key store are always listed, since KMS can't rotate them.
+    pub async fn keys_without_rotation(&self) -> Result<Vec<KeyMetadata>, KmsRsError> {
+        self.key_stream(None)
+            .map_ok(|key| self.unrotated_key(key.key_id))
+            .try_buffered(INSPECT_KEYS_CONCURRENCY)
+            .try_filter_map(|metadata| async move { Ok(metadata) })
+            .try_collect()
+            .await
+    }
+
+    async fn unrotated_key(&self, key_id: String) -> Result<Option<KeyMetadata>, KmsRsError> {
+        let metadata = self.describe_key(&key_id).await?;
+        if metadata.is_aws_managed() || metadata.is_asymmetric() || metadata.is_pending_deletion() {
+            return Ok(None);
+        }
+
+        // Some responses carry an empty `CustomKeyStoreId` for CMKs outside custom key stores.
+        let rotatable = metadata.origin.as_deref() == Some("AWS_KMS")
+            && metadata
+                .custom_key_store_id
+                .as_deref()
+                .is_none_or(str::is_empty);
+        if rotatable
+            && self
+                .get_key_rotation_status(&key_id)
+                .await?
+                .key_rotation_enabled
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(metadata))
+    }
+
+    /// Generates a unique symmetric data key for client-side encryption. This operation returns a plaintext copy of the data key and a copy that is encrypted under a customer master key (CMK) that you specify.
+    pub async fn generate_data_key(
+        &self,
+        key_id: &str,
+        key_spec: Option<String>,
+        bytes: Option<i64>,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKey, KmsRsError> {
+        let request = GenerateDataKeyRequest {
+            encryption_context,
+            grant_tokens,
+            key_id: key_id.to_string(),
+            key_spec,
+            number_of_bytes: bytes,
+        };
+
+        let response = self.backend.generate_data_key(request).await?;
+
+        Ok(parse::data_key_response(response))
+    }
+
+    /// Generates a unique symmetric data key. This operation returns a data key that is encrypted under a customer master key (CMK) that you specify.
+    pub async fn generate_data_key_without_plaintext(
+        &self,
+        key_id: &str,
+        key_spec: Option<String>,
+        bytes: Option<i64>,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKey, KmsRsError> {
+        let request = GenerateDataKeyWithoutPlaintextRequest {
+            encryption_context,
+            grant_tokens,
+            key_id: key_id.to_string(),
+            key_spec,
+            number_of_bytes: bytes,
+        };
+
+        let response = self
+            .backend
+            .generate_data_key_without_plaintext(request)
+            .await?;
+
+        Ok(parse::data_key_without_plaintext_response(response))
+    }
+
+    /// Generates a unique asymmetric data key pair. The GenerateDataKeyPair operation returns a plaintext public key, a plaintext private key, and a copy of the private key that is encrypted under the symmetric CMK you specify.
+    pub async fn generate_data_key_pair(
+        &self,
+        key_id: &str,
+        key_pair_spec: String,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKeyPair, KmsRsError> {
+        let request = GenerateDataKeyPairRequest {
+            encryption_context,
+            grant_tokens,
+            key_id: key_id.to_string(),
+            key_pair_spec,
+        };
+
+        let response = self.backend.generate_data_key_pair(request).await?;
+
+        Ok(parse::data_key_pair_response(response))
+    }
+
+    /// Generates a unique asymmetric data key pair. The GenerateDataKeyPair-WithoutPlaintext operation returns a plaintext public key and a copy of the private key that is encrypted under the symmetric CMK you specify.
+    pub async fn generate_data_key_pair_without_plaintext(
+        &self,
+        key_id: &str,
+        key_pair_spec: String,
+        encryption_context: Option<HashMap<String, String>>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DataKeyPair, KmsRsError> {
+        let request = GenerateDataKeyPairWithoutPlaintextRequest {
+            encryption_context,
+            grant_tokens,
+            key_id: key_id.to_string(),
+            key_pair_spec,
+        };
+
+        let response = self
+            .backend
+            .generate_data_key_pair_without_plaintext(request)
+            .await?;
+
+        Ok(parse::data_key_pair_without_plaintext_response(response))
+    }
+
+    /// Encrypts  plaintext  into  ciphertext  by  using  a customer master key (CMK).
+    pub async fn encrypt(
+        &self,
+        key_id: String,
+        plaintext: Bytes,
+        encryption_context: Option<HashMap<String, String>>,
+        encryption_algorithm: Option<String>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<EncryptOutput, KmsRsError> {
+        let request = EncryptRequest {
+            key_id,
+            plaintext,
+            encryption_context,
+            encryption_algorithm,
+            grant_tokens,
+        };
+
+        let response = self.backend.encrypt(request).await?;
+
+        Ok(parse::encrypt_response(response))
+    }
+
+    /// Decrypts ciphertext that was encrypted by a AWS KMS customer master key (CMK) using any of the following operations: Encrypt, GenerateDataKey, GenerateDataKeyPair, GenerateDataKeyWithoutPlaintext, GenerateDataKeyPairWithoutPlaintext
+    pub async fn decrypt(
+        &self,
+        key_id: Option<String>,
+        ciphertext_blob: Bytes,
+        encryption_context: Option<HashMap<String, String>>,
+        encryption_algorithm: Option<String>,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<DecryptOutput, KmsRsError> {
+        let request = DecryptRequest {
+            key_id,
+            ciphertext_blob,
+            encryption_context,
+            encryption_algorithm,
+            grant_tokens,
+        };
+
+        let response = self.backend.decrypt(request).await?;
+
+        Ok(parse::decrypt_response(response))
+    }
+
+    /// Decrypts ciphertext and encrypts it again under another CMK (or the same CMK with another encryption
+    /// context) entirely within AWS KMS, so the plaintext is never exposed to the caller.
+    pub async fn re_encrypt(
+        &self,
+        ciphertext_blob: Bytes,
+        options: &ReEncryptOptions,
+    ) -> Result<ReEncryptOutput, KmsRsError> {
+        let request = options.to_request(ciphertext_blob);
+
+        let response = self.backend.re_encrypt(request).await?;
+
+        Ok(parse::re_encrypt_response(response))
+    }
+
+    /// Re-encrypts every ciphertext of `ciphertext_blobs` with the same `options`, running up to `concurrency`
+    /// ReEncrypt requests at a time. `progress` is called each time a ciphertext is done.
+    ///
+    /// A failure doesn't stop the other ciphertexts: the results are returned in input order, one per
+    /// ciphertext.
+    pub async fn re_encrypt_all<I, F>(
+        &self,
+        ciphertext_blobs: I,
+        options: &ReEncryptOptions,
+        concurrency: usize,
+        mut progress: F,
+    ) -> Vec<Result<ReEncryptOutput, KmsRsError>>
+    where
+        I: IntoIterator<Item = Bytes>,
+        F: FnMut(ReEncryptProgress),
+    {
+        let mut results = Vec::new();
+        let mut status = ReEncryptProgress::default();
+        let mut outputs = stream::iter(ciphertext_blobs.into_iter().enumerate())
+            .map(|(index, ciphertext_blob)| async move {
+                (index, self.re_encrypt(ciphertext_blob, options).await)
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        while let Some((index, result)) = outputs.next().await {
+            status.index = index;
+            status.completed += 1;
+            if result.is_err() {
+                status.failed += 1;
+            }
+            progress(status);
+
+            if results.len() <= index {
+                results.resize_with(index + 1, || None);
+            }
+            results[index] = Some(result);
+        }
+
+        results.into_iter().flatten().collect()
+    }
+
+    /// Creates a digital signature for a message or message digest by using the private key in an asymmetric CMK. To verify the signature, use the Verify operation, or use the public key in the same asymmetric CMK outside of AWS KMS.
+    pub async fn sign(
+        &self,
+        key_id: String,
+        message: Bytes,
+        message_type: Option<String>,
+        signing_algorithm: String,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<SignOutput, KmsRsError> {
+        let request = SignRequest {
+            key_id,
+            message,
+            message_type,
+            signing_algorithm,
+            grant_tokens,
+        };
+
+        let response = self.backend.sign(request).await?;
+
+        Ok(parse::sign_response(response))
+    }
+
+    /// Verifies a digital signature that was generated by the Sign operation.
+    pub async fn verify(
+        &self,
+        key_id: String,
+        message: Bytes,
+        message_type: Option<String>,
+        signature: Bytes,
+        signing_algorithm: String,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<VerifyOutput, KmsRsError> {
+        let request = VerifyRequest {
+            key_id,
+            message,
+            message_type,
+            signature,
+            signing_algorithm,
+            grant_tokens,
+        };
+
+        let response = self.backend.verify(request).await?;
+
+        Ok(parse::verify_response(response))
+    }
+
+    /// Signs a message of any size read from `reader`, such as a release tarball. The message is hashed locally
+    /// with the digest of `signing_algorithm` (SHA-256, SHA-384 or SHA-512) and only the digest is sent to
+    /// Sign, with `MessageType=DIGEST`, so the 4096-byte limit on messages does not apply.
+    pub async fn sign_reader<R>(
+        &self,
+        key_id: &str,
+        reader: R,
+        signing_algorithm: &str,
+    ) -> Result<SignOutput, KmsRsError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let digest = digest_reader(signing_algorithm, reader).await?;
+        self.sign(
+            key_id.to_string(),
+            Bytes::from(digest),
+            Some("DIGEST".to_string()),
+            signing_algorithm.to_string(),
+            None,
+        )
+        .await
+    }
+
+    /// Verifies a signature made by [`KmsRs::sign_reader`] (or by Sign over the whole message), hashing the
+    /// message read from `reader` locally and calling Verify with `MessageType=DIGEST`.
+    pub async fn verify_reader<R>(
+        &self,
+        key_id: &str,
+        reader: R,
+        signature: Bytes,
+        signing_algorithm: &str,
+    ) -> Result<VerifyOutput, KmsRsError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let digest = digest_reader(signing_algorithm, reader).await?;
+        self.verify(
+            key_id.to_string(),
+            Bytes::from(digest),
+            Some("DIGEST".to_string()),
+            signature,
+            signing_algorithm.to_string(),
+            None,
+        )
+        .await
+    }
+
+    /// Returns the public key of an asymmetric CMK. To quickly create a key to test with outside of this lib, run: `aws kms create-key --key-usage ENCRYPT_DECRYPT --customer-master-key-spec RSA_2048`
+    pub async fn get_public_key(
+        &self,
+        key_id: String,
+        grant_tokens: Option<Vec<String>>,
+    ) -> Result<PublicKey, KmsRsError> {
+        let request = GetPublicKeyRequest {
+            key_id,
+            grant_tokens,
+        };
+
+        let response = self.backend.get_public_key(request).await?;
+
+        Ok(parse::get_public_key_response(response))
+    }
+
+    /// Encrypts `plaintext` locally with the public key of an RSA `ENCRYPT_DECRYPT` CMK, fetched through
+    /// GetPublicKey. [`KmsRs::decrypt`] accepts the ciphertext given the CMK and the same
+    /// `encryption_algorithm`. To encrypt without any KMS call, store the [`PublicKey`] and use
+    /// [`PublicKey::encrypt`].
+    pub async fn encrypt_with_public_key(
+        &self,
+        key_id: &str,
+        plaintext: &[u8],
+        encryption_algorithm: &str,
+    ) -> Result<EncryptOutput, KmsRsError> {
+        self.get_public_key(key_id.to_string(), None)
+            .await?
+            .encrypt(plaintext, encryption_algorithm)
+    }
+
+    /// Returns a random byte string that is cryptographically secure. By default, the random byte string is generated in AWS KMS. To generate the byte string in the AWS CloudHSM cluster that is associated with a custom key store , specify the custom key store ID.
+    pub async fn generate_random(
+        &self,
+        number_of_bytes: i64,
+        custom_key_store_id: Option<String>,
+    ) -> Result<Bytes, KmsRsError> {
+        let request = GenerateRandomRequest {
+            number_of_bytes: Some(number_of_bytes),
+            custom_key_store_id,
+        };
+
+        let response = self.backend.generate_random(request).await?;
+
+        Ok(parse::generate_random_response(response))
+    }
+
+    /// Creates a custom key store associated with an active AWS CloudHSM cluster, given the cluster's trust
+    /// anchor certificate (PEM) and the password of its `kmsuser` crypto user, and returns its ID. The store
+    /// starts disconnected: see [`KmsRs::connect_custom_key_store`].
+    pub async fn create_custom_key_store(
+        &self,
+        custom_key_store_name: &str,
+        cloud_hsm_cluster_id: &str,
+        trust_anchor_certificate: &str,
+        key_store_password: &str,
+    ) -> Result<String, KmsRsError> {
+        let request = CreateCustomKeyStoreRequest {
+            cloud_hsm_cluster_id: cloud_hsm_cluster_id.to_string(),
+            custom_key_store_name: custom_key_store_name.to_string(),
+            key_store_password: key_store_password.to_string(),
+            trust_anchor_certificate: trust_anchor_certificate.to_string(),
+        };
+
+        let response = self.backend.create_custom_key_store(request).await?;
+
+        Ok(response.custom_key_store_id.unwrap_or_default())
+    }
 
-    let result = get_client().get_public_key(request).await;
+    /// Gets every custom key store in current AWS account and Region, following pagination markers until every page has been read.
+    pub async fn describe_custom_key_stores(
+        &self,
+    ) -> Result<DescribeCustomKeyStoresOutput, KmsRsError> {
+        let custom_key_stores = collect_pages(|marker| async move {
+            let request = DescribeCustomKeyStoresRequest {
+                custom_key_store_id: None,
+                custom_key_store_name: None,
+                limit: None,
+                marker,
+            };
+            let response = self.backend.describe_custom_key_stores(request).await?;
+            Ok((
+                response.custom_key_stores.unwrap_or_default(),
+                next_marker(response.next_marker, response.truncated),
+            ))
+        })
+        .await?;
 
-    match result {
-        Ok(response) => parse::get_public_key_response(response),
-        Err(value) => json!(value.to_string()),
+        Ok(parse::custom_key_store_entries(custom_key_stores))
     }
+
+    /// Provides detailed information about a custom key store, including its connection state.
+    pub async fn describe_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+    ) -> Result<CustomKeyStore, KmsRsError> {
+        let request = DescribeCustomKeyStoresRequest {
+            custom_key_store_id: Some(custom_key_store_id.to_string()),
+            custom_key_store_name: None,
+            limit: None,
+            marker: None,
+        };
+
+        let response = self.backend.describe_custom_key_stores(request).await?;
+
+        response
+            .custom_key_stores
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .map(parse::custom_key_store_entry)
+            .ok_or_else(|| {
+                KmsRsError::CustomKeyStoreNotFound(format!(
+                    "Custom key store {} does not exist",
+                    custom_key_store_id
+                ))
+            })
+    }
+
+    /// Starts connecting a custom key store to its CloudHSM cluster, which can take 20 minutes or more. Use
+    /// [`KmsRs::wait_for_custom_key_store`] to wait until it is connected.
+    pub async fn connect_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+    ) -> Result<(), KmsRsError> {
+        let request = ConnectCustomKeyStoreRequest {
+            custom_key_store_id: custom_key_store_id.to_string(),
+        };
+        self.backend.connect_custom_key_store(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Disconnects a custom key store from its CloudHSM cluster. Its CMKs are unusable until it is connected again.
+    pub async fn disconnect_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+    ) -> Result<(), KmsRsError> {
+        let request = DisconnectCustomKeyStoreRequest {
+            custom_key_store_id: custom_key_store_id.to_string(),
+        };
+        self.backend.disconnect_custom_key_store(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Renames a disconnected custom key store, updates the `kmsuser` password KMS uses, or associates it with
+    /// a related CloudHSM cluster.
+    pub async fn update_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+        options: UpdateCustomKeyStoreOptions,
+    ) -> Result<(), KmsRsError> {
+        let request = options.into_request(custom_key_store_id);
+        self.backend.update_custom_key_store(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Deletes a disconnected custom key store. Its CMKs must have been deleted first; the cluster is not affected.
+    pub async fn delete_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+    ) -> Result<(), KmsRsError> {
+        let request = DeleteCustomKeyStoreRequest {
+            custom_key_store_id: custom_key_store_id.to_string(),
+        };
+        self.backend.delete_custom_key_store(request).await?; // AWS gives an empty response
+
+        Ok(())
+    }
+
+    /// Describes a custom key store every `poll_interval` until its connection state is `CONNECTED` or
+    /// `FAILED`, then returns it. CMKs with `Origin=AWS_CLOUDHSM` can be created once it is connected.
+    ///
+    /// A store may still be `DISCONNECTED` right after [`KmsRs::connect_custom_key_store`], so that state
+    /// is polled like `CONNECTING`, until the store goes back to `DISCONNECTED` after `CONNECTING` (the
+    /// connection attempt ended).
+    ///
+    /// Fails with [`KmsRsError::CustomKeyStoreInvalidState`] if the store is `FAILED` (the message includes
+    /// its `ConnectionErrorCode`), disconnected again after connecting, or still not connected after
+    /// `timeout`.
+    pub async fn wait_for_custom_key_store(
+        &self,
+        custom_key_store_id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<CustomKeyStore, KmsRsError> {
+        let deadline = Instant::now() + timeout;
+        let mut connecting = false;
+
+        loop {
+            let custom_key_store = self.describe_custom_key_store(custom_key_store_id).await?;
+            match custom_key_store.connection_state.as_deref() {
+                Some("CONNECTED") => return Ok(custom_key_store),
+                Some("FAILED") => {
+                    return Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                        "Custom key store {} failed to connect: {}",
+                        custom_key_store_id,
+                        custom_key_store
+                            .connection_error_code
+                            .as_deref()
+                            .unwrap_or("unknown error")
+                    )))
+                }
+                Some("DISCONNECTED") if connecting => {
+                    return Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                        "Custom key store {} was disconnected while connecting",
+                        custom_key_store_id
+                    )))
+                }
+                state if Instant::now() + poll_interval > deadline => {
+                    return Err(KmsRsError::CustomKeyStoreInvalidState(format!(
+                        "Custom key store {} is still {} after {:?}",
+                        custom_key_store_id,
+                        state.unwrap_or_default(),
+                        timeout
+                    )))
+                }
+                state => {
+                    connecting |= state == Some("CONNECTING");
+                    tokio::time::sleep(poll_interval).await
+                }
+            }
+        }
+    }
+}
+
+/// The marker of the next page, if the response was truncated.
+fn next_marker(next_marker: Option<String>, truncated: Option<bool>) -> Option<String> {
+    next_marker.filter(|_| truncated.unwrap_or_default())
+}
+
+/// Streams the items of a paginated operation. `fetch` requests the page starting at the given marker
+/// (`None` for the first page) and returns its items along with the marker of the next page, if any.
+/// The next page is only requested once the previous one has been consumed.
+fn paginate<'a, T, F, Fut>(fetch: F) -> impl Stream<Item = Result<T, KmsRsError>> + Send + 'a
+where
+    T: Send + 'a,
+    F: Fn(Option<String>) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>), KmsRsError>> + Send + 'a,
+{
+    // The state is the marker of the next page to request, or None once the last page was read.
+    stream::try_unfold(Some(None), move |marker: Option<Option<String>>| {
+        let page = marker.map(&fetch);
+        async move {
+            match page {
+                Some(page) => {
+                    let (items, next_marker) = page.await?;
+                    Ok::<_, KmsRsError>(Some((
+                        stream::iter(items.into_iter().map(Ok)),
+                        next_marker.map(Some),
+                    )))
+                }
+                None => Ok(None),
+            }
+        }
+    })
+    .try_flatten()
+}
+
+/// Reads every page of a paginated operation, with `fetch` as in [`paginate`], and returns all their items.
+async fn collect_pages<T, F, Fut>(fetch: F) -> Result<Vec<T>, KmsRsError>
+where
+    T: Send,
+    F: Fn(Option<String>) -> Fut + Send,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>), KmsRsError>> + Send,
+{
+    paginate(fetch).try_collect().await
+}
+
+/// Hashes everything `reader` returns with the digest of a signing algorithm.
+async fn digest_reader<R>(signing_algorithm: &str, reader: R) -> Result<Vec<u8>, KmsRsError>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut hasher = crypto::MessageHasher::new(signing_algorithm)?;
+    hasher.update_from_reader(reader).await?;
+    Ok(hasher.finalize())
 }
 
-pub async fn generate_random(number_of_bytes: i64, custom_key_store_id: Option<String>) -> Value {
-    let request = GenerateRandomRequest {
-        number_of_bytes: Some(number_of_bytes),
-        custom_key_store_id,
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use rusoto_core::Region;
+    use rusoto_mock::{
+        MockCredentialsProvider, MockRequestDispatcher, MultipleMockRequestDispatcher,
     };
 
-    match get_client().generate_random(request).await {
-        Ok(response) => parse::generate_random_response(response),
-        Err(err) => json!(err.to_string()),
+    pub(crate) fn mock_kms(responses: Vec<&str>) -> KmsRs {
+        let dispatchers: Vec<MockRequestDispatcher> = responses
+            .into_iter()
+            .map(|body| MockRequestDispatcher::default().with_body(body))
+            .collect();
+        KmsRs::builder()
+            .region(Region::UsEast1)
+            .credentials(MockCredentialsProvider)
+            .dispatcher(MultipleMockRequestDispatcher::new(dispatchers))
+            .build()
+            .unwrap()
+    }
+
+    /// Generates a data key pair under a new CMK of `kms`, without plaintext.
+    pub(crate) async fn data_key_pair(
+        kms: &KmsRs,
+        key_pair_spec: &str,
+        encryption_context: Option<HashMap<String, String>>,
+    ) -> DataKeyPair {
+        let key_id = kms.create_key(Default::default()).await.unwrap().key_id;
+        kms.generate_data_key_pair_without_plaintext(
+            &key_id,
+            key_pair_spec.to_string(),
+            encryption_context,
+            None,
+        )
+        .await
+        .unwrap()
+    }
+
+    const FIRST_PAGE: &str = r#"{
+        "Keys": [
+            {"KeyId": "abcd-4321-wxyz", "KeyArn": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz"}
+        ],
+        "NextMarker": "page-2",
+        "Truncated": true
+    }"#;
+
+    const LAST_PAGE: &str = r#"{
+        "Keys": [
+            {"KeyId": "efgh-8765-stuv", "KeyArn": "arn:aws:kms:us-east-1:123456789:key/efgh-8765-stuv"}
+        ],
+        "Truncated": false
+    }"#;
+
+    #[tokio::test]
+    async fn test_list_keys_follows_markers() {
+        let kms = mock_kms(vec![FIRST_PAGE, LAST_PAGE]);
+        let output = kms.list_keys().await.unwrap();
+        let key_ids: Vec<&str> = output.keys.iter().map(|key| key.key_id.as_str()).collect();
+        assert_eq!(key_ids, vec!["abcd-4321-wxyz", "efgh-8765-stuv"]);
+    }
+
+    #[tokio::test]
+    async fn test_key_stream_reads_every_page() {
+        let kms = mock_kms(vec![FIRST_PAGE, LAST_PAGE]);
+        let keys: Vec<Result<KeyListEntry, KmsRsError>> = kms.key_stream(Some(1)).collect().await;
+        let key_ids: Vec<String> = keys.into_iter().map(|key| key.unwrap().key_id).collect();
+        assert_eq!(key_ids, vec!["abcd-4321-wxyz", "efgh-8765-stuv"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_aliases_follows_markers() {
+        let kms = mock_kms(vec![
+            r#"{
+                "Aliases": [
+                    {"AliasName": "alias/prod", "AliasArn": "arn:aws:kms:us-east-1:123456789:alias/prod", "TargetKeyId": "abcd-4321-wxyz"}
+                ],
+                "NextMarker": "page-2",
+                "Truncated": true
+            }"#,
+            r#"{
+                "Aliases": [
+                    {"AliasName": "alias/aws/s3", "AliasArn": "arn:aws:kms:us-east-1:123456789:alias/aws/s3"}
+                ],
+                "Truncated": false
+            }"#,
+        ]);
+        let output = kms.list_aliases(None).await.unwrap();
+        let aliases: Vec<(&str, Option<&str>)> = output
+            .aliases
+            .iter()
+            .map(|alias| (alias.alias_name.as_str(), alias.target_key_id.as_deref()))
+            .collect();
+        assert_eq!(
+            aliases,
+            vec![
+                ("alias/prod", Some("abcd-4321-wxyz")),
+                ("alias/aws/s3", None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_custom_key_store_fails_with_error_code() {
+        let kms = mock_kms(vec![
+            r#"{"CustomKeyStores": [{"CustomKeyStoreId": "cks-1234567890abcdef0", "CustomKeyStoreName": "ExampleKeyStore", "ConnectionState": "CONNECTING"}]}"#,
+            r#"{"CustomKeyStores": [{"CustomKeyStoreId": "cks-1234567890abcdef0", "CustomKeyStoreName": "ExampleKeyStore", "ConnectionState": "FAILED", "ConnectionErrorCode": "INVALID_CREDENTIALS"}]}"#,
+        ]);
+        match kms
+            .wait_for_custom_key_store(
+                "cks-1234567890abcdef0",
+                Duration::from_millis(1),
+                Duration::from_secs(1),
+            )
+            .await
+        {
+            Err(KmsRsError::CustomKeyStoreInvalidState(message)) => {
+                assert!(message.contains("INVALID_CREDENTIALS"))
+            }
+            other => panic!(
+                "Expected a CustomKeyStoreInvalidState error, got {:?}",
+                other
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_with_public_key_decrypts_in_kms() {
+        let kms = KmsRs::from_backend(crate::InMemoryKms::new());
+        let key_id = kms
+            .create_key(
+                CreateKeyOptions::new()
+                    .key_usage("ENCRYPT_DECRYPT")
+                    .customer_master_key_spec("RSA_2048"),
+            )
+            .await
+            .unwrap()
+            .key_id;
+
+        let output = kms
+            .encrypt_with_public_key(&key_id, b"sensor reading", "RSAES_OAEP_SHA_256")
+            .await
+            .unwrap();
+        let decrypted = kms
+            .decrypt(
+                Some(key_id.clone()),
+                output.ciphertext_blob,
+                None,
+                Some(output.encryption_algorithm),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(&decrypted.plaintext[..], b"sensor reading");
+
+        // A stored public key encrypts without the client.
+        let public_key: PublicKey = serde_json::from_str(
+            &serde_json::to_string(&kms.get_public_key(key_id.clone(), None).await.unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        let output = public_key.encrypt(b"offline", "RSAES_OAEP_SHA_1").unwrap();
+        let decrypted = kms
+            .decrypt(
+                Some(key_id),
+                output.ciphertext_blob,
+                None,
+                Some("RSAES_OAEP_SHA_1".to_string()),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(&decrypted.plaintext[..], b"offline");
+
+        match public_key.encrypt(b"offline", "SYMMETRIC_DEFAULT") {
+            Err(KmsRsError::InvalidKeyUsage(_)) => (),
+            other => panic!("Expected an InvalidKeyUsage error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify_reader() {
+        let kms = KmsRs::from_backend(crate::InMemoryKms::new());
+        let key_id = kms
+            .create_key(
+                CreateKeyOptions::new()
+                    .key_usage("SIGN_VERIFY")
+                    .customer_master_key_spec("ECC_NIST_P384"),
+            )
+            .await
+            .unwrap()
+            .key_id;
+        // Larger than both the read buffer and the 4096-byte limit on RAW messages.
+        let tarball: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        let signature = kms
+            .sign_reader(&key_id, &tarball[..], "ECDSA_SHA_384")
+            .await
+            .unwrap()
+            .signature;
+        let output = kms
+            .verify_reader(&key_id, &tarball[..], signature.clone(), "ECDSA_SHA_384")
+            .await
+            .unwrap();
+        assert!(output.signature_valid);
+        let digest = crypto::digest("ECDSA_SHA_384", &tarball).unwrap();
+        kms.verify(
+            key_id.clone(),
+            Bytes::from(digest),
+            Some("DIGEST".to_string()),
+            signature.clone(),
+            "ECDSA_SHA_384".to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        match kms
+            .verify_reader(&key_id, &tarball[1..], signature, "ECDSA_SHA_384")
+            .await
+        {
+            Err(KmsRsError::KmsInvalidSignature(_)) => (),
+            other => panic!("Expected a KmsInvalidSignature error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keys_without_rotation_ignores_empty_custom_key_store_id() {
+        let kms = mock_kms(vec![
+            r#"{"Keys": [{"KeyId": "abcd-4321-wxyz", "KeyArn": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz"}], "Truncated": false}"#,
+            r#"{"KeyMetadata": {"KeyId": "abcd-4321-wxyz", "Arn": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz", "Enabled": true, "KeyState": "Enabled", "KeyManager": "CUSTOMER", "KeySpec": "SYMMETRIC_DEFAULT", "CustomerMasterKeySpec": "SYMMETRIC_DEFAULT", "Origin": "AWS_KMS", "CustomKeyStoreId": ""}}"#,
+            r#"{"KeyRotationEnabled": true}"#,
+        ]);
+        assert!(kms.keys_without_rotation().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_custom_key_store_polls_while_disconnected() {
+        let kms = mock_kms(vec![
+            r#"{"CustomKeyStores": [{"CustomKeyStoreId": "cks-1234567890abcdef0", "CustomKeyStoreName": "ExampleKeyStore", "ConnectionState": "DISCONNECTED"}]}"#,
+            r#"{"CustomKeyStores": [{"CustomKeyStoreId": "cks-1234567890abcdef0", "CustomKeyStoreName": "ExampleKeyStore", "ConnectionState": "CONNECTING"}]}"#,
+            r#"{"CustomKeyStores": [{"CustomKeyStoreId": "cks-1234567890abcdef0", "CustomKeyStoreName": "ExampleKeyStore", "ConnectionState": "CONNECTED"}]}"#,
+        ]);
+        let custom_key_store = kms
+            .wait_for_custom_key_store(
+                "cks-1234567890abcdef0",
+                Duration::from_millis(1),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+        assert!(custom_key_store.is_connected());
     }
 }
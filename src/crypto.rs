@@ -0,0 +1,633 @@
+//! The asymmetric cryptography AWS KMS performs, done locally: key pair generation, signing, verification,
+//! RSA encryption and key encapsulation for hybrid encryption, using the same key specs, algorithms and
+//! encodings as KMS.
+//!
+//! Private keys are PKCS #8 DER and public keys `SubjectPublicKeyInfo` DER, like GenerateDataKeyPair and
+//! GetPublicKey. ECDSA signatures are DER-encoded and RSASSA-PSS uses a salt as long as the digest.
+
+use p256::elliptic_curve::{self, ecdh::diffie_hellman, CurveArithmetic, SecretKey};
+use rand_core::{OsRng, RngCore};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use rsa::{Oaep, Pkcs1v15Sign, Pss, RsaPrivateKey, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use zeroize::Zeroizing;
+
+use crate::error::KmsRsError;
+
+/// How much of a message is read at a time when hashing it from a reader.
+const READ_BUFFER_BYTES: usize = 64 * 1024;
+
+/// The length of the keys [`PublicKey::encapsulate`] produces, for AES-256.
+const KEM_KEY_BYTES: usize = 32;
+
+/// How RSA public keys encapsulate keys.
+const KEM_RSA_ALGORITHM: &str = "RSAES_OAEP_SHA_256";
+
+/// The HKDF info prefix of keys derived from an ECDH agreement.
+const KEM_INFO: &[u8] = b"kms_rs hybrid encryption";
+
+/// The signing algorithms KMS supports for a key spec.
+pub(crate) fn signing_algorithms(key_spec: &str) -> Vec<String> {
+    let algorithms: &[&str] = match key_spec {
+        "RSA_2048" | "RSA_3072" | "RSA_4096" => &[
+            "RSASSA_PSS_SHA_256",
+            "RSASSA_PSS_SHA_384",
+            "RSASSA_PSS_SHA_512",
+            "RSASSA_PKCS1_V1_5_SHA_256",
+            "RSASSA_PKCS1_V1_5_SHA_384",
+            "RSASSA_PKCS1_V1_5_SHA_512",
+        ],
+        "ECC_NIST_P256" | "ECC_SECG_P256K1" => &["ECDSA_SHA_256"],
+        "ECC_NIST_P384" => &["ECDSA_SHA_384"],
+        "ECC_NIST_P521" => &["ECDSA_SHA_512"],
+        _ => &[],
+    };
+    algorithms
+        .iter()
+        .map(|algorithm| algorithm.to_string())
+        .collect()
+}
+
+/// The encryption algorithms KMS supports for a key spec.
+pub(crate) fn encryption_algorithms(key_spec: &str) -> Vec<String> {
+    let algorithms: &[&str] = match key_spec {
+        "SYMMETRIC_DEFAULT" => &["SYMMETRIC_DEFAULT"],
+        "RSA_2048" | "RSA_3072" | "RSA_4096" => &["RSAES_OAEP_SHA_1", "RSAES_OAEP_SHA_256"],
+        _ => &[],
+    };
+    algorithms
+        .iter()
+        .map(|algorithm| algorithm.to_string())
+        .collect()
+}
+
+/// Hashes `message` with the digest of a signing algorithm (SHA-256, SHA-384 or SHA-512).
+pub(crate) fn digest(signing_algorithm: &str, message: &[u8]) -> Result<Vec<u8>, KmsRsError> {
+    Ok(match digest_len(signing_algorithm)? {
+        32 => Sha256::digest(message).to_vec(),
+        48 => Sha384::digest(message).to_vec(),
+        _ => Sha512::digest(message).to_vec(),
+    })
+}
+
+/// Hashes a message piece by piece with the digest of a signing algorithm, like [`digest`].
+pub(crate) enum MessageHasher {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl MessageHasher {
+    pub(crate) fn new(signing_algorithm: &str) -> Result<MessageHasher, KmsRsError> {
+        Ok(match digest_len(signing_algorithm)? {
+            32 => MessageHasher::Sha256(Sha256::new()),
+            48 => MessageHasher::Sha384(Sha384::new()),
+            _ => MessageHasher::Sha512(Sha512::new()),
+        })
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            MessageHasher::Sha256(hasher) => hasher.update(bytes),
+            MessageHasher::Sha384(hasher) => hasher.update(bytes),
+            MessageHasher::Sha512(hasher) => hasher.update(bytes),
+        }
+    }
+
+    /// Hashes everything `reader` returns, until it reaches the end.
+    pub(crate) async fn update_from_reader<R>(&mut self, mut reader: R) -> Result<(), KmsRsError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut buffer = vec![0u8; READ_BUFFER_BYTES];
+        loop {
+            match reader.read(&mut buffer).await {
+                Ok(0) => return Ok(()),
+                Ok(read) => self.update(&buffer[..read]),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(KmsRsError::Io(err.to_string())),
+            }
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Vec<u8> {
+        match self {
+            MessageHasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            MessageHasher::Sha384(hasher) => hasher.finalize().to_vec(),
+            MessageHasher::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// The length in bytes of the digest a signing algorithm signs.
+pub(crate) fn digest_len(signing_algorithm: &str) -> Result<usize, KmsRsError> {
+    if signing_algorithm.ends_with("SHA_256") {
+        Ok(32)
+    } else if signing_algorithm.ends_with("SHA_384") {
+        Ok(48)
+    } else if signing_algorithm.ends_with("SHA_512") {
+        Ok(64)
+    } else {
+        Err(unsupported_algorithm(signing_algorithm))
+    }
+}
+
+fn unsupported_algorithm(algorithm: &str) -> KmsRsError {
+    KmsRsError::Validation(format!("Unsupported algorithm {}", algorithm))
+}
+
+fn crypto_error<E: std::fmt::Display>(err: E) -> KmsRsError {
+    KmsRsError::Crypto(err.to_string())
+}
+
+fn check_digest(signing_algorithm: &str, digest: &[u8]) -> Result<(), KmsRsError> {
+    let expected = digest_len(signing_algorithm)?;
+    if digest.len() != expected {
+        return Err(KmsRsError::Validation(format!(
+            "Digest is {} bytes but {} requires {} bytes",
+            digest.len(),
+            signing_algorithm,
+            expected
+        )));
+    }
+    Ok(())
+}
+
+fn pss(signing_algorithm: &str) -> Option<Pss> {
+    match signing_algorithm {
+        "RSASSA_PSS_SHA_256" => Some(Pss::new::<Sha256>()),
+        "RSASSA_PSS_SHA_384" => Some(Pss::new::<Sha384>()),
+        "RSASSA_PSS_SHA_512" => Some(Pss::new::<Sha512>()),
+        _ => None,
+    }
+}
+
+fn pkcs1v15(signing_algorithm: &str) -> Option<Pkcs1v15Sign> {
+    match signing_algorithm {
+        "RSASSA_PKCS1_V1_5_SHA_256" => Some(Pkcs1v15Sign::new::<Sha256>()),
+        "RSASSA_PKCS1_V1_5_SHA_384" => Some(Pkcs1v15Sign::new::<Sha384>()),
+        "RSASSA_PKCS1_V1_5_SHA_512" => Some(Pkcs1v15Sign::new::<Sha512>()),
+        _ => None,
+    }
+}
+
+fn oaep(encryption_algorithm: &str) -> Result<Oaep, KmsRsError> {
+    match encryption_algorithm {
+        "RSAES_OAEP_SHA_1" => Ok(Oaep::new::<Sha1>()),
+        "RSAES_OAEP_SHA_256" => Ok(Oaep::new::<Sha256>()),
+        _ => Err(unsupported_algorithm(encryption_algorithm)),
+    }
+}
+
+/// The ECDSA algorithm each curve is used with.
+fn check_ecdsa(signing_algorithm: &str, expected: &str) -> Result<(), KmsRsError> {
+    if signing_algorithm == expected {
+        Ok(())
+    } else {
+        Err(unsupported_algorithm(signing_algorithm))
+    }
+}
+
+/// The private half of an asymmetric key. Zeroized on drop.
+#[derive(Clone)]
+pub(crate) enum PrivateKey {
+    Rsa(Box<RsaPrivateKey>),
+    P256(p256::SecretKey),
+    P384(p384::SecretKey),
+    P521(p521::SecretKey),
+    Secp256k1(k256::SecretKey),
+}
+
+impl PrivateKey {
+    /// Generates a key pair for a KMS key spec such as `RSA_2048` or `ECC_NIST_P256`.
+    pub(crate) fn generate(key_spec: &str) -> Result<PrivateKey, KmsRsError> {
+        let rsa = |bits| {
+            RsaPrivateKey::new(&mut OsRng, bits)
+                .map(|key| PrivateKey::Rsa(Box::new(key)))
+                .map_err(crypto_error)
+        };
+        match key_spec {
+            "RSA_2048" => rsa(2048),
+            "RSA_3072" => rsa(3072),
+            "RSA_4096" => rsa(4096),
+            "ECC_NIST_P256" => Ok(PrivateKey::P256(p256::SecretKey::random(&mut OsRng))),
+            "ECC_NIST_P384" => Ok(PrivateKey::P384(p384::SecretKey::random(&mut OsRng))),
+            "ECC_NIST_P521" => Ok(PrivateKey::P521(p521::SecretKey::random(&mut OsRng))),
+            "ECC_SECG_P256K1" => Ok(PrivateKey::Secp256k1(k256::SecretKey::random(&mut OsRng))),
+            _ => Err(KmsRsError::Validation(format!(
+                "Unsupported key spec {}",
+                key_spec
+            ))),
+        }
+    }
+
+    /// Parses a PKCS #8 DER private key of a KMS key spec, as returned by GenerateDataKeyPair.
+    pub(crate) fn from_pkcs8_der(key_spec: &str, der: &[u8]) -> Result<PrivateKey, KmsRsError> {
+        match key_spec {
+            "RSA_2048" | "RSA_3072" | "RSA_4096" => RsaPrivateKey::from_pkcs8_der(der)
+                .map(|key| PrivateKey::Rsa(Box::new(key)))
+                .map_err(crypto_error),
+            "ECC_NIST_P256" => p256::SecretKey::from_pkcs8_der(der)
+                .map(PrivateKey::P256)
+                .map_err(crypto_error),
+            "ECC_NIST_P384" => p384::SecretKey::from_pkcs8_der(der)
+                .map(PrivateKey::P384)
+                .map_err(crypto_error),
+            "ECC_NIST_P521" => p521::SecretKey::from_pkcs8_der(der)
+                .map(PrivateKey::P521)
+                .map_err(crypto_error),
+            "ECC_SECG_P256K1" => k256::SecretKey::from_pkcs8_der(der)
+                .map(PrivateKey::Secp256k1)
+                .map_err(crypto_error),
+            _ => Err(KmsRsError::Validation(format!(
+                "Unsupported key spec {}",
+                key_spec
+            ))),
+        }
+    }
+
+    /// The PKCS #8 DER encoding of this key.
+    pub(crate) fn to_pkcs8_der(&self) -> Result<Zeroizing<Vec<u8>>, KmsRsError> {
+        let document = match self {
+            PrivateKey::Rsa(key) => key.to_pkcs8_der(),
+            PrivateKey::P256(key) => key.to_pkcs8_der(),
+            PrivateKey::P384(key) => key.to_pkcs8_der(),
+            PrivateKey::P521(key) => key.to_pkcs8_der(),
+            PrivateKey::Secp256k1(key) => key.to_pkcs8_der(),
+        }
+        .map_err(crypto_error)?;
+        Ok(Zeroizing::new(document.as_bytes().to_vec()))
+    }
+
+    /// The public half of this key.
+    pub(crate) fn public_key(&self) -> PublicKey {
+        match self {
+            PrivateKey::Rsa(key) => PublicKey::Rsa(RsaPublicKey::from(key.as_ref())),
+            PrivateKey::P256(key) => PublicKey::P256(key.public_key()),
+            PrivateKey::P384(key) => PublicKey::P384(key.public_key()),
+            PrivateKey::P521(key) => PublicKey::P521(key.public_key()),
+            PrivateKey::Secp256k1(key) => PublicKey::Secp256k1(key.public_key()),
+        }
+    }
+
+    /// Signs a digest computed with the hash of `signing_algorithm`.
+    pub(crate) fn sign_digest(
+        &self,
+        signing_algorithm: &str,
+        digest: &[u8],
+    ) -> Result<Vec<u8>, KmsRsError> {
+        use p256::ecdsa::signature::hazmat::PrehashSigner;
+
+        check_digest(signing_algorithm, digest)?;
+        match self {
+            PrivateKey::Rsa(key) => if let Some(pss) = pss(signing_algorithm) {
+                key.sign_with_rng(&mut OsRng, pss, digest)
+            } else if let Some(pkcs1v15) = pkcs1v15(signing_algorithm) {
+                key.sign(pkcs1v15, digest)
+            } else {
+                return Err(unsupported_algorithm(signing_algorithm));
+            }
+            .map_err(crypto_error),
+            PrivateKey::P256(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_256")?;
+                let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(key)
+                    .sign_prehash(digest)
+                    .map_err(crypto_error)?;
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+            PrivateKey::P384(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_384")?;
+                let signature: p384::ecdsa::Signature = p384::ecdsa::SigningKey::from(key)
+                    .sign_prehash(digest)
+                    .map_err(crypto_error)?;
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+            PrivateKey::P521(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_512")?;
+                let signature: p521::ecdsa::Signature =
+                    p521::ecdsa::SigningKey::from_bytes(&key.to_bytes())
+                        .and_then(|signing_key| signing_key.sign_prehash(digest))
+                        .map_err(crypto_error)?;
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+            PrivateKey::Secp256k1(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_256")?;
+                let signature: k256::ecdsa::Signature = k256::ecdsa::SigningKey::from(key)
+                    .sign_prehash(digest)
+                    .map_err(crypto_error)?;
+                Ok(signature.to_der().as_bytes().to_vec())
+            }
+        }
+    }
+
+    /// Decrypts a ciphertext produced with the RSA public key.
+    pub(crate) fn decrypt(
+        &self,
+        encryption_algorithm: &str,
+        ciphertext: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, KmsRsError> {
+        match self {
+            PrivateKey::Rsa(key) => key
+                .decrypt(oaep(encryption_algorithm)?, ciphertext)
+                .map(Zeroizing::new)
+                .map_err(|_| {
+                    KmsRsError::InvalidCiphertext("The ciphertext is invalid".to_string())
+                }),
+            _ => Err(unsupported_algorithm(encryption_algorithm)),
+        }
+    }
+
+    /// Recovers the 32-byte key that [`PublicKey::encapsulate`] produced `encapsulated_key` for.
+    pub(crate) fn decapsulate(
+        &self,
+        encapsulated_key: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, KmsRsError> {
+        let recipient = self.public_key().to_der()?;
+        let invalid =
+            || KmsRsError::InvalidCiphertext("The encapsulated key is invalid".to_string());
+        let key = match (self, PublicKey::from_der(encapsulated_key)) {
+            (PrivateKey::Rsa(_), _) => self.decrypt(KEM_RSA_ALGORITHM, encapsulated_key)?,
+            (PrivateKey::P256(key), Ok(PublicKey::P256(ephemeral))) => {
+                derive_kem_key(key, &ephemeral, encapsulated_key, &recipient)
+            }
+            (PrivateKey::P384(key), Ok(PublicKey::P384(ephemeral))) => {
+                derive_kem_key(key, &ephemeral, encapsulated_key, &recipient)
+            }
+            (PrivateKey::P521(key), Ok(PublicKey::P521(ephemeral))) => {
+                derive_kem_key(key, &ephemeral, encapsulated_key, &recipient)
+            }
+            (PrivateKey::Secp256k1(key), Ok(PublicKey::Secp256k1(ephemeral))) => {
+                derive_kem_key(key, &ephemeral, encapsulated_key, &recipient)
+            }
+            _ => return Err(invalid()),
+        };
+        if key.len() != KEM_KEY_BYTES {
+            return Err(invalid());
+        }
+        Ok(key)
+    }
+}
+
+/// The public half of an asymmetric key.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PublicKey {
+    Rsa(RsaPublicKey),
+    P256(p256::PublicKey),
+    P384(p384::PublicKey),
+    P521(p521::PublicKey),
+    Secp256k1(k256::PublicKey),
+}
+
+impl PublicKey {
+    /// Parses a DER-encoded `SubjectPublicKeyInfo`, as returned by GetPublicKey.
+    pub(crate) fn from_der(der: &[u8]) -> Result<PublicKey, KmsRsError> {
+        if let Ok(key) = RsaPublicKey::from_public_key_der(der) {
+            return Ok(PublicKey::Rsa(key));
+        }
+        if let Ok(key) = p256::PublicKey::from_public_key_der(der) {
+            return Ok(PublicKey::P256(key));
+        }
+        if let Ok(key) = p384::PublicKey::from_public_key_der(der) {
+            return Ok(PublicKey::P384(key));
+        }
+        if let Ok(key) = p521::PublicKey::from_public_key_der(der) {
+            return Ok(PublicKey::P521(key));
+        }
+        k256::PublicKey::from_public_key_der(der)
+            .map(PublicKey::Secp256k1)
+            .map_err(|_| KmsRsError::Crypto("Unsupported public key".to_string()))
+    }
+
+    /// The DER-encoded `SubjectPublicKeyInfo` of this key.
+    pub(crate) fn to_der(&self) -> Result<Vec<u8>, KmsRsError> {
+        match self {
+            PublicKey::Rsa(key) => key.to_public_key_der(),
+            PublicKey::P256(key) => key.to_public_key_der(),
+            PublicKey::P384(key) => key.to_public_key_der(),
+            PublicKey::P521(key) => key.to_public_key_der(),
+            PublicKey::Secp256k1(key) => key.to_public_key_der(),
+        }
+        .map(|document| document.as_bytes().to_vec())
+        .map_err(crypto_error)
+    }
+
+    /// Whether `signature` is a valid signature of `digest` under `signing_algorithm`. Fails if the algorithm
+    /// or digest length does not fit the key.
+    pub(crate) fn verify_digest(
+        &self,
+        signing_algorithm: &str,
+        digest: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, KmsRsError> {
+        use p256::ecdsa::signature::hazmat::PrehashVerifier;
+
+        check_digest(signing_algorithm, digest)?;
+        match self {
+            PublicKey::Rsa(key) => {
+                if let Some(pss) = pss(signing_algorithm) {
+                    Ok(key.verify(pss, digest, signature).is_ok())
+                } else if let Some(pkcs1v15) = pkcs1v15(signing_algorithm) {
+                    Ok(key.verify(pkcs1v15, digest, signature).is_ok())
+                } else {
+                    Err(unsupported_algorithm(signing_algorithm))
+                }
+            }
+            PublicKey::P256(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_256")?;
+                Ok(p256::ecdsa::Signature::from_der(signature)
+                    .and_then(|signature| {
+                        p256::ecdsa::VerifyingKey::from(key).verify_prehash(digest, &signature)
+                    })
+                    .is_ok())
+            }
+            PublicKey::P384(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_384")?;
+                Ok(p384::ecdsa::Signature::from_der(signature)
+                    .and_then(|signature| {
+                        p384::ecdsa::VerifyingKey::from(key).verify_prehash(digest, &signature)
+                    })
+                    .is_ok())
+            }
+            PublicKey::P521(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_512")?;
+                Ok(p521::ecdsa::Signature::from_der(signature)
+                    .and_then(|signature| {
+                        p521::ecdsa::VerifyingKey::from_affine(*key.as_affine())?
+                            .verify_prehash(digest, &signature)
+                    })
+                    .is_ok())
+            }
+            PublicKey::Secp256k1(key) => {
+                check_ecdsa(signing_algorithm, "ECDSA_SHA_256")?;
+                // KMS does not normalize secp256k1 signatures to a low S value, but k256 only accepts those.
+                Ok(k256::ecdsa::Signature::from_der(signature)
+                    .and_then(|signature| {
+                        let signature = signature.normalize_s().unwrap_or(signature);
+                        k256::ecdsa::VerifyingKey::from(key).verify_prehash(digest, &signature)
+                    })
+                    .is_ok())
+            }
+        }
+    }
+
+    /// Encrypts `plaintext` with RSAES-OAEP, as KMS Encrypt does with an RSA key.
+    pub(crate) fn encrypt(
+        &self,
+        encryption_algorithm: &str,
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, KmsRsError> {
+        match self {
+            PublicKey::Rsa(key) => key
+                .encrypt(&mut OsRng, oaep(encryption_algorithm)?, plaintext)
+                .map_err(|err| KmsRsError::Validation(err.to_string())),
+            _ => Err(unsupported_algorithm(encryption_algorithm)),
+        }
+    }
+
+    /// Generates a random 32-byte key only the private half of this key can recover, returning it along with
+    /// its encapsulation. RSA keys wrap it with RSAES-OAEP-SHA-256; ECC keys derive it with HKDF-SHA-256 from
+    /// an ECDH agreement with an ephemeral key pair, whose public key is the encapsulation.
+    pub(crate) fn encapsulate(&self) -> Result<(Zeroizing<Vec<u8>>, Vec<u8>), KmsRsError> {
+        let recipient = self.to_der()?;
+        let ephemeral = match self {
+            PublicKey::Rsa(_) => {
+                let mut key = Zeroizing::new(vec![0u8; KEM_KEY_BYTES]);
+                OsRng.fill_bytes(&mut key);
+                let encapsulated_key = self.encrypt(KEM_RSA_ALGORITHM, &key)?;
+                return Ok((key, encapsulated_key));
+            }
+            PublicKey::P256(_) => PrivateKey::generate("ECC_NIST_P256")?,
+            PublicKey::P384(_) => PrivateKey::generate("ECC_NIST_P384")?,
+            PublicKey::P521(_) => PrivateKey::generate("ECC_NIST_P521")?,
+            PublicKey::Secp256k1(_) => PrivateKey::generate("ECC_SECG_P256K1")?,
+        };
+        let encapsulated_key = ephemeral.public_key().to_der()?;
+        let key = match (&ephemeral, self) {
+            (PrivateKey::P256(secret), PublicKey::P256(public)) => {
+                derive_kem_key(secret, public, &encapsulated_key, &recipient)
+            }
+            (PrivateKey::P384(secret), PublicKey::P384(public)) => {
+                derive_kem_key(secret, public, &encapsulated_key, &recipient)
+            }
+            (PrivateKey::P521(secret), PublicKey::P521(public)) => {
+                derive_kem_key(secret, public, &encapsulated_key, &recipient)
+            }
+            (PrivateKey::Secp256k1(secret), PublicKey::Secp256k1(public)) => {
+                derive_kem_key(secret, public, &encapsulated_key, &recipient)
+            }
+            _ => unreachable!("the ephemeral key is generated on the recipient's curve"),
+        };
+        Ok((key, encapsulated_key))
+    }
+}
+
+/// Derives the key shared by an ECDH agreement, bound to both public keys (as DER) through the HKDF info.
+fn derive_kem_key<C>(
+    secret: &SecretKey<C>,
+    public: &elliptic_curve::PublicKey<C>,
+    encapsulated_key: &[u8],
+    recipient: &[u8],
+) -> Zeroizing<Vec<u8>>
+where
+    C: CurveArithmetic,
+{
+    let shared_secret = diffie_hellman(secret.to_nonzero_scalar(), public.as_affine());
+    let info = [KEM_INFO, encapsulated_key, recipient].concat();
+    let mut key = Zeroizing::new(vec![0u8; KEM_KEY_BYTES]);
+    shared_secret
+        .extract::<Sha256>(None)
+        .expand(&info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA-256 output length");
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign_and_verify(key_spec: &str) {
+        let private_key = PrivateKey::generate(key_spec).unwrap();
+        let public_key = private_key.public_key();
+        for algorithm in signing_algorithms(key_spec) {
+            let digest = digest(&algorithm, b"message").unwrap();
+            let signature = private_key.sign_digest(&algorithm, &digest).unwrap();
+            assert!(public_key
+                .verify_digest(&algorithm, &digest, &signature)
+                .unwrap());
+            let other = super::digest(&algorithm, b"other message").unwrap();
+            assert!(!public_key
+                .verify_digest(&algorithm, &other, &signature)
+                .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_ecc() {
+        for key_spec in &[
+            "ECC_NIST_P256",
+            "ECC_NIST_P384",
+            "ECC_NIST_P521",
+            "ECC_SECG_P256K1",
+        ] {
+            sign_and_verify(key_spec);
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_rsa() {
+        sign_and_verify("RSA_2048");
+    }
+
+    #[test]
+    fn test_rsa_encrypt_and_decrypt() {
+        let private_key = PrivateKey::generate("RSA_2048").unwrap();
+        for algorithm in encryption_algorithms("RSA_2048") {
+            let ciphertext = private_key
+                .public_key()
+                .encrypt(&algorithm, b"secret")
+                .unwrap();
+            assert_eq!(
+                private_key
+                    .decrypt(&algorithm, &ciphertext)
+                    .unwrap()
+                    .as_slice(),
+                b"secret"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_wrong_digest_length() {
+        let private_key = PrivateKey::generate("ECC_NIST_P256").unwrap();
+        match private_key.sign_digest("ECDSA_SHA_256", &[0; 20]) {
+            Err(KmsRsError::Validation(_)) => (),
+            other => panic!("Expected a Validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_encapsulate_and_decapsulate() {
+        for key_spec in &[
+            "RSA_2048",
+            "ECC_NIST_P256",
+            "ECC_NIST_P521",
+            "ECC_SECG_P256K1",
+        ] {
+            let der = PrivateKey::generate(key_spec)
+                .unwrap()
+                .to_pkcs8_der()
+                .unwrap();
+            let private_key = PrivateKey::from_pkcs8_der(key_spec, &der).unwrap();
+            let (key, encapsulated_key) = private_key.public_key().encapsulate().unwrap();
+            assert_eq!(key.len(), 32);
+            assert_eq!(private_key.decapsulate(&encapsulated_key).unwrap(), key);
+
+            let other = PrivateKey::generate(key_spec).unwrap();
+            assert!(other
+                .decapsulate(&encapsulated_key)
+                .map_or(true, |other_key| other_key != key));
+        }
+    }
+}
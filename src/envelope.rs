@@ -0,0 +1,315 @@
+//! Envelope encryption: data is encrypted locally with AES-256-GCM under a fresh data key from
+//! GenerateDataKey, and only the wrapped data key travels to AWS KMS to be decrypted again.
+//!
+//! This lifts the 4 KB plaintext limit of Encrypt and costs a single KMS call per message, whatever its size.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), kms_rs::KmsRsError> {
+//! use kms_rs::{envelope, KmsRs};
+//! use std::collections::HashMap;
+//!
+//! let kms = KmsRs::default();
+//! let mut context = HashMap::new();
+//! context.insert("Purpose".to_string(), "backup".to_string());
+//!
+//! let sealed = envelope::seal(&kms, "alias/backups", b"a large payload", context).await?;
+//! let stored = sealed.to_vec()?;
+//!
+//! let plaintext = envelope::open(&kms, &envelope::Envelope::from_slice(&stored)?).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::cache::DataKeyCache;
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::model::base64_bytes;
+use crate::secret::into_zeroizing;
+
+/// The envelope format version written by [`seal`].
+pub const VERSION: u8 = 1;
+
+/// The only data encryption algorithm supported so far.
+pub const AES_256_GCM: &str = "AES_256_GCM";
+
+/// Data encrypted by [`seal`], along with everything [`open`] needs to decrypt it.
+///
+/// Serializes to JSON (PascalCase fields, binary fields as base64) with [`Envelope::to_vec`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Envelope {
+    pub version: u8,
+    /// The ARN of the CMK the data key is encrypted under.
+    pub key_id: String,
+    pub algorithm: String,
+    /// The data key, encrypted under the CMK.
+    #[serde(with = "base64_bytes")]
+    pub encrypted_data_key: Bytes,
+    #[serde(with = "base64_bytes")]
+    pub nonce: Bytes,
+    /// The encryption context the data key was generated with. It is also authenticated by AES-GCM.
+    #[serde(default)]
+    pub encryption_context: BTreeMap<String, String>,
+    /// The encrypted data followed by the 16-byte authentication tag.
+    #[serde(with = "base64_bytes")]
+    pub ciphertext: Bytes,
+}
+
+impl Envelope {
+    /// Serializes the envelope to JSON.
+    pub fn to_vec(&self) -> Result<Vec<u8>, KmsRsError> {
+        serde_json::to_vec(self).map_err(|err| KmsRsError::Parse(err.to_string()))
+    }
+
+    /// Deserializes an envelope written by [`Envelope::to_vec`].
+    pub fn from_slice(bytes: &[u8]) -> Result<Envelope, KmsRsError> {
+        serde_json::from_slice(bytes).map_err(|err| KmsRsError::Parse(err.to_string()))
+    }
+
+    /// The additional authenticated data: everything in the envelope that is not key material or ciphertext.
+    fn aad(&self) -> Result<Vec<u8>, KmsRsError> {
+        serde_json::to_vec(&(
+            self.version,
+            &self.key_id,
+            &self.algorithm,
+            &self.encryption_context,
+        ))
+        .map_err(|err| KmsRsError::Parse(err.to_string()))
+    }
+}
+
+/// Encrypts `plaintext` under a new AES-256 data key generated by the CMK `key_id`. The plaintext data key
+/// is zeroized before returning.
+pub async fn seal(
+    kms: &KmsRs,
+    key_id: &str,
+    plaintext: &[u8],
+    encryption_context: HashMap<String, String>,
+) -> Result<Envelope, KmsRsError> {
+    let data_key = kms
+        .generate_data_key(
+            key_id,
+            Some("AES_256".to_string()),
+            None,
+            Some(encryption_context.clone()).filter(|context| !context.is_empty()),
+            None,
+        )
+        .await?;
+    let key = match data_key.plaintext {
+        Some(plaintext) => into_zeroizing(plaintext),
+        None => {
+            return Err(KmsRsError::Parse(
+                "GenerateDataKey returned no plaintext data key".to_string(),
+            ))
+        }
+    };
+
+    seal_with_key(
+        data_key.key_id,
+        data_key.ciphertext_blob,
+        &key,
+        plaintext,
+        encryption_context,
+    )
+}
+
+/// Same as [`seal`], but the data key comes from `cache` and may be shared with other envelopes for the
+/// same CMK and encryption context, within the cache limits.
+pub async fn seal_cached(
+    cache: &DataKeyCache,
+    key_id: &str,
+    plaintext: &[u8],
+    encryption_context: HashMap<String, String>,
+) -> Result<Envelope, KmsRsError> {
+    let data_key = cache
+        .generate_data_key(
+            key_id,
+            "AES_256",
+            encryption_context.clone(),
+            plaintext.len() as u64,
+        )
+        .await?;
+
+    seal_with_key(
+        data_key.key_id.clone(),
+        data_key.ciphertext_blob.clone(),
+        data_key.plaintext(),
+        plaintext,
+        encryption_context,
+    )
+}
+
+/// Decrypts the data key of `envelope` with AWS KMS, then the data itself. Fails with
+/// [`KmsRsError::InvalidCiphertext`] if the envelope has been tampered with.
+pub async fn open(kms: &KmsRs, envelope: &Envelope) -> Result<Bytes, KmsRsError> {
+    check_format(envelope)?;
+    let data_key = kms
+        .decrypt(
+            Some(envelope.key_id.clone()),
+            envelope.encrypted_data_key.clone(),
+            Some(envelope.encryption_context.clone().into_iter().collect())
+                .filter(|context: &HashMap<String, String>| !context.is_empty()),
+            None,
+            None,
+        )
+        .await?;
+    let key = into_zeroizing(data_key.plaintext);
+
+    open_with_key(&key, envelope)
+}
+
+/// Same as [`open`], but the decrypted data key comes from `cache` when the same wrapped key was
+/// decrypted recently.
+pub async fn open_cached(cache: &DataKeyCache, envelope: &Envelope) -> Result<Bytes, KmsRsError> {
+    check_format(envelope)?;
+    let data_key = cache
+        .decrypt(
+            Some(envelope.key_id.clone()),
+            envelope.encrypted_data_key.clone(),
+            envelope.encryption_context.clone().into_iter().collect(),
+        )
+        .await?;
+
+    open_with_key(data_key.plaintext(), envelope)
+}
+
+fn seal_with_key(
+    key_id: String,
+    encrypted_data_key: Bytes,
+    key: &[u8],
+    plaintext: &[u8],
+    encryption_context: HashMap<String, String>,
+) -> Result<Envelope, KmsRsError> {
+    let mut envelope = Envelope {
+        version: VERSION,
+        key_id,
+        algorithm: AES_256_GCM.to_string(),
+        encrypted_data_key,
+        nonce: Bytes::copy_from_slice(&Aes256Gcm::generate_nonce(&mut OsRng)),
+        encryption_context: encryption_context.into_iter().collect(),
+        ciphertext: Bytes::new(),
+    };
+    let aad = envelope.aad()?;
+    envelope.ciphertext = cipher(key)?
+        .encrypt(
+            Nonce::from_slice(&envelope.nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map(Bytes::from)
+        .map_err(|_| KmsRsError::Crypto("AES-GCM encryption failed".to_string()))?;
+
+    Ok(envelope)
+}
+
+fn check_format(envelope: &Envelope) -> Result<(), KmsRsError> {
+    if envelope.version != VERSION || envelope.algorithm != AES_256_GCM {
+        return Err(KmsRsError::UnsupportedOperation(format!(
+            "Unsupported envelope version {} with algorithm {}",
+            envelope.version, envelope.algorithm
+        )));
+    }
+    if envelope.nonce.len() != 12 {
+        return Err(KmsRsError::InvalidCiphertext(
+            "The envelope nonce must be 12 bytes".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn open_with_key(key: &[u8], envelope: &Envelope) -> Result<Bytes, KmsRsError> {
+    let aad = envelope.aad()?;
+    cipher(key)?
+        .decrypt(
+            Nonce::from_slice(&envelope.nonce),
+            Payload {
+                msg: &envelope.ciphertext,
+                aad: &aad,
+            },
+        )
+        .map(Bytes::from)
+        .map_err(|_| {
+            KmsRsError::InvalidCiphertext("The envelope failed authentication".to_string())
+        })
+}
+
+fn cipher(key: &[u8]) -> Result<Aes256Gcm, KmsRsError> {
+    if key.len() != 32 {
+        return Err(KmsRsError::Crypto(format!(
+            "Expected a 32-byte data key, got {} bytes",
+            key.len()
+        )));
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::mock_kms;
+
+    // base64 of 32 bytes 0x01..=0x20
+    const PLAINTEXT_KEY: &str = "AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyA=";
+
+    fn generate_data_key_response() -> String {
+        format!(
+            r#"{{"KeyId": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz", "CiphertextBlob": "d3JhcHBlZA==", "Plaintext": "{}"}}"#,
+            PLAINTEXT_KEY
+        )
+    }
+
+    fn decrypt_response() -> String {
+        format!(
+            r#"{{"KeyId": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz", "Plaintext": "{}", "EncryptionAlgorithm": "SYMMETRIC_DEFAULT"}}"#,
+            PLAINTEXT_KEY
+        )
+    }
+
+    fn context() -> HashMap<String, String> {
+        let mut context = HashMap::new();
+        context.insert("Purpose".to_string(), "test".to_string());
+        context
+    }
+
+    #[tokio::test]
+    async fn test_seal_and_open() {
+        let kms = mock_kms(vec![&generate_data_key_response(), &decrypt_response()]);
+        let envelope = seal(&kms, "alias/test", b"hello envelope", context())
+            .await
+            .unwrap();
+        assert_eq!(
+            envelope.key_id,
+            "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz"
+        );
+        assert_eq!(envelope.encrypted_data_key, Bytes::from_static(b"wrapped"));
+        assert_eq!(envelope.nonce.len(), 12);
+        assert_eq!(envelope.ciphertext.len(), b"hello envelope".len() + 16);
+
+        let envelope = Envelope::from_slice(&envelope.to_vec().unwrap()).unwrap();
+        let plaintext = open(&kms, &envelope).await.unwrap();
+        assert_eq!(plaintext, Bytes::from_static(b"hello envelope"));
+    }
+
+    #[tokio::test]
+    async fn test_open_rejects_modified_context() {
+        let kms = mock_kms(vec![&generate_data_key_response(), &decrypt_response()]);
+        let mut envelope = seal(&kms, "alias/test", b"hello envelope", context())
+            .await
+            .unwrap();
+        envelope
+            .encryption_context
+            .insert("Purpose".to_string(), "other".to_string());
+        match open(&kms, &envelope).await {
+            Err(KmsRsError::InvalidCiphertext(_)) => (),
+            other => panic!("Expected an InvalidCiphertext error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,741 @@
+//! Error type returned by every operation in this crate.
+
+use rusoto_core::proto::json::Error as JsonError;
+use rusoto_core::request::BufferedHttpResponse;
+use rusoto_core::RusotoError;
+use rusoto_kms::{
+    CancelKeyDeletionError, ConnectCustomKeyStoreError, CreateAliasError,
+    CreateCustomKeyStoreError, CreateGrantError, CreateKeyError, DecryptError, DeleteAliasError,
+    DeleteCustomKeyStoreError, DeleteImportedKeyMaterialError, DescribeCustomKeyStoresError,
+    DescribeKeyError, DisableKeyError, DisableKeyRotationError, DisconnectCustomKeyStoreError,
+    EnableKeyError, EnableKeyRotationError, EncryptError, GenerateDataKeyError,
+    GenerateDataKeyPairError, GenerateDataKeyPairWithoutPlaintextError,
+    GenerateDataKeyWithoutPlaintextError, GenerateRandomError, GetKeyPolicyError,
+    GetKeyRotationStatusError, GetParametersForImportError, GetPublicKeyError,
+    ImportKeyMaterialError, ListAliasesError, ListGrantsError, ListKeyPoliciesError, ListKeysError,
+    ListResourceTagsError, ListRetirableGrantsError, PutKeyPolicyError, ReEncryptError,
+    ReplicateKeyError, RetireGrantError, RevokeGrantError, ScheduleKeyDeletionError, SignError,
+    TagResourceError, UntagResourceError, UpdateAliasError, UpdateCustomKeyStoreError,
+    UpdatePrimaryRegionError, VerifyError,
+};
+use std::error::Error;
+use std::fmt;
+
+/// An error returned by AWS KMS or by the transport used to reach it.
+///
+/// Service variants mirror the KMS exception types (e.g. `NotFoundException` becomes
+/// [`KmsRsError::NotFound`]) and carry the message returned by AWS.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KmsRsError {
+    /// The specified entity or resource could not be found.
+    NotFound(String),
+    /// The specified CMK is not enabled.
+    Disabled(String),
+    /// The state of the specified resource is not valid for this request.
+    KmsInvalidState(String),
+    /// The ciphertext, or the encryption context bound to it, is corrupted, missing or otherwise invalid.
+    InvalidCiphertext(String),
+    /// The specified CMK cannot decrypt the data.
+    IncorrectKey(String),
+    /// The `KeyUsage` of the CMK, or the requested algorithm, is incompatible with the operation.
+    InvalidKeyUsage(String),
+    /// The specified CMK was not available. The request can be retried.
+    KeyUnavailable(String),
+    /// The specified grant token is not valid.
+    InvalidGrantToken(String),
+    /// The specified grant ID is not valid.
+    InvalidGrantId(String),
+    /// A specified ARN, or an ARN in a key policy, is not valid.
+    InvalidArn(String),
+    /// The marker that specifies where pagination should next begin is not valid.
+    InvalidMarker(String),
+    /// The specified alias name is not valid.
+    InvalidAliasName(String),
+    /// The specified import token is not valid.
+    InvalidImportToken(String),
+    /// The specified import token has expired.
+    ExpiredImportToken(String),
+    /// The key material in the request is expired, invalid, or is not the same key material that was previously imported.
+    IncorrectKeyMaterial(String),
+    /// The signature verification failed.
+    KmsInvalidSignature(String),
+    /// The specified policy is not syntactically or semantically correct.
+    MalformedPolicyDocument(String),
+    /// A tag key or value is not valid.
+    Tag(String),
+    /// The resource already exists.
+    AlreadyExists(String),
+    /// A quota would be exceeded.
+    LimitExceeded(String),
+    /// The operation is not supported for the specified resource.
+    UnsupportedOperation(String),
+    /// The system timed out while trying to fulfill the request. The request can be retried.
+    DependencyTimeout(String),
+    /// An internal exception occurred in AWS KMS. The request can be retried.
+    KmsInternal(String),
+    /// The associated AWS CloudHSM cluster is already associated with a custom key store.
+    CloudHsmClusterInUse(String),
+    /// The associated AWS CloudHSM cluster does not meet the configuration requirements.
+    CloudHsmClusterInvalidConfiguration(String),
+    /// The associated AWS CloudHSM cluster is not active.
+    CloudHsmClusterNotActive(String),
+    /// No AWS CloudHSM cluster was found with the specified cluster ID.
+    CloudHsmClusterNotFound(String),
+    /// The specified AWS CloudHSM cluster does not share a backup history with the original cluster.
+    CloudHsmClusterNotRelated(String),
+    /// The custom key store still contains CMKs.
+    CustomKeyStoreHasCMKs(String),
+    /// The custom key store is not in a valid connection state for the request.
+    CustomKeyStoreInvalidState(String),
+    /// The specified custom key store name is already assigned to another custom key store.
+    CustomKeyStoreNameInUse(String),
+    /// No custom key store was found with the specified name or ID.
+    CustomKeyStoreNotFound(String),
+    /// The trust anchor certificate does not match the AWS CloudHSM cluster.
+    IncorrectTrustAnchor(String),
+    /// The request was denied due to request throttling. The request can be retried.
+    Throttling(String),
+    /// The caller does not have permission to perform the operation.
+    AccessDenied(String),
+    /// AWS credentials could not be loaded, or were rejected by AWS.
+    Credentials(String),
+    /// The HTTP request could not be dispatched.
+    HttpDispatch(String),
+    /// The request failed validation.
+    Validation(String),
+    /// The response from AWS (or a serialized value such as an envelope) could not be parsed.
+    Parse(String),
+    /// A cryptographic operation performed locally, outside of AWS KMS, failed.
+    Crypto(String),
+    /// A message or file could not be read (e.g. by [`crate::KmsRs::sign_reader`]).
+    Io(String),
+    /// A key policy was not sent to AWS because the local linter flagged it (see [`crate::policy::PolicyLinter`]).
+    UnsafePolicy(String),
+    /// Any other error returned by AWS, with its exception type and message.
+    Unknown {
+        /// HTTP status code of the response.
+        status: u16,
+        /// Exception type (e.g. `ValidationException`), if one could be read from the response.
+        error_type: Option<String>,
+        /// Message (or raw body, when it could not be parsed) of the response.
+        message: String,
+    },
+}
+
+impl KmsRsError {
+    fn from_unknown(response: BufferedHttpResponse) -> KmsRsError {
+        match JsonError::parse(&response) {
+            Some(error) => match error.typ.as_str() {
+                "ThrottlingException" => KmsRsError::Throttling(error.msg),
+                "AccessDeniedException" => KmsRsError::AccessDenied(error.msg),
+                "UnrecognizedClientException"
+                | "InvalidSignatureException"
+                | "ExpiredTokenException"
+                | "IncompleteSignature" => KmsRsError::Credentials(error.msg),
+                "ValidationException" => KmsRsError::Validation(error.msg),
+                _ => KmsRsError::Unknown {
+                    status: response.status.as_u16(),
+                    error_type: Some(error.typ),
+                    message: error.msg,
+                },
+            },
+            None => KmsRsError::Unknown {
+                status: response.status.as_u16(),
+                error_type: None,
+                message: response.body_as_str().to_string(),
+            },
+        }
+    }
+
+    fn from_rusoto<E, F>(error: RusotoError<E>, service: F) -> KmsRsError
+    where
+        F: FnOnce(E) -> KmsRsError,
+    {
+        match error {
+            RusotoError::Service(err) => service(err),
+            RusotoError::HttpDispatch(err) => KmsRsError::HttpDispatch(err.to_string()),
+            RusotoError::Credentials(err) => KmsRsError::Credentials(err.to_string()),
+            RusotoError::Validation(message) => KmsRsError::Validation(message),
+            RusotoError::ParseError(message) => KmsRsError::Parse(message),
+            RusotoError::Unknown(response) => KmsRsError::from_unknown(response),
+            RusotoError::Blocking => {
+                KmsRsError::HttpDispatch("Failed to run blocking future".to_string())
+            }
+        }
+    }
+
+    fn kind_and_message(&self) -> (&str, &str) {
+        match self {
+            KmsRsError::NotFound(message) => ("NotFound", message),
+            KmsRsError::Disabled(message) => ("Disabled", message),
+            KmsRsError::KmsInvalidState(message) => ("KmsInvalidState", message),
+            KmsRsError::InvalidCiphertext(message) => ("InvalidCiphertext", message),
+            KmsRsError::IncorrectKey(message) => ("IncorrectKey", message),
+            KmsRsError::InvalidKeyUsage(message) => ("InvalidKeyUsage", message),
+            KmsRsError::KeyUnavailable(message) => ("KeyUnavailable", message),
+            KmsRsError::InvalidGrantToken(message) => ("InvalidGrantToken", message),
+            KmsRsError::InvalidGrantId(message) => ("InvalidGrantId", message),
+            KmsRsError::InvalidArn(message) => ("InvalidArn", message),
+            KmsRsError::InvalidMarker(message) => ("InvalidMarker", message),
+            KmsRsError::InvalidAliasName(message) => ("InvalidAliasName", message),
+            KmsRsError::InvalidImportToken(message) => ("InvalidImportToken", message),
+            KmsRsError::ExpiredImportToken(message) => ("ExpiredImportToken", message),
+            KmsRsError::IncorrectKeyMaterial(message) => ("IncorrectKeyMaterial", message),
+            KmsRsError::KmsInvalidSignature(message) => ("KmsInvalidSignature", message),
+            KmsRsError::MalformedPolicyDocument(message) => ("MalformedPolicyDocument", message),
+            KmsRsError::Tag(message) => ("Tag", message),
+            KmsRsError::AlreadyExists(message) => ("AlreadyExists", message),
+            KmsRsError::LimitExceeded(message) => ("LimitExceeded", message),
+            KmsRsError::UnsupportedOperation(message) => ("UnsupportedOperation", message),
+            KmsRsError::DependencyTimeout(message) => ("DependencyTimeout", message),
+            KmsRsError::KmsInternal(message) => ("KmsInternal", message),
+            KmsRsError::CloudHsmClusterInUse(message) => ("CloudHsmClusterInUse", message),
+            KmsRsError::CloudHsmClusterInvalidConfiguration(message) => {
+                ("CloudHsmClusterInvalidConfiguration", message)
+            }
+            KmsRsError::CloudHsmClusterNotActive(message) => ("CloudHsmClusterNotActive", message),
+            KmsRsError::CloudHsmClusterNotFound(message) => ("CloudHsmClusterNotFound", message),
+            KmsRsError::CloudHsmClusterNotRelated(message) => {
+                ("CloudHsmClusterNotRelated", message)
+            }
+            KmsRsError::CustomKeyStoreHasCMKs(message) => ("CustomKeyStoreHasCMKs", message),
+            KmsRsError::CustomKeyStoreInvalidState(message) => {
+                ("CustomKeyStoreInvalidState", message)
+            }
+            KmsRsError::CustomKeyStoreNameInUse(message) => ("CustomKeyStoreNameInUse", message),
+            KmsRsError::CustomKeyStoreNotFound(message) => ("CustomKeyStoreNotFound", message),
+            KmsRsError::IncorrectTrustAnchor(message) => ("IncorrectTrustAnchor", message),
+            KmsRsError::Throttling(message) => ("Throttling", message),
+            KmsRsError::AccessDenied(message) => ("AccessDenied", message),
+            KmsRsError::Credentials(message) => ("Credentials", message),
+            KmsRsError::HttpDispatch(message) => ("HttpDispatch", message),
+            KmsRsError::Validation(message) => ("Validation", message),
+            KmsRsError::Parse(message) => ("Parse", message),
+            KmsRsError::Crypto(message) => ("Crypto", message),
+            KmsRsError::Io(message) => ("Io", message),
+            KmsRsError::UnsafePolicy(message) => ("UnsafePolicy", message),
+            KmsRsError::Unknown {
+                error_type,
+                message,
+                ..
+            } => (error_type.as_deref().unwrap_or("Unknown"), message),
+        }
+    }
+}
+
+impl fmt::Display for KmsRsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (kind, message) = self.kind_and_message();
+        write!(f, "{}: {}", kind, message)
+    }
+}
+
+impl Error for KmsRsError {}
+
+/// Implements `From<RusotoError<E>>` for each rusoto_kms error type, mapping every service
+/// variant onto the `KmsRsError` variant of the same exception.
+macro_rules! from_rusoto_error {
+    ($($error:ident { $($variant:ident => $kind:ident),* $(,)? })*) => {
+        $(
+            impl From<RusotoError<$error>> for KmsRsError {
+                fn from(error: RusotoError<$error>) -> KmsRsError {
+                    KmsRsError::from_rusoto(error, |err| match err {
+                        $($error::$variant(message) => KmsRsError::$kind(message),)*
+                    })
+                }
+            }
+        )*
+    };
+}
+
+from_rusoto_error! {
+    CancelKeyDeletionError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    ConnectCustomKeyStoreError {
+        CloudHsmClusterInvalidConfiguration => CloudHsmClusterInvalidConfiguration,
+        CloudHsmClusterNotActive => CloudHsmClusterNotActive,
+        CustomKeyStoreInvalidState => CustomKeyStoreInvalidState,
+        CustomKeyStoreNotFound => CustomKeyStoreNotFound,
+        KMSInternal => KmsInternal,
+    }
+    CreateAliasError {
+        AlreadyExists => AlreadyExists,
+        DependencyTimeout => DependencyTimeout,
+        InvalidAliasName => InvalidAliasName,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        LimitExceeded => LimitExceeded,
+        NotFound => NotFound,
+    }
+    CreateCustomKeyStoreError {
+        CloudHsmClusterInUse => CloudHsmClusterInUse,
+        CloudHsmClusterInvalidConfiguration => CloudHsmClusterInvalidConfiguration,
+        CloudHsmClusterNotActive => CloudHsmClusterNotActive,
+        CloudHsmClusterNotFound => CloudHsmClusterNotFound,
+        CustomKeyStoreNameInUse => CustomKeyStoreNameInUse,
+        IncorrectTrustAnchor => IncorrectTrustAnchor,
+        KMSInternal => KmsInternal,
+    }
+    CreateGrantError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidArn => InvalidArn,
+        InvalidGrantToken => InvalidGrantToken,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        LimitExceeded => LimitExceeded,
+        NotFound => NotFound,
+    }
+    CreateKeyError {
+        CloudHsmClusterInvalidConfiguration => CloudHsmClusterInvalidConfiguration,
+        CustomKeyStoreInvalidState => CustomKeyStoreInvalidState,
+        CustomKeyStoreNotFound => CustomKeyStoreNotFound,
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        LimitExceeded => LimitExceeded,
+        MalformedPolicyDocument => MalformedPolicyDocument,
+        Tag => Tag,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    DecryptError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        IncorrectKey => IncorrectKey,
+        InvalidCiphertext => InvalidCiphertext,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+    }
+    DeleteAliasError {
+        DependencyTimeout => DependencyTimeout,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    DeleteCustomKeyStoreError {
+        CustomKeyStoreHasCMKs => CustomKeyStoreHasCMKs,
+        CustomKeyStoreInvalidState => CustomKeyStoreInvalidState,
+        CustomKeyStoreNotFound => CustomKeyStoreNotFound,
+        KMSInternal => KmsInternal,
+    }
+    DeleteImportedKeyMaterialError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    DescribeCustomKeyStoresError {
+        CustomKeyStoreNotFound => CustomKeyStoreNotFound,
+        InvalidMarker => InvalidMarker,
+        KMSInternal => KmsInternal,
+    }
+    DescribeKeyError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        NotFound => NotFound,
+    }
+    DisconnectCustomKeyStoreError {
+        CustomKeyStoreInvalidState => CustomKeyStoreInvalidState,
+        CustomKeyStoreNotFound => CustomKeyStoreNotFound,
+        KMSInternal => KmsInternal,
+    }
+    DisableKeyError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    DisableKeyRotationError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    EnableKeyError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        LimitExceeded => LimitExceeded,
+        NotFound => NotFound,
+    }
+    EnableKeyRotationError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    EncryptError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+    }
+    GenerateDataKeyError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+    }
+    GenerateDataKeyPairError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    GenerateDataKeyPairWithoutPlaintextError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    GenerateDataKeyWithoutPlaintextError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+    }
+    GenerateRandomError {
+        CustomKeyStoreInvalidState => CustomKeyStoreInvalidState,
+        CustomKeyStoreNotFound => CustomKeyStoreNotFound,
+        DependencyTimeout => DependencyTimeout,
+        KMSInternal => KmsInternal,
+    }
+    GetKeyPolicyError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    GetKeyRotationStatusError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    GetParametersForImportError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    GetPublicKeyError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidArn => InvalidArn,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    ImportKeyMaterialError {
+        DependencyTimeout => DependencyTimeout,
+        ExpiredImportToken => ExpiredImportToken,
+        IncorrectKeyMaterial => IncorrectKeyMaterial,
+        InvalidArn => InvalidArn,
+        InvalidCiphertext => InvalidCiphertext,
+        InvalidImportToken => InvalidImportToken,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    ListAliasesError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        InvalidMarker => InvalidMarker,
+        KMSInternal => KmsInternal,
+        NotFound => NotFound,
+    }
+    ListGrantsError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        InvalidGrantId => InvalidGrantId,
+        InvalidMarker => InvalidMarker,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    ListKeyPoliciesError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    ListKeysError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidMarker => InvalidMarker,
+        KMSInternal => KmsInternal,
+    }
+    ListRetirableGrantsError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        InvalidMarker => InvalidMarker,
+        KMSInternal => KmsInternal,
+        NotFound => NotFound,
+    }
+    ListResourceTagsError {
+        InvalidArn => InvalidArn,
+        InvalidMarker => InvalidMarker,
+        KMSInternal => KmsInternal,
+        NotFound => NotFound,
+    }
+    PutKeyPolicyError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        LimitExceeded => LimitExceeded,
+        MalformedPolicyDocument => MalformedPolicyDocument,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    ReEncryptError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        IncorrectKey => IncorrectKey,
+        InvalidCiphertext => InvalidCiphertext,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+    }
+    ReplicateKeyError {
+        AlreadyExists => AlreadyExists,
+        Disabled => Disabled,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        LimitExceeded => LimitExceeded,
+        MalformedPolicyDocument => MalformedPolicyDocument,
+        NotFound => NotFound,
+        Tag => Tag,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    RetireGrantError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        InvalidGrantId => InvalidGrantId,
+        InvalidGrantToken => InvalidGrantToken,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    RevokeGrantError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        InvalidGrantId => InvalidGrantId,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    ScheduleKeyDeletionError {
+        DependencyTimeout => DependencyTimeout,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+    }
+    SignError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+    }
+    TagResourceError {
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        LimitExceeded => LimitExceeded,
+        NotFound => NotFound,
+        Tag => Tag,
+    }
+    UntagResourceError {
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        Tag => Tag,
+    }
+    UpdateAliasError {
+        DependencyTimeout => DependencyTimeout,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        LimitExceeded => LimitExceeded,
+        NotFound => NotFound,
+    }
+    UpdateCustomKeyStoreError {
+        CloudHsmClusterInvalidConfiguration => CloudHsmClusterInvalidConfiguration,
+        CloudHsmClusterNotActive => CloudHsmClusterNotActive,
+        CloudHsmClusterNotFound => CloudHsmClusterNotFound,
+        CloudHsmClusterNotRelated => CloudHsmClusterNotRelated,
+        CustomKeyStoreInvalidState => CustomKeyStoreInvalidState,
+        CustomKeyStoreNameInUse => CustomKeyStoreNameInUse,
+        CustomKeyStoreNotFound => CustomKeyStoreNotFound,
+        KMSInternal => KmsInternal,
+    }
+    UpdatePrimaryRegionError {
+        Disabled => Disabled,
+        InvalidArn => InvalidArn,
+        KMSInternal => KmsInternal,
+        KMSInvalidState => KmsInvalidState,
+        NotFound => NotFound,
+        UnsupportedOperation => UnsupportedOperation,
+    }
+    VerifyError {
+        DependencyTimeout => DependencyTimeout,
+        Disabled => Disabled,
+        InvalidGrantToken => InvalidGrantToken,
+        InvalidKeyUsage => InvalidKeyUsage,
+        KMSInternal => KmsInternal,
+        KMSInvalidSignature => KmsInvalidSignature,
+        KMSInvalidState => KmsInvalidState,
+        KeyUnavailable => KeyUnavailable,
+        NotFound => NotFound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use http::{HeaderMap, StatusCode};
+    use rusoto_core::HttpDispatchError;
+
+    fn unknown_response(status: u16, body: &'static str) -> BufferedHttpResponse {
+        BufferedHttpResponse {
+            status: StatusCode::from_u16(status).unwrap(),
+            body: Bytes::from(body),
+            headers: HeaderMap::default(),
+        }
+    }
+
+    #[test]
+    fn test_service_error_maps_to_same_exception() {
+        let error: KmsRsError = RusotoError::Service(DecryptError::KMSInvalidState(
+            "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz is pending deletion.".to_string(),
+        ))
+        .into();
+        assert_eq!(
+            error,
+            KmsRsError::KmsInvalidState(
+                "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz is pending deletion."
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_unknown_throttling_error() {
+        let response = unknown_response(
+            400,
+            r#"{"__type":"ThrottlingException","message":"Rate exceeded"}"#,
+        );
+        let error: KmsRsError = RusotoError::<EncryptError>::Unknown(response).into();
+        assert_eq!(error, KmsRsError::Throttling("Rate exceeded".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_access_denied_error() {
+        let response = unknown_response(
+            400,
+            r#"{"__type":"AccessDeniedException","Message":"User is not authorized"}"#,
+        );
+        let error: KmsRsError = RusotoError::<SignError>::Unknown(response).into();
+        assert_eq!(
+            error,
+            KmsRsError::AccessDenied("User is not authorized".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_error_without_json_body() {
+        let response = unknown_response(503, "Service Unavailable");
+        let error: KmsRsError = RusotoError::<ListKeysError>::Unknown(response).into();
+        assert_eq!(
+            error,
+            KmsRsError::Unknown {
+                status: 503,
+                error_type: None,
+                message: "Service Unavailable".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_http_dispatch_error() {
+        let error: KmsRsError = RusotoError::<DescribeKeyError>::HttpDispatch(
+            HttpDispatchError::new("connection refused".to_string()),
+        )
+        .into();
+        assert_eq!(
+            error,
+            KmsRsError::HttpDispatch("connection refused".to_string())
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let error = KmsRsError::NotFound("Key 'abcd-4321-wxyz' does not exist".to_string());
+        assert_eq!(
+            error.to_string(),
+            "NotFound: Key 'abcd-4321-wxyz' does not exist"
+        );
+    }
+}
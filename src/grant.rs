@@ -0,0 +1,225 @@
+//! Grants scoped to a piece of work.
+//!
+//! A [`GrantGuard`] creates a grant, lends its token to the calls that need it, and retires the grant once
+//! the work is done, so short-lived workers only hold permissions for as long as they run.
+
+use std::mem;
+use tokio::runtime::Handle;
+
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::model::CreateGrantOutput;
+use crate::options::CreateGrantOptions;
+
+/// A grant that is retired when the guard goes out of scope.
+///
+/// Dropping the guard retires the grant:
+///
+/// - Inside a tokio runtime, in a task spawned on that runtime, so the drop does not block. If the runtime
+///   shuts down before the task runs, the grant is left in place.
+/// - Outside one, before the drop returns: on the [`crate::blocking`] runtime, or without the `blocking`
+///   feature on a runtime created for the call.
+///
+/// Failures are ignored, and the grant then stays until it is retired or revoked, so prefer
+/// [`GrantGuard::retire`] when the outcome matters. Retiring uses the grant token, so the caller must be
+/// allowed to retire the grant (see [`CreateGrantOptions::retiring_principal`]).
+///
+/// ```
+/// # async fn run() -> Result<(), kms_rs::KmsRsError> {
+/// use bytes::Bytes;
+/// use kms_rs::{CreateGrantOptions, CreateKeyOptions, GrantGuard, InMemoryKms, KmsRs};
+///
+/// let kms = KmsRs::from_backend(InMemoryKms::new());
+/// let key = kms.create_key(CreateKeyOptions::new()).await?;
+/// let options = CreateGrantOptions::new("arn:aws:iam::111122223333:role/batch-worker")
+///     .operation("Encrypt")
+///     .operation("RetireGrant");
+///
+/// let grant = GrantGuard::create(&kms, &key.key_id, options).await?;
+/// kms.encrypt(
+///     key.key_id,
+///     Bytes::from_static(b"report"),
+///     None,
+///     None,
+///     grant.grant_tokens(),
+/// )
+/// .await?;
+/// grant.retire().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct GrantGuard {
+    kms: KmsRs,
+    key_id: String,
+    grant: CreateGrantOutput,
+    /// Whether the grant still has to be retired.
+    armed: bool,
+}
+
+impl GrantGuard {
+    /// Creates a grant on the CMK `key_id` and guards it.
+    pub async fn create(
+        kms: &KmsRs,
+        key_id: &str,
+        options: CreateGrantOptions,
+    ) -> Result<GrantGuard, KmsRsError> {
+        let grant = kms.create_grant(key_id, options).await?;
+        Ok(GrantGuard {
+            kms: kms.clone(),
+            key_id: key_id.to_string(),
+            grant,
+            armed: true,
+        })
+    }
+
+    /// The CMK the grant is on, as given to [`GrantGuard::create`].
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn grant_id(&self) -> &str {
+        &self.grant.grant_id
+    }
+
+    pub fn grant_token(&self) -> &str {
+        &self.grant.grant_token
+    }
+
+    /// The grant token, in the shape of the `grant_tokens` parameter of [`KmsRs`] operations.
+    pub fn grant_tokens(&self) -> Option<Vec<String>> {
+        Some(vec![self.grant.grant_token.clone()])
+    }
+
+    /// Retires the grant now, returning any error.
+    pub async fn retire(mut self) -> Result<(), KmsRsError> {
+        self.armed = false;
+        self.kms.retire_grant(&self.grant.grant_token).await
+    }
+
+    /// Disarms the guard: the grant outlives it and is left to the caller.
+    pub fn keep(mut self) -> CreateGrantOutput {
+        self.armed = false;
+        mem::take(&mut self.grant)
+    }
+}
+
+impl Drop for GrantGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let kms = self.kms.clone();
+        let grant_token = mem::take(&mut self.grant.grant_token);
+        let retire = async move { kms.retire_grant(&grant_token).await };
+
+        match Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(retire);
+            }
+            #[cfg(feature = "blocking")]
+            Err(_) => {
+                let _ = crate::blocking::block_on(retire);
+            }
+            #[cfg(not(feature = "blocking"))]
+            Err(_) => {
+                if let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    let _ = runtime.block_on(retire);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CreateKeyOptions, InMemoryKms};
+    use std::time::Duration;
+
+    async fn grant_count(kms: &KmsRs, key_id: &str) -> usize {
+        kms.list_grants(key_id).await.unwrap().grants.len()
+    }
+
+    fn options() -> CreateGrantOptions {
+        CreateGrantOptions::new("arn:aws:iam::111122223333:role/worker")
+            .operation("Decrypt")
+            .operation("RetireGrant")
+    }
+
+    #[tokio::test]
+    async fn test_drop_retires_grant() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        {
+            let grant = GrantGuard::create(&kms, &key.key_id, options())
+                .await
+                .unwrap();
+            let grants = kms.list_grants(&key.key_id).await.unwrap().grants;
+            assert_eq!(grants.len(), 1);
+            assert_eq!(grants[0].grant_id, grant.grant_id());
+        }
+        // The grant is retired by a spawned task, which may need several polls to run.
+        for _ in 0..100 {
+            if grant_count(&kms, &key.key_id).await == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("The dropped guard did not retire its grant");
+    }
+
+    #[tokio::test]
+    async fn test_retire_and_keep() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+
+        let grant = GrantGuard::create(&kms, &key.key_id, options())
+            .await
+            .unwrap();
+        grant.retire().await.unwrap();
+        assert_eq!(grant_count(&kms, &key.key_id).await, 0);
+
+        let grant = GrantGuard::create(&kms, &key.key_id, options())
+            .await
+            .unwrap()
+            .keep();
+        tokio::task::yield_now().await;
+        assert_eq!(grant_count(&kms, &key.key_id).await, 1);
+        kms.revoke_grant(&key.key_id, &grant.grant_id)
+            .await
+            .unwrap();
+        assert_eq!(grant_count(&kms, &key.key_id).await, 0);
+    }
+
+    #[test]
+    fn test_drop_after_runtime_call() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key = runtime
+            .block_on(kms.create_key(CreateKeyOptions::new()))
+            .unwrap();
+        let grant = runtime
+            .block_on(GrantGuard::create(&kms, &key.key_id, options()))
+            .unwrap();
+        // Outside `block_on`, so the guard retires the grant before the drop returns.
+        drop(grant);
+        assert_eq!(runtime.block_on(grant_count(&kms, &key.key_id)), 0);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_drop_outside_runtime() {
+        let kms = crate::blocking::KmsRs::new(KmsRs::from_backend(InMemoryKms::new()));
+        let key = kms.create_key(CreateKeyOptions::new()).unwrap();
+        let grant = kms.grant_guard(&key.key_id, options()).unwrap();
+        assert_eq!(kms.list_grants(&key.key_id).unwrap().grants.len(), 1);
+        drop(grant);
+        assert!(kms.list_grants(&key.key_id).unwrap().grants.is_empty());
+    }
+}
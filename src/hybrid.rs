@@ -0,0 +1,270 @@
+//! Hybrid encryption with data key pairs: producers encrypt with the public key of a pair from
+//! GenerateDataKeyPair(WithoutPlaintext) alone, with no AWS credentials or KMS calls, and consumers decrypt
+//! once the private key has been unwrapped through Decrypt.
+//!
+//! ECC pairs use ECIES (an ECDH agreement with an ephemeral key pair, HKDF-SHA-256 and AES-256-GCM), RSA pairs
+//! wrap a random AES-256-GCM key with RSAES-OAEP-SHA-256.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), kms_rs::KmsRsError> {
+//! use kms_rs::{hybrid, KmsRs};
+//! use std::collections::HashMap;
+//!
+//! let kms = KmsRs::default();
+//! let key_pair = kms
+//!     .generate_data_key_pair_without_plaintext("alias/telemetry", "ECC_NIST_P256".to_string(), None, None)
+//!     .await?;
+//!
+//! // On the producer, with the stored key pair only.
+//! let sealed = hybrid::seal(&key_pair, b"sensor reading")?.to_vec()?;
+//!
+//! // On the consumer, one Decrypt call for any number of messages.
+//! let decryptor = hybrid::KeyPairDecryptor::new(&kms, &key_pair, HashMap::new()).await?;
+//! let plaintext = decryptor.open(&hybrid::HybridCiphertext::from_slice(&sealed)?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::crypto::{PrivateKey, PublicKey};
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::model::{base64_bytes, DataKeyPair};
+use crate::secret::decrypt_private_key;
+
+/// The hybrid ciphertext format version written by [`seal`].
+pub const VERSION: u8 = 1;
+
+/// The algorithm used with ECC key pairs (`ECC_NIST_P256`, `ECC_NIST_P384`, `ECC_NIST_P521` and
+/// `ECC_SECG_P256K1`).
+pub const ECIES_HKDF_SHA_256_AES_256_GCM: &str = "ECIES_HKDF_SHA_256_AES_256_GCM";
+
+/// The algorithm used with RSA key pairs (`RSA_2048`, `RSA_3072` and `RSA_4096`).
+pub const RSAES_OAEP_SHA_256_AES_256_GCM: &str = "RSAES_OAEP_SHA_256_AES_256_GCM";
+
+/// Data encrypted by [`seal`] to the public key of a data key pair.
+///
+/// Serializes to JSON (PascalCase fields, binary fields as base64) with [`HybridCiphertext::to_vec`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct HybridCiphertext {
+    pub version: u8,
+    pub algorithm: String,
+    pub key_pair_spec: String,
+    /// What the private key needs to recover the AES key: the DER public key of the ephemeral key pair for
+    /// ECIES, or the AES key encrypted with RSAES-OAEP-SHA-256.
+    #[serde(with = "base64_bytes")]
+    pub encapsulated_key: Bytes,
+    #[serde(with = "base64_bytes")]
+    pub nonce: Bytes,
+    /// The encrypted data followed by the 16-byte authentication tag.
+    #[serde(with = "base64_bytes")]
+    pub ciphertext: Bytes,
+}
+
+impl HybridCiphertext {
+    /// Serializes the ciphertext to JSON.
+    pub fn to_vec(&self) -> Result<Vec<u8>, KmsRsError> {
+        serde_json::to_vec(self).map_err(|err| KmsRsError::Parse(err.to_string()))
+    }
+
+    /// Deserializes a ciphertext written by [`HybridCiphertext::to_vec`].
+    pub fn from_slice(bytes: &[u8]) -> Result<HybridCiphertext, KmsRsError> {
+        serde_json::from_slice(bytes).map_err(|err| KmsRsError::Parse(err.to_string()))
+    }
+
+    /// The additional authenticated data: everything that is not the nonce or ciphertext.
+    fn aad(&self) -> Result<Vec<u8>, KmsRsError> {
+        serde_json::to_vec(&(
+            self.version,
+            &self.algorithm,
+            &self.key_pair_spec,
+            &self.encapsulated_key[..],
+        ))
+        .map_err(|err| KmsRsError::Parse(err.to_string()))
+    }
+}
+
+/// Encrypts `plaintext` to the public key of `key_pair`, which may come from
+/// GenerateDataKeyPairWithoutPlaintext. Only the holder of the private key can decrypt it.
+pub fn seal(key_pair: &DataKeyPair, plaintext: &[u8]) -> Result<HybridCiphertext, KmsRsError> {
+    let algorithm = algorithm(&key_pair.key_pair_spec)?;
+    let (key, encapsulated_key) = PublicKey::from_der(&key_pair.public_key)?.encapsulate()?;
+
+    let mut sealed = HybridCiphertext {
+        version: VERSION,
+        algorithm: algorithm.to_string(),
+        key_pair_spec: key_pair.key_pair_spec.clone(),
+        encapsulated_key: Bytes::from(encapsulated_key),
+        nonce: Bytes::copy_from_slice(&Aes256Gcm::generate_nonce(&mut OsRng)),
+        ciphertext: Bytes::new(),
+    };
+    let aad = sealed.aad()?;
+    sealed.ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .encrypt(
+            Nonce::from_slice(&sealed.nonce),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map(Bytes::from)
+        .map_err(|_| KmsRsError::Crypto("AES-GCM encryption failed".to_string()))?;
+
+    Ok(sealed)
+}
+
+/// Decrypts [`HybridCiphertext`]s sealed to one data key pair, with its private key unwrapped once through
+/// Decrypt. The private key stays in memory, and is zeroized when the decryptor is dropped.
+pub struct KeyPairDecryptor {
+    key_pair_spec: String,
+    private_key: PrivateKey,
+}
+
+impl KeyPairDecryptor {
+    /// Decrypts the `PrivateKeyCiphertextBlob` of `key_pair` with the CMK that generated it, given the
+    /// encryption context the pair was generated with (if any).
+    pub async fn new(
+        kms: &KmsRs,
+        key_pair: &DataKeyPair,
+        encryption_context: HashMap<String, String>,
+    ) -> Result<KeyPairDecryptor, KmsRsError> {
+        algorithm(&key_pair.key_pair_spec)?;
+        Ok(KeyPairDecryptor {
+            key_pair_spec: key_pair.key_pair_spec.clone(),
+            private_key: PrivateKey::from_pkcs8_der(
+                &key_pair.key_pair_spec,
+                &decrypt_private_key(kms, key_pair, encryption_context).await?,
+            )?,
+        })
+    }
+
+    /// Decrypts `sealed` locally. Fails with [`KmsRsError::InvalidCiphertext`] if it was sealed to another
+    /// key pair or has been tampered with.
+    pub fn open(&self, sealed: &HybridCiphertext) -> Result<Bytes, KmsRsError> {
+        if sealed.version != VERSION || sealed.algorithm != algorithm(&sealed.key_pair_spec)? {
+            return Err(KmsRsError::UnsupportedOperation(format!(
+                "Unsupported hybrid ciphertext version {} with algorithm {}",
+                sealed.version, sealed.algorithm
+            )));
+        }
+        if sealed.key_pair_spec != self.key_pair_spec {
+            return Err(KmsRsError::InvalidCiphertext(format!(
+                "The ciphertext was sealed to a {} key pair, not {}",
+                sealed.key_pair_spec, self.key_pair_spec
+            )));
+        }
+        if sealed.nonce.len() != 12 {
+            return Err(KmsRsError::InvalidCiphertext(
+                "The ciphertext nonce must be 12 bytes".to_string(),
+            ));
+        }
+
+        let key = self.private_key.decapsulate(&sealed.encapsulated_key)?;
+        let aad = sealed.aad()?;
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(
+                Nonce::from_slice(&sealed.nonce),
+                Payload {
+                    msg: &sealed.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map(Bytes::from)
+            .map_err(|_| {
+                KmsRsError::InvalidCiphertext("The ciphertext failed authentication".to_string())
+            })
+    }
+}
+
+/// The hybrid algorithm used with a key pair spec.
+fn algorithm(key_pair_spec: &str) -> Result<&'static str, KmsRsError> {
+    match key_pair_spec {
+        "RSA_2048" | "RSA_3072" | "RSA_4096" => Ok(RSAES_OAEP_SHA_256_AES_256_GCM),
+        "ECC_NIST_P256" | "ECC_NIST_P384" | "ECC_NIST_P521" | "ECC_SECG_P256K1" => {
+            Ok(ECIES_HKDF_SHA_256_AES_256_GCM)
+        }
+        _ => Err(KmsRsError::Validation(format!(
+            "Unsupported key pair spec {}",
+            key_pair_spec
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::data_key_pair;
+    use crate::InMemoryKms;
+
+    fn context() -> HashMap<String, String> {
+        let mut context = HashMap::new();
+        context.insert("Purpose".to_string(), "telemetry".to_string());
+        context
+    }
+
+    #[tokio::test]
+    async fn test_seal_and_open() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        for key_pair_spec in &[
+            "RSA_2048",
+            "ECC_NIST_P256",
+            "ECC_NIST_P384",
+            "ECC_NIST_P521",
+            "ECC_SECG_P256K1",
+        ] {
+            let key_pair = data_key_pair(&kms, key_pair_spec, Some(context())).await;
+            let sealed = seal(&key_pair, b"sensor reading").unwrap();
+            assert_eq!(sealed.algorithm, algorithm(key_pair_spec).unwrap());
+
+            let sealed = HybridCiphertext::from_slice(&sealed.to_vec().unwrap()).unwrap();
+            let decryptor = KeyPairDecryptor::new(&kms, &key_pair, context())
+                .await
+                .unwrap();
+            assert_eq!(
+                decryptor.open(&sealed).unwrap(),
+                Bytes::from_static(b"sensor reading")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_rejects_other_key_pair_and_tampering() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_pair = data_key_pair(&kms, "ECC_NIST_P256", Some(context())).await;
+        let decryptor = KeyPairDecryptor::new(&kms, &key_pair, context())
+            .await
+            .unwrap();
+
+        let other = data_key_pair(&kms, "ECC_NIST_P256", Some(context())).await;
+        match decryptor.open(&seal(&other, b"sensor reading").unwrap()) {
+            Err(KmsRsError::InvalidCiphertext(_)) => (),
+            other => panic!("Expected an InvalidCiphertext error, got {:?}", other),
+        }
+
+        let mut sealed = seal(&key_pair, b"sensor reading").unwrap();
+        let mut ciphertext = sealed.ciphertext.to_vec();
+        ciphertext[0] ^= 1;
+        sealed.ciphertext = Bytes::from(ciphertext);
+        match decryptor.open(&sealed) {
+            Err(KmsRsError::InvalidCiphertext(_)) => (),
+            other => panic!("Expected an InvalidCiphertext error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decryptor_needs_encryption_context() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_pair = data_key_pair(&kms, "ECC_NIST_P256", Some(context())).await;
+        match KeyPairDecryptor::new(&kms, &key_pair, HashMap::new()).await {
+            Err(KmsRsError::InvalidCiphertext(_)) => (),
+            Err(other) => panic!("Expected an InvalidCiphertext error, got {:?}", other),
+            Ok(_) => panic!("Expected an InvalidCiphertext error"),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! The configurable client used by every operation in this crate.
+
+use async_trait::async_trait;
+use rusoto_core::credential::{
+    AwsCredentials, CredentialsError, DefaultCredentialsProvider, ProvideAwsCredentials,
+};
+use rusoto_core::request::{DispatchSignedRequestFuture, HttpClient};
+use rusoto_core::signature::SignedRequest;
+use rusoto_core::{DispatchSignedRequest, Region};
+use rusoto_kms::KmsClient;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::backend::KmsBackend;
+use crate::error::KmsRsError;
+
+/// A client for AWS KMS, configured once and reused for every call.
+///
+/// Use [`KmsRs::builder`] to choose the Region, a custom endpoint (e.g. a local KMS), the credentials
+/// provider or the HTTP dispatcher. [`KmsRs::default`] uses the Region from `AWS_DEFAULT_REGION` or
+/// `AWS_REGION` (falling back to us-east-1) along with the default credentials chain.
+///
+/// Operations are sent to a [`KmsBackend`]: AWS KMS by default, or e.g. an [`crate::InMemoryKms`] given
+/// to [`KmsRs::from_backend`].
+#[derive(Clone)]
+pub struct KmsRs {
+    pub(crate) backend: Arc<dyn KmsBackend>,
+    region: Region,
+    connector: Connector,
+}
+
+impl KmsRs {
+    /// Creates a client for the given Region using the default credentials chain and HTTP dispatcher.
+    pub fn new(region: Region) -> KmsRs {
+        KmsRs {
+            backend: Arc::new(KmsClient::new(region.clone())),
+            region,
+            connector: Connector::Default { endpoint: None },
+        }
+    }
+
+    /// Creates a client that sends every operation to `backend`, e.g. an [`crate::InMemoryKms`] in tests.
+    /// [`KmsRs::region`] returns `Region::default()`.
+    pub fn from_backend<B: KmsBackend + 'static>(backend: B) -> KmsRs {
+        let backend: Arc<dyn KmsBackend> = Arc::new(backend);
+        KmsRs {
+            backend: backend.clone(),
+            region: Region::default(),
+            connector: Connector::Backend(backend),
+        }
+    }
+
+    /// Returns a builder to configure a client.
+    pub fn builder() -> KmsRsBuilder {
+        KmsRsBuilder::default()
+    }
+
+    /// The Region (or custom endpoint) this client sends requests to.
+    pub fn region(&self) -> &Region {
+        &self.region
+    }
+
+    /// A client for another Region, configured like this one: same credentials, HTTP dispatcher and custom
+    /// endpoint (requests are then signed for `region`), or [`KmsBackend::for_region`] of the same backend.
+    /// Multi-Region keys need one client per Region they live in.
+    pub fn for_region(&self, region: Region) -> Result<KmsRs, KmsRsError> {
+        self.connector.connect(region)
+    }
+}
+
+impl Default for KmsRs {
+    fn default() -> KmsRs {
+        KmsRs::new(Region::default())
+    }
+}
+
+/// Builder for [`KmsRs`].
+///
+/// ```no_run
+/// use kms_rs::{KmsRs, Region};
+///
+/// let kms = KmsRs::builder()
+///     .region(Region::EuWest1)
+///     .endpoint("http://localhost:4599")
+///     .build()
+///     .expect("Failed to build client");
+/// ```
+#[derive(Default)]
+pub struct KmsRsBuilder {
+    region: Option<Region>,
+    endpoint: Option<String>,
+    credentials: Option<Arc<dyn ProvideAwsCredentials + Send + Sync>>,
+    dispatcher: Option<Arc<dyn DispatchSignedRequest + Send + Sync>>,
+    backend: Option<Arc<dyn KmsBackend>>,
+}
+
+impl KmsRsBuilder {
+    /// Sets the Region requests are signed for and sent to (defaults to `Region::default()`).
+    pub fn region(mut self, region: Region) -> KmsRsBuilder {
+        self.region = Some(region);
+        self
+    }
+
+    /// Sends requests to a custom endpoint (e.g. `http://localhost:4599`) instead of the Region's KMS endpoint.
+    /// Requests are still signed for the configured Region.
+    pub fn endpoint<S: Into<String>>(mut self, endpoint: S) -> KmsRsBuilder {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Sets the provider used to load AWS credentials (defaults to `DefaultCredentialsProvider`).
+    pub fn credentials<P>(mut self, provider: P) -> KmsRsBuilder
+    where
+        P: ProvideAwsCredentials + Send + Sync + 'static,
+    {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+
+    /// Sets the HTTP dispatcher used to send signed requests (defaults to `HttpClient`).
+    pub fn dispatcher<D>(mut self, dispatcher: D) -> KmsRsBuilder
+    where
+        D: DispatchSignedRequest + Send + Sync + 'static,
+    {
+        self.dispatcher = Some(Arc::new(dispatcher));
+        self
+    }
+
+    /// Sends every operation to `backend` instead of AWS KMS. The endpoint, credentials and dispatcher are
+    /// then ignored.
+    pub fn backend<B: KmsBackend + 'static>(mut self, backend: B) -> KmsRsBuilder {
+        self.backend = Some(Arc::new(backend));
+        self
+    }
+
+    /// Builds the client. Fails if the default credentials provider or HTTP dispatcher cannot be created.
+    pub fn build(self) -> Result<KmsRs, KmsRsError> {
+        let region = self.region.unwrap_or_default();
+
+        if let Some(backend) = self.backend {
+            let region = match self.endpoint {
+                Some(endpoint) => Region::Custom {
+                    name: region.name().to_string(),
+                    endpoint,
+                },
+                None => region,
+            };
+            return Ok(KmsRs {
+                backend: backend.clone(),
+                region,
+                connector: Connector::Backend(backend),
+            });
+        }
+        if self.credentials.is_none() && self.dispatcher.is_none() {
+            let connector = Connector::Default {
+                endpoint: self.endpoint,
+            };
+            return connector.connect(region);
+        }
+
+        let credentials = match self.credentials {
+            Some(credentials) => credentials,
+            None => Arc::new(
+                DefaultCredentialsProvider::new()
+                    .map_err(|err| KmsRsError::Credentials(err.to_string()))?,
+            ),
+        };
+        let dispatcher = match self.dispatcher {
+            Some(dispatcher) => dispatcher,
+            None => Arc::new(
+                HttpClient::new().map_err(|err| KmsRsError::HttpDispatch(err.to_string()))?,
+            ),
+        };
+        let connector = Connector::Shared {
+            endpoint: self.endpoint,
+            credentials,
+            dispatcher,
+        };
+        connector.connect(region)
+    }
+}
+
+/// How a [`KmsRs`] creates the backend for a Region, so [`KmsRs::for_region`] gets the same configuration.
+#[derive(Clone)]
+enum Connector {
+    /// A rusoto `KmsClient` with the default credentials chain and HTTP dispatcher.
+    Default { endpoint: Option<String> },
+    /// A rusoto `KmsClient` with the credentials provider and HTTP dispatcher given to the builder.
+    Shared {
+        endpoint: Option<String>,
+        credentials: Arc<dyn ProvideAwsCredentials + Send + Sync>,
+        dispatcher: Arc<dyn DispatchSignedRequest + Send + Sync>,
+    },
+    /// A backend given to the builder, which switches Region itself.
+    Backend(Arc<dyn KmsBackend>),
+}
+
+impl Connector {
+    fn connect(&self, region: Region) -> Result<KmsRs, KmsRsError> {
+        let endpoint = match self {
+            Connector::Default { endpoint } | Connector::Shared { endpoint, .. } => {
+                endpoint.clone()
+            }
+            Connector::Backend(_) => None,
+        };
+        let region = match endpoint {
+            Some(endpoint) => Region::Custom {
+                name: region.name().to_string(),
+                endpoint,
+            },
+            None => region,
+        };
+        let backend: Arc<dyn KmsBackend> = match self {
+            Connector::Default { .. } => Arc::new(KmsClient::new(region.clone())),
+            Connector::Shared {
+                credentials,
+                dispatcher,
+                ..
+            } => Arc::new(KmsClient::new_with(
+                SharedDispatcher(dispatcher.clone()),
+                SharedCredentials(credentials.clone()),
+                region.clone(),
+            )),
+            Connector::Backend(backend) => backend.for_region(&region)?,
+        };
+        Ok(KmsRs {
+            backend,
+            region,
+            connector: self.clone(),
+        })
+    }
+}
+
+struct SharedCredentials(Arc<dyn ProvideAwsCredentials + Send + Sync>);
+
+#[async_trait]
+impl ProvideAwsCredentials for SharedCredentials {
+    async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        self.0.credentials().await
+    }
+}
+
+struct SharedDispatcher(Arc<dyn DispatchSignedRequest + Send + Sync>);
+
+impl DispatchSignedRequest for SharedDispatcher {
+    fn dispatch(
+        &self,
+        request: SignedRequest,
+        timeout: Option<Duration>,
+    ) -> DispatchSignedRequestFuture {
+        self.0.dispatch(request, timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusoto_core::credential::StaticProvider;
+
+    #[test]
+    fn test_builder_with_endpoint() {
+        let kms = KmsRs::builder()
+            .region(Region::EuWest1)
+            .endpoint("http://localhost:4599")
+            .credentials(StaticProvider::new_minimal(
+                "access_key".to_string(),
+                "secret_key".to_string(),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            kms.region(),
+            &Region::Custom {
+                name: "eu-west-1".to_string(),
+                endpoint: "http://localhost:4599".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_builder_with_region() {
+        let kms = KmsRs::builder()
+            .region(Region::ApSoutheast2)
+            .build()
+            .unwrap();
+        assert_eq!(kms.region(), &Region::ApSoutheast2);
+    }
+}
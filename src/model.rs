@@ -0,0 +1,499 @@
+//! Response types returned by the operations in this crate.
+//!
+//! Every type serializes to the same JSON shape AWS uses (PascalCase fields, binary fields as base64).
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::crypto;
+use crate::error::KmsRsError;
+
+/// A customer master key (CMK) as returned by ListKeys.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct KeyListEntry {
+    pub key_id: String,
+    pub key_arn: String,
+}
+
+/// The customer master keys (CMKs) in the caller's AWS account and Region.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListKeysOutput {
+    pub keys: Vec<KeyListEntry>,
+}
+
+/// An alias and the customer master key (CMK) it refers to.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct AliasListEntry {
+    pub alias_name: String,
+    pub alias_arn: String,
+    /// Key ID of the CMK the alias refers to. AWS managed aliases may not have one until the key is first used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_key_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creation_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_updated_date: Option<DateTime<Utc>>,
+}
+
+/// The aliases in the caller's AWS account and Region.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListAliasesOutput {
+    pub aliases: Vec<AliasListEntry>,
+}
+
+/// The grant created by CreateGrant. The token lets the grantee use the grant before it is eventually consistent.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateGrantOutput {
+    pub grant_id: String,
+    pub grant_token: String,
+}
+
+/// A grant on a customer master key (CMK).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct GrantListEntry {
+    pub grant_id: String,
+    pub key_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grantee_principal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retiring_principal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuing_account: Option<String>,
+    pub operations: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<GrantConstraints>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creation_date: Option<DateTime<Utc>>,
+}
+
+/// Encryption context a request must carry for a grant to allow it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct GrantConstraints {
+    /// The request's encryption context must be exactly this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption_context_equals: Option<HashMap<String, String>>,
+    /// The request's encryption context must contain these pairs, and may contain others.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encryption_context_subset: Option<HashMap<String, String>>,
+}
+
+/// The grants returned by ListGrants or ListRetirableGrants.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListGrantsOutput {
+    pub grants: Vec<GrantListEntry>,
+}
+
+/// A tag on a customer master key (CMK).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "PascalCase")]
+pub struct Tag {
+    pub tag_key: String,
+    pub tag_value: String,
+}
+
+/// The tags on a customer master key (CMK).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListResourceTagsOutput {
+    pub tags: Vec<Tag>,
+}
+
+/// The names of the key policies of a customer master key (CMK). The only name is `default`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ListKeyPoliciesOutput {
+    pub policy_names: Vec<String>,
+}
+
+/// Detailed information about a customer master key (CMK).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct KeyMetadata {
+    pub key_id: String,
+    pub arn: String,
+    #[serde(rename = "AWSAccountId", skip_serializing_if = "Option::is_none")]
+    pub aws_account_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_usage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_master_key_spec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_manager: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creation_date: Option<DateTime<Utc>>,
+    /// When the CMK will be deleted. Only present while its key state is `PendingDeletion`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion_date: Option<DateTime<Utc>>,
+    /// The waiting period before deletion, in days. Only present while its key state is `PendingDeletion`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_deletion_window_in_days: Option<i64>,
+    /// When imported key material expires. Only present for CMKs with imported key material that expires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_to: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_key_store_id: Option<String>,
+    #[serde(rename = "CloudHsmClusterId", skip_serializing_if = "Option::is_none")]
+    pub cloud_hsm_cluster_id: Option<String>,
+    #[serde(default)]
+    pub multi_region: bool,
+    /// The primary key and replicas of a multi-Region key, and which of them this CMK is. Only present for
+    /// multi-Region keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multi_region_configuration: Option<MultiRegionConfiguration>,
+    #[serde(default)]
+    pub encryption_algorithms: Vec<String>,
+    #[serde(default)]
+    pub signing_algorithms: Vec<String>,
+}
+
+impl KeyMetadata {
+    /// Whether the CMK can currently be used in cryptographic operations (its key state is `Enabled`).
+    pub fn is_enabled(&self) -> bool {
+        self.key_state.as_deref() == Some("Enabled")
+    }
+
+    /// Whether the CMK is scheduled for deletion.
+    pub fn is_pending_deletion(&self) -> bool {
+        self.key_state.as_deref() == Some("PendingDeletion")
+    }
+
+    /// Whether the CMK is an AWS managed CMK (as opposed to a customer managed CMK).
+    pub fn is_aws_managed(&self) -> bool {
+        self.key_manager.as_deref() == Some("AWS")
+    }
+
+    /// Whether the CMK is asymmetric (any spec other than `SYMMETRIC_DEFAULT`).
+    pub fn is_asymmetric(&self) -> bool {
+        match self.customer_master_key_spec.as_deref() {
+            Some(spec) => spec != "SYMMETRIC_DEFAULT",
+            None => false,
+        }
+    }
+
+    /// Whether the CMK is used to sign and verify (its key usage is `SIGN_VERIFY`).
+    pub fn can_sign(&self) -> bool {
+        self.key_usage.as_deref() == Some("SIGN_VERIFY")
+    }
+
+    /// Whether the CMK is used to encrypt and decrypt (its key usage is `ENCRYPT_DECRYPT`).
+    pub fn can_encrypt(&self) -> bool {
+        self.key_usage.as_deref() == Some("ENCRYPT_DECRYPT")
+    }
+
+    /// Whether the key material was imported (its origin is `EXTERNAL`).
+    pub fn has_imported_key_material(&self) -> bool {
+        self.origin.as_deref() == Some("EXTERNAL")
+    }
+
+    /// Whether the CMK is a multi-Region primary key, which can be replicated into other Regions.
+    pub fn is_multi_region_primary(&self) -> bool {
+        self.multi_region_key_type() == Some("PRIMARY")
+    }
+
+    /// Whether the CMK is a replica of a multi-Region primary key.
+    pub fn is_multi_region_replica(&self) -> bool {
+        self.multi_region_key_type() == Some("REPLICA")
+    }
+
+    fn multi_region_key_type(&self) -> Option<&str> {
+        self.multi_region_configuration
+            .as_ref()
+            .and_then(|configuration| configuration.multi_region_key_type.as_deref())
+    }
+}
+
+/// The related multi-Region keys of a multi-Region CMK. They share key material and key ID.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultiRegionConfiguration {
+    /// `PRIMARY` or `REPLICA`: which of the related keys this CMK is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multi_region_key_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<MultiRegionKey>,
+    #[serde(default)]
+    pub replica_keys: Vec<MultiRegionKey>,
+}
+
+/// A multi-Region key in one Region.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultiRegionKey {
+    pub arn: String,
+    pub region: String,
+}
+
+/// A custom key store: an AWS CloudHSM cluster that holds the key material of the CMKs created in it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct CustomKeyStore {
+    pub custom_key_store_id: String,
+    pub custom_key_store_name: String,
+    #[serde(rename = "CloudHsmClusterId", skip_serializing_if = "Option::is_none")]
+    pub cloud_hsm_cluster_id: Option<String>,
+    /// The PEM-encoded certificate of the cluster's issuing CA.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trust_anchor_certificate: Option<String>,
+    /// `CONNECTED`, `CONNECTING`, `FAILED`, `DISCONNECTED` or `DISCONNECTING`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_state: Option<String>,
+    /// Why the last connection attempt failed. Only present while the connection state is `FAILED`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creation_date: Option<DateTime<Utc>>,
+}
+
+impl CustomKeyStore {
+    /// Whether CMKs can be created and used in the custom key store (its connection state is `CONNECTED`).
+    pub fn is_connected(&self) -> bool {
+        self.connection_state.as_deref() == Some("CONNECTED")
+    }
+}
+
+/// The custom key stores in the caller's AWS account and Region.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct DescribeCustomKeyStoresOutput {
+    pub custom_key_stores: Vec<CustomKeyStore>,
+}
+
+/// The result of scheduling a CMK for deletion.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ScheduleKeyDeletionOutput {
+    pub key_id: String,
+    /// The date and time after which the CMK is deleted.
+    pub deletion_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_window_in_days: Option<i64>,
+}
+
+/// The result of cancelling the deletion of a CMK.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct CancelKeyDeletionOutput {
+    pub key_id: String,
+}
+
+/// Whether KMS rotates the key material of a CMK every year.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct GetKeyRotationStatusOutput {
+    pub key_rotation_enabled: bool,
+}
+
+/// A symmetric data key. The plaintext copy is only present when requested through GenerateDataKey.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct DataKey {
+    pub key_id: String,
+    #[serde(with = "base64_bytes")]
+    pub ciphertext_blob: Bytes,
+    #[serde(
+        default,
+        with = "base64_option_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub plaintext: Option<Bytes>,
+}
+
+/// An asymmetric data key pair. The plaintext private key is only present when requested through GenerateDataKeyPair.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct DataKeyPair {
+    pub key_id: String,
+    pub key_pair_spec: String,
+    #[serde(with = "base64_bytes")]
+    pub private_key_ciphertext_blob: Bytes,
+    #[serde(
+        default,
+        with = "base64_option_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub private_key_plaintext: Option<Bytes>,
+    #[serde(with = "base64_bytes")]
+    pub public_key: Bytes,
+}
+
+/// Ciphertext produced by the Encrypt operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct EncryptOutput {
+    pub key_id: String,
+    #[serde(with = "base64_bytes")]
+    pub ciphertext_blob: Bytes,
+    pub encryption_algorithm: String,
+}
+
+/// Plaintext produced by the Decrypt operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct DecryptOutput {
+    pub key_id: String,
+    #[serde(with = "base64_bytes")]
+    pub plaintext: Bytes,
+    pub encryption_algorithm: String,
+}
+
+/// Ciphertext produced by the ReEncrypt operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ReEncryptOutput {
+    /// ARN of the CMK the ciphertext was decrypted with.
+    pub source_key_id: String,
+    /// ARN of the CMK the ciphertext is now encrypted under.
+    pub key_id: String,
+    #[serde(with = "base64_bytes")]
+    pub ciphertext_blob: Bytes,
+    pub source_encryption_algorithm: String,
+    pub destination_encryption_algorithm: String,
+}
+
+/// Passed to the progress callback of [`crate::KmsRs::re_encrypt_all`] each time a ciphertext is done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReEncryptProgress {
+    /// Position in the input of the ciphertext that just finished.
+    pub index: usize,
+    /// Ciphertexts finished so far, including failures.
+    pub completed: usize,
+    /// Ciphertexts that failed so far.
+    pub failed: usize,
+}
+
+/// A signature produced by the Sign operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct SignOutput {
+    pub key_id: String,
+    #[serde(with = "base64_bytes")]
+    pub signature: Bytes,
+    pub signing_algorithm: String,
+}
+
+/// The result of the Verify operation.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct VerifyOutput {
+    pub key_id: String,
+    pub signature_valid: bool,
+    pub signing_algorithm: String,
+}
+
+/// The public key of an asymmetric CMK, DER-encoded as a `SubjectPublicKeyInfo`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct PublicKey {
+    pub key_id: String,
+    #[serde(with = "base64_bytes")]
+    pub public_key: Bytes,
+    pub customer_master_key_spec: String,
+    pub key_usage: String,
+    #[serde(default)]
+    pub encryption_algorithms: Vec<String>,
+    #[serde(default)]
+    pub signing_algorithms: Vec<String>,
+}
+
+impl PublicKey {
+    /// Encrypts `plaintext` with this public key of an RSA `ENCRYPT_DECRYPT` CMK, without calling KMS. The
+    /// ciphertext is decrypted by Decrypt with the same CMK and `encryption_algorithm` (`RSAES_OAEP_SHA_1` or
+    /// `RSAES_OAEP_SHA_256`), so devices holding a stored `PublicKey` can encrypt without AWS credentials.
+    pub fn encrypt(
+        &self,
+        plaintext: &[u8],
+        encryption_algorithm: &str,
+    ) -> Result<EncryptOutput, KmsRsError> {
+        if self.key_usage != "ENCRYPT_DECRYPT" {
+            return Err(KmsRsError::InvalidKeyUsage(format!(
+                "{} key usage is {} which is not valid for Encrypt.",
+                self.key_id, self.key_usage
+            )));
+        }
+        if !self
+            .encryption_algorithms
+            .iter()
+            .any(|algorithm| algorithm == encryption_algorithm)
+        {
+            return Err(KmsRsError::InvalidKeyUsage(format!(
+                "Algorithm {} is incompatible with key spec {}.",
+                encryption_algorithm, self.customer_master_key_spec
+            )));
+        }
+
+        let ciphertext_blob = crypto::PublicKey::from_der(&self.public_key)?
+            .encrypt(encryption_algorithm, plaintext)?;
+        Ok(EncryptOutput {
+            key_id: self.key_id.clone(),
+            ciphertext_blob: Bytes::from(ciphertext_blob),
+            encryption_algorithm: encryption_algorithm.to_string(),
+        })
+    }
+}
+
+/// (De)serializes `Bytes` as a base64 string.
+pub(crate) mod base64_bytes {
+    use bytes::Bytes;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::decode(encoded)
+            .map(Bytes::from)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// (De)serializes `Option<Bytes>` as an optional base64 string.
+pub(crate) mod base64_option_bytes {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Bytes>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => super::base64_bytes::serialize(bytes, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Bytes>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::base64_bytes")] Bytes);
+
+        let wrapper: Option<Wrapper> = Option::deserialize(deserializer)?;
+        Ok(wrapper.map(|Wrapper(bytes)| bytes))
+    }
+}
@@ -0,0 +1,616 @@
+//! Optional parameters for operations that take more than a handful of arguments.
+
+use bytes::Bytes;
+use rusoto_kms::{
+    CreateGrantRequest, CreateKeyRequest, GrantConstraints, ReEncryptRequest, Tag,
+    UpdateCustomKeyStoreRequest,
+};
+use std::collections::HashMap;
+
+use crate::model::{self, KeyMetadata};
+
+/// Options for [`crate::KmsRs::create_key`]. The default creates a symmetric `ENCRYPT_DECRYPT` CMK with
+/// the default key policy, the same as `aws kms create-key` without arguments.
+///
+/// ```
+/// use kms_rs::CreateKeyOptions;
+///
+/// let options = CreateKeyOptions::new()
+///     .key_usage("SIGN_VERIFY")
+///     .customer_master_key_spec("ECC_NIST_P256")
+///     .description("Release signing key")
+///     .tag("Owner", "release-engineering");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreateKeyOptions {
+    key_usage: Option<String>,
+    customer_master_key_spec: Option<String>,
+    description: Option<String>,
+    policy: Option<String>,
+    tags: Vec<(String, String)>,
+    origin: Option<String>,
+    custom_key_store_id: Option<String>,
+    bypass_policy_lockout_safety_check: bool,
+    multi_region: bool,
+}
+
+impl CreateKeyOptions {
+    /// Same as `CreateKeyOptions::default()`.
+    pub fn new() -> CreateKeyOptions {
+        CreateKeyOptions::default()
+    }
+
+    /// `ENCRYPT_DECRYPT` (default) or `SIGN_VERIFY`. RSA CMKs support either, ECC CMKs only `SIGN_VERIFY`.
+    pub fn key_usage<S: Into<String>>(mut self, key_usage: S) -> CreateKeyOptions {
+        self.key_usage = Some(key_usage.into());
+        self
+    }
+
+    /// `SYMMETRIC_DEFAULT` (default), `RSA_2048`, `RSA_3072`, `RSA_4096`, `ECC_NIST_P256`, `ECC_NIST_P384`,
+    /// `ECC_NIST_P521` or `ECC_SECG_P256K1`.
+    pub fn customer_master_key_spec<S: Into<String>>(mut self, spec: S) -> CreateKeyOptions {
+        self.customer_master_key_spec = Some(spec.into());
+        self
+    }
+
+    /// A description of the CMK (up to 8192 characters).
+    pub fn description<S: Into<String>>(mut self, description: S) -> CreateKeyOptions {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The key policy, as a JSON document. When omitted, AWS KMS attaches the default key policy.
+    pub fn policy<S: Into<String>>(mut self, policy: S) -> CreateKeyOptions {
+        self.policy = Some(policy.into());
+        self
+    }
+
+    /// Adds a tag to the CMK. Can be called once per tag.
+    pub fn tag<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> CreateKeyOptions {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    /// The source of the key material: `AWS_KMS` (default), `EXTERNAL` (to import key material later)
+    /// or `AWS_CLOUDHSM` (requires [`CreateKeyOptions::custom_key_store_id`]).
+    pub fn origin<S: Into<String>>(mut self, origin: S) -> CreateKeyOptions {
+        self.origin = Some(origin.into());
+        self
+    }
+
+    /// Creates the CMK in the given custom key store (an AWS CloudHSM cluster).
+    pub fn custom_key_store_id<S: Into<String>>(
+        mut self,
+        custom_key_store_id: S,
+    ) -> CreateKeyOptions {
+        self.custom_key_store_id = Some(custom_key_store_id.into());
+        self
+    }
+
+    /// Skips the check that the key policy still allows the caller to make subsequent PutKeyPolicy requests.
+    /// Setting this is a security risk: the CMK can become unmanageable.
+    pub fn bypass_policy_lockout_safety_check(mut self, bypass: bool) -> CreateKeyOptions {
+        self.bypass_policy_lockout_safety_check = bypass;
+        self
+    }
+
+    /// Creates a multi-Region primary key that can be replicated into other Regions.
+    pub fn multi_region(mut self, multi_region: bool) -> CreateKeyOptions {
+        self.multi_region = multi_region;
+        self
+    }
+
+    pub(crate) fn into_request(self) -> CreateKeyRequest {
+        let tags = if self.tags.is_empty() {
+            None
+        } else {
+            Some(
+                self.tags
+                    .into_iter()
+                    .map(|(tag_key, tag_value)| Tag { tag_key, tag_value })
+                    .collect(),
+            )
+        };
+
+        CreateKeyRequest {
+            bypass_policy_lockout_safety_check: Some(self.bypass_policy_lockout_safety_check)
+                .filter(|bypass| *bypass),
+            custom_key_store_id: self.custom_key_store_id,
+            customer_master_key_spec: self.customer_master_key_spec,
+            description: self.description,
+            key_usage: self.key_usage,
+            multi_region: Some(self.multi_region).filter(|multi_region| *multi_region),
+            origin: self.origin,
+            policy: self.policy,
+            tags,
+        }
+    }
+}
+
+/// Options for [`crate::KmsRs::create_grant`] and [`crate::GrantGuard::create`]: who may use the CMK,
+/// for which operations, and under which encryption context.
+///
+/// ```
+/// use kms_rs::CreateGrantOptions;
+///
+/// let options = CreateGrantOptions::new("arn:aws:iam::111122223333:role/batch-worker")
+///     .operation("Decrypt")
+///     .operation("GenerateDataKey")
+///     .retiring_principal("arn:aws:iam::111122223333:role/batch-scheduler")
+///     .encryption_context_subset("job", "nightly-export");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreateGrantOptions {
+    grantee_principal: String,
+    operations: Vec<String>,
+    name: Option<String>,
+    retiring_principal: Option<String>,
+    encryption_context_equals: HashMap<String, String>,
+    encryption_context_subset: HashMap<String, String>,
+    grant_tokens: Vec<String>,
+}
+
+impl CreateGrantOptions {
+    /// A grant for `grantee_principal`, the ARN of an AWS principal (account, IAM user or role, federated
+    /// user or assumed role user). At least one operation must be added.
+    pub fn new<S: Into<String>>(grantee_principal: S) -> CreateGrantOptions {
+        CreateGrantOptions {
+            grantee_principal: grantee_principal.into(),
+            ..CreateGrantOptions::default()
+        }
+    }
+
+    /// Allows an operation, e.g. `Decrypt`, `Encrypt`, `GenerateDataKey`, `Sign`, `RetireGrant` or
+    /// `CreateGrant`. Can be called once per operation.
+    pub fn operation<S: Into<String>>(mut self, operation: S) -> CreateGrantOptions {
+        self.operations.push(operation.into());
+        self
+    }
+
+    /// A friendly name. Retrying CreateGrant with the same name and parameters returns the existing grant
+    /// instead of creating a new one.
+    pub fn name<S: Into<String>>(mut self, name: S) -> CreateGrantOptions {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// The principal allowed to retire the grant, besides the grantee if it is given `RetireGrant`.
+    pub fn retiring_principal<S: Into<String>>(mut self, principal: S) -> CreateGrantOptions {
+        self.retiring_principal = Some(principal.into());
+        self
+    }
+
+    /// Only allows requests whose encryption context is exactly the pairs added here. Can be called once
+    /// per pair. Cannot be combined with [`CreateGrantOptions::encryption_context_subset`].
+    pub fn encryption_context_equals<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> CreateGrantOptions {
+        self.encryption_context_equals
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Only allows requests whose encryption context includes the pairs added here. Can be called once
+    /// per pair.
+    pub fn encryption_context_subset<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> CreateGrantOptions {
+        self.encryption_context_subset
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// A grant token that allows this CreateGrant call itself, when the caller's permission comes from a
+    /// grant that is not yet eventually consistent.
+    pub fn grant_token<S: Into<String>>(mut self, grant_token: S) -> CreateGrantOptions {
+        self.grant_tokens.push(grant_token.into());
+        self
+    }
+
+    pub(crate) fn into_request(self, key_id: &str) -> CreateGrantRequest {
+        let constraints = if self.encryption_context_equals.is_empty()
+            && self.encryption_context_subset.is_empty()
+        {
+            None
+        } else {
+            Some(GrantConstraints {
+                encryption_context_equals: Some(self.encryption_context_equals)
+                    .filter(|context| !context.is_empty()),
+                encryption_context_subset: Some(self.encryption_context_subset)
+                    .filter(|context| !context.is_empty()),
+            })
+        };
+
+        CreateGrantRequest {
+            constraints,
+            grant_tokens: Some(self.grant_tokens).filter(|tokens| !tokens.is_empty()),
+            grantee_principal: self.grantee_principal,
+            key_id: key_id.to_string(),
+            name: self.name,
+            operations: self.operations,
+            retiring_principal: self.retiring_principal,
+        }
+    }
+}
+
+/// Options for [`crate::KmsRs::re_encrypt`] and [`crate::KmsRs::re_encrypt_all`]. The source CMK is read
+/// from symmetric ciphertexts, so only the destination is required.
+///
+/// ```
+/// use kms_rs::ReEncryptOptions;
+///
+/// let options = ReEncryptOptions::new("alias/payments-2024")
+///     .source_encryption_context("table", "payments")
+///     .destination_encryption_context("table", "payments");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReEncryptOptions {
+    destination_key_id: String,
+    destination_encryption_context: HashMap<String, String>,
+    destination_encryption_algorithm: Option<String>,
+    source_key_id: Option<String>,
+    source_encryption_context: HashMap<String, String>,
+    source_encryption_algorithm: Option<String>,
+    grant_tokens: Vec<String>,
+}
+
+impl ReEncryptOptions {
+    /// Re-encrypts under `destination_key_id`, a key ID, key ARN, alias name or alias ARN.
+    pub fn new<S: Into<String>>(destination_key_id: S) -> ReEncryptOptions {
+        ReEncryptOptions {
+            destination_key_id: destination_key_id.into(),
+            ..ReEncryptOptions::default()
+        }
+    }
+
+    /// The CMK the ciphertexts were encrypted under. Required for asymmetric CMKs; for symmetric CMKs,
+    /// requests fail with `IncorrectKey` if it doesn't match.
+    pub fn source_key_id<S: Into<String>>(mut self, source_key_id: S) -> ReEncryptOptions {
+        self.source_key_id = Some(source_key_id.into());
+        self
+    }
+
+    /// A pair of the encryption context the ciphertexts were encrypted with. Can be called once per pair.
+    pub fn source_encryption_context<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> ReEncryptOptions {
+        self.source_encryption_context
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// The algorithm the ciphertexts were encrypted with, `SYMMETRIC_DEFAULT` (default) or `RSAES_OAEP_SHA_*`.
+    pub fn source_encryption_algorithm<S: Into<String>>(
+        mut self,
+        algorithm: S,
+    ) -> ReEncryptOptions {
+        self.source_encryption_algorithm = Some(algorithm.into());
+        self
+    }
+
+    /// A pair of the encryption context to encrypt with under the destination CMK. Can be called once per
+    /// pair.
+    pub fn destination_encryption_context<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> ReEncryptOptions {
+        self.destination_encryption_context
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// The algorithm to encrypt with under the destination CMK, `SYMMETRIC_DEFAULT` (default) or
+    /// `RSAES_OAEP_SHA_*`.
+    pub fn destination_encryption_algorithm<S: Into<String>>(
+        mut self,
+        algorithm: S,
+    ) -> ReEncryptOptions {
+        self.destination_encryption_algorithm = Some(algorithm.into());
+        self
+    }
+
+    /// A grant token for a grant that allows the request but is not yet eventually consistent.
+    pub fn grant_token<S: Into<String>>(mut self, grant_token: S) -> ReEncryptOptions {
+        self.grant_tokens.push(grant_token.into());
+        self
+    }
+
+    pub(crate) fn to_request(&self, ciphertext_blob: Bytes) -> ReEncryptRequest {
+        let context =
+            |context: &HashMap<String, String>| Some(context.clone()).filter(|c| !c.is_empty());
+
+        ReEncryptRequest {
+            ciphertext_blob,
+            destination_encryption_algorithm: self.destination_encryption_algorithm.clone(),
+            destination_encryption_context: context(&self.destination_encryption_context),
+            destination_key_id: self.destination_key_id.clone(),
+            grant_tokens: Some(self.grant_tokens.clone()).filter(|tokens| !tokens.is_empty()),
+            source_encryption_algorithm: self.source_encryption_algorithm.clone(),
+            source_encryption_context: context(&self.source_encryption_context),
+            source_key_id: self.source_key_id.clone(),
+        }
+    }
+}
+
+/// Changes made by [`crate::KmsRs::update_custom_key_store`]. The custom key store must be disconnected.
+///
+/// ```
+/// use kms_rs::UpdateCustomKeyStoreOptions;
+///
+/// let options = UpdateCustomKeyStoreOptions::new()
+///     .new_custom_key_store_name("ExampleKeyStore-2")
+///     .cloud_hsm_cluster_id("cluster-1a23b4cdefg");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpdateCustomKeyStoreOptions {
+    new_custom_key_store_name: Option<String>,
+    key_store_password: Option<String>,
+    cloud_hsm_cluster_id: Option<String>,
+}
+
+impl UpdateCustomKeyStoreOptions {
+    /// Same as `UpdateCustomKeyStoreOptions::default()`, which changes nothing.
+    pub fn new() -> UpdateCustomKeyStoreOptions {
+        UpdateCustomKeyStoreOptions::default()
+    }
+
+    /// Renames the custom key store. The name must be unique in the account and Region.
+    pub fn new_custom_key_store_name<S: Into<String>>(
+        mut self,
+        name: S,
+    ) -> UpdateCustomKeyStoreOptions {
+        self.new_custom_key_store_name = Some(name.into());
+        self
+    }
+
+    /// Tells KMS the current password of the `kmsuser` crypto user, after it was changed in the cluster.
+    /// This does not change the password in the cluster.
+    pub fn key_store_password<S: Into<String>>(
+        mut self,
+        password: S,
+    ) -> UpdateCustomKeyStoreOptions {
+        self.key_store_password = Some(password.into());
+        self
+    }
+
+    /// Associates the custom key store with a related cluster, e.g. one restored from a backup of the
+    /// original cluster.
+    pub fn cloud_hsm_cluster_id<S: Into<String>>(
+        mut self,
+        cloud_hsm_cluster_id: S,
+    ) -> UpdateCustomKeyStoreOptions {
+        self.cloud_hsm_cluster_id = Some(cloud_hsm_cluster_id.into());
+        self
+    }
+
+    pub(crate) fn into_request(self, custom_key_store_id: &str) -> UpdateCustomKeyStoreRequest {
+        UpdateCustomKeyStoreRequest {
+            cloud_hsm_cluster_id: self.cloud_hsm_cluster_id,
+            custom_key_store_id: custom_key_store_id.to_string(),
+            key_store_password: self.key_store_password,
+            new_custom_key_store_name: self.new_custom_key_store_name,
+        }
+    }
+}
+
+/// Which keys [`crate::KmsRs::find_keys`] returns. Every condition must hold; the default matches every key.
+///
+/// ```
+/// use kms_rs::KeyFilter;
+///
+/// let filter = KeyFilter::new()
+///     .tag("Environment", "prod")
+///     .tag_in("Classification", &["confidential", "restricted"])
+///     .key_state("Enabled")
+///     .key_spec("SYMMETRIC_DEFAULT");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyFilter {
+    tags: Vec<TagPredicate>,
+    key_states: Vec<String>,
+    key_specs: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TagPredicate {
+    OneOf(String, Vec<String>),
+    Present(String),
+    Absent(String),
+}
+
+impl KeyFilter {
+    /// Same as `KeyFilter::default()`.
+    pub fn new() -> KeyFilter {
+        KeyFilter::default()
+    }
+
+    /// Only keys tagged `key` with the value `value`.
+    pub fn tag<K: Into<String>, V: Into<String>>(self, key: K, value: V) -> KeyFilter {
+        self.tag_predicate(TagPredicate::OneOf(key.into(), vec![value.into()]))
+    }
+
+    /// Only keys tagged `key` with one of `values`.
+    pub fn tag_in<K: Into<String>>(self, key: K, values: &[&str]) -> KeyFilter {
+        let values = values.iter().map(|value| value.to_string()).collect();
+        self.tag_predicate(TagPredicate::OneOf(key.into(), values))
+    }
+
+    /// Only keys tagged `key`, whatever the value.
+    pub fn has_tag<K: Into<String>>(self, key: K) -> KeyFilter {
+        self.tag_predicate(TagPredicate::Present(key.into()))
+    }
+
+    /// Only keys without a `key` tag, e.g. to find keys missing an owner.
+    pub fn without_tag<K: Into<String>>(self, key: K) -> KeyFilter {
+        self.tag_predicate(TagPredicate::Absent(key.into()))
+    }
+
+    fn tag_predicate(mut self, predicate: TagPredicate) -> KeyFilter {
+        self.tags.push(predicate);
+        self
+    }
+
+    /// Only keys in this key state, e.g. `Enabled` or `PendingDeletion`. When called several times, keys in
+    /// any of the states match.
+    pub fn key_state<S: Into<String>>(mut self, key_state: S) -> KeyFilter {
+        self.key_states.push(key_state.into());
+        self
+    }
+
+    /// Only keys of this key spec, e.g. `SYMMETRIC_DEFAULT` or `RSA_2048`. When called several times, keys
+    /// of any of the specs match.
+    pub fn key_spec<S: Into<String>>(mut self, key_spec: S) -> KeyFilter {
+        self.key_specs.push(key_spec.into());
+        self
+    }
+
+    pub(crate) fn has_tag_predicates(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    pub(crate) fn matches_metadata(&self, metadata: &KeyMetadata) -> bool {
+        let one_of = |allowed: &[String], value: &Option<String>| {
+            allowed.is_empty() || value.as_ref().is_some_and(|value| allowed.contains(value))
+        };
+        one_of(&self.key_states, &metadata.key_state)
+            && one_of(&self.key_specs, &metadata.customer_master_key_spec)
+    }
+
+    pub(crate) fn matches_tags(&self, tags: &[model::Tag]) -> bool {
+        let value_of = |key: &str| {
+            tags.iter()
+                .find(|tag| tag.tag_key == key)
+                .map(|tag| &tag.tag_value)
+        };
+        self.tags.iter().all(|predicate| match predicate {
+            TagPredicate::OneOf(key, values) => value_of(key).is_some_and(|v| values.contains(v)),
+            TagPredicate::Present(key) => value_of(key).is_some(),
+            TagPredicate::Absent(key) => value_of(key).is_none(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_request() {
+        assert_eq!(
+            CreateKeyOptions::new().into_request(),
+            CreateKeyRequest::default()
+        );
+    }
+
+    #[test]
+    fn test_into_request() {
+        let request = CreateKeyOptions::new()
+            .key_usage("ENCRYPT_DECRYPT")
+            .customer_master_key_spec("RSA_3072")
+            .description("RSA encryption key")
+            .tag("Owner", "security")
+            .tag("Environment", "prod")
+            .origin("AWS_KMS")
+            .into_request();
+        assert_eq!(request.key_usage, Some("ENCRYPT_DECRYPT".to_string()));
+        assert_eq!(
+            request.customer_master_key_spec,
+            Some("RSA_3072".to_string())
+        );
+        assert_eq!(request.description, Some("RSA encryption key".to_string()));
+        assert_eq!(
+            request.tags,
+            Some(vec![
+                Tag {
+                    tag_key: "Owner".to_string(),
+                    tag_value: "security".to_string(),
+                },
+                Tag {
+                    tag_key: "Environment".to_string(),
+                    tag_value: "prod".to_string(),
+                },
+            ])
+        );
+        assert_eq!(request.bypass_policy_lockout_safety_check, None);
+        assert_eq!(request.multi_region, None);
+    }
+
+    #[test]
+    fn test_grant_request() {
+        let request = CreateGrantOptions::new("arn:aws:iam::111122223333:role/worker")
+            .operation("Decrypt")
+            .operation("RetireGrant")
+            .encryption_context_subset("job", "export")
+            .into_request("abcd-4321-wxyz");
+        assert_eq!(request.key_id, "abcd-4321-wxyz");
+        assert_eq!(
+            request.grantee_principal,
+            "arn:aws:iam::111122223333:role/worker"
+        );
+        assert_eq!(request.operations, vec!["Decrypt", "RetireGrant"]);
+        let constraints = request.constraints.unwrap();
+        assert_eq!(constraints.encryption_context_equals, None);
+        assert_eq!(
+            constraints.encryption_context_subset,
+            Some(HashMap::from([("job".to_string(), "export".to_string())]))
+        );
+        assert_eq!(request.grant_tokens, None);
+    }
+
+    #[test]
+    fn test_key_filter() {
+        let tag = |key: &str, value: &str| model::Tag {
+            tag_key: key.to_string(),
+            tag_value: value.to_string(),
+        };
+        let filter = KeyFilter::new()
+            .tag_in("Environment", &["prod", "staging"])
+            .has_tag("Owner")
+            .without_tag("Deprecated");
+        assert!(filter.matches_tags(&[tag("Environment", "prod"), tag("Owner", "payments")]));
+        assert!(!filter.matches_tags(&[tag("Environment", "dev"), tag("Owner", "payments")]));
+        assert!(!filter.matches_tags(&[tag("Environment", "prod")]));
+        assert!(!filter.matches_tags(&[
+            tag("Environment", "prod"),
+            tag("Owner", "payments"),
+            tag("Deprecated", "true"),
+        ]));
+
+        let metadata = KeyMetadata {
+            key_state: Some("Enabled".to_string()),
+            customer_master_key_spec: Some("RSA_2048".to_string()),
+            ..KeyMetadata::default()
+        };
+        assert!(KeyFilter::new().matches_metadata(&metadata));
+        assert!(KeyFilter::new()
+            .key_state("Disabled")
+            .key_state("Enabled")
+            .matches_metadata(&metadata));
+        assert!(!KeyFilter::new()
+            .key_spec("SYMMETRIC_DEFAULT")
+            .matches_metadata(&metadata));
+    }
+
+    #[test]
+    fn test_re_encrypt_request() {
+        let options = ReEncryptOptions::new("alias/new")
+            .source_encryption_context("table", "payments")
+            .grant_token("token");
+        let request = options.to_request(Bytes::from_static(b"blob"));
+        assert_eq!(request.destination_key_id, "alias/new");
+        assert_eq!(request.source_key_id, None);
+        assert_eq!(
+            request.source_encryption_context.unwrap().get("table"),
+            Some(&"payments".to_string())
+        );
+        assert_eq!(request.destination_encryption_context, None);
+        assert_eq!(request.grant_tokens, Some(vec!["token".to_string()]));
+    }
+}
@@ -1,221 +1,307 @@
 //! Parsing functions to handle Responses from rusoto_kms.
-#![allow(non_snake_case)]
 
 use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
 use rusoto_kms::{
-    CancelKeyDeletionResponse, DecryptResponse, EncryptResponse, GenerateDataKeyPairResponse,
+    AliasListEntry, CancelKeyDeletionResponse, CreateGrantResponse, CustomKeyStoresListEntry,
+    DecryptResponse, EncryptResponse, GenerateDataKeyPairResponse,
     GenerateDataKeyPairWithoutPlaintextResponse, GenerateDataKeyResponse,
     GenerateDataKeyWithoutPlaintextResponse, GenerateRandomResponse, GetPublicKeyResponse,
-    KeyListEntry, KeyMetadata, ScheduleKeyDeletionResponse, SignResponse, VerifyResponse,
+    GrantConstraints, GrantListEntry, KeyListEntry, KeyMetadata, MultiRegionConfiguration,
+    MultiRegionKey, ReEncryptResponse, ScheduleKeyDeletionResponse, SignResponse, Tag,
+    VerifyResponse,
 };
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use serde_json::value::Value;
-
-#[derive(Serialize, Deserialize, Debug)]
-struct KmsRsKey {
-    KeyId: String,
-    KeyArn: String,
+
+use crate::model;
+
+pub fn key_list_entries(key_list: Vec<KeyListEntry>) -> model::ListKeysOutput {
+    let mut keys: Vec<model::KeyListEntry> = Vec::new();
+
+    for key in key_list {
+        keys.push(key_list_entry(key));
+    }
+
+    model::ListKeysOutput { keys }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct KmsRsKeyMetadata {
-    KeyId: String,
-    Arn: String,
-    Description: String,
-    Enabled: bool,
+pub fn key_list_entry(key: KeyListEntry) -> model::KeyListEntry {
+    model::KeyListEntry {
+        key_id: key.key_id.unwrap_or_default(),
+        key_arn: key.key_arn.unwrap_or_default(),
+    }
 }
 
-pub fn key_list_entries(key_list: Vec<KeyListEntry>) -> Value {
-    let mut keys_json: Vec<KmsRsKey> = Vec::new();
+pub fn alias_list_entries(alias_list: Vec<AliasListEntry>) -> model::ListAliasesOutput {
+    let mut aliases: Vec<model::AliasListEntry> = Vec::new();
 
-    for key in key_list {
-        keys_json.push(KmsRsKey {
-            KeyId: key.key_id.unwrap_or_default(),
-            KeyArn: key.key_arn.unwrap_or_default(),
-        });
+    for alias in alias_list {
+        aliases.push(alias_list_entry(alias));
+    }
+
+    model::ListAliasesOutput { aliases }
+}
+
+pub fn alias_list_entry(alias: AliasListEntry) -> model::AliasListEntry {
+    model::AliasListEntry {
+        alias_name: alias.alias_name.unwrap_or_default(),
+        alias_arn: alias.alias_arn.unwrap_or_default(),
+        target_key_id: alias.target_key_id,
+        creation_date: alias.creation_date.and_then(epoch_to_date_time),
+        last_updated_date: alias.last_updated_date.and_then(epoch_to_date_time),
     }
+}
 
-    json!({ "Keys": &keys_json })
+pub fn create_grant_response(response: CreateGrantResponse) -> model::CreateGrantOutput {
+    model::CreateGrantOutput {
+        grant_id: response.grant_id.unwrap_or_default(),
+        grant_token: response.grant_token.unwrap_or_default(),
+    }
 }
 
-pub fn key_metadata(metatdata: KeyMetadata) -> Value {
-    let key_metadata = KmsRsKeyMetadata {
-        KeyId: metatdata.key_id,
-        Arn: metatdata.arn.unwrap_or_default(),
-        Description: metatdata.description.unwrap_or_default(),
-        Enabled: metatdata.enabled.unwrap_or_default(),
-    };
-    json!(&key_metadata)
+pub fn grant_list_entries(grant_list: Vec<GrantListEntry>) -> model::ListGrantsOutput {
+    let mut grants: Vec<model::GrantListEntry> = Vec::new();
+
+    for grant in grant_list {
+        grants.push(grant_list_entry(grant));
+    }
+
+    model::ListGrantsOutput { grants }
+}
+
+pub fn grant_list_entry(grant: GrantListEntry) -> model::GrantListEntry {
+    model::GrantListEntry {
+        grant_id: grant.grant_id.unwrap_or_default(),
+        key_id: grant.key_id.unwrap_or_default(),
+        name: grant.name.filter(|name| !name.is_empty()),
+        grantee_principal: grant.grantee_principal,
+        retiring_principal: grant.retiring_principal,
+        issuing_account: grant.issuing_account,
+        operations: grant.operations.unwrap_or_default(),
+        constraints: grant.constraints.map(grant_constraints),
+        creation_date: grant.creation_date.and_then(epoch_to_date_time),
+    }
+}
+
+fn grant_constraints(constraints: GrantConstraints) -> model::GrantConstraints {
+    model::GrantConstraints {
+        encryption_context_equals: constraints.encryption_context_equals,
+        encryption_context_subset: constraints.encryption_context_subset,
+    }
+}
+
+pub fn tags(tag_list: Vec<Tag>) -> model::ListResourceTagsOutput {
+    model::ListResourceTagsOutput {
+        tags: tag_list
+            .into_iter()
+            .map(|tag| model::Tag {
+                tag_key: tag.tag_key,
+                tag_value: tag.tag_value,
+            })
+            .collect(),
+    }
+}
+
+pub fn key_metadata(metatdata: KeyMetadata) -> model::KeyMetadata {
+    model::KeyMetadata {
+        key_id: metatdata.key_id,
+        arn: metatdata.arn.unwrap_or_default(),
+        aws_account_id: metatdata.aws_account_id,
+        description: metatdata.description,
+        enabled: metatdata.enabled.unwrap_or_default(),
+        key_state: metatdata.key_state,
+        key_usage: metatdata.key_usage,
+        customer_master_key_spec: metatdata.customer_master_key_spec,
+        key_manager: metatdata.key_manager,
+        origin: metatdata.origin,
+        creation_date: metatdata.creation_date.and_then(epoch_to_date_time),
+        deletion_date: metatdata.deletion_date.and_then(epoch_to_date_time),
+        pending_deletion_window_in_days: metatdata.pending_deletion_window_in_days,
+        valid_to: metatdata.valid_to.and_then(epoch_to_date_time),
+        expiration_model: metatdata.expiration_model,
+        custom_key_store_id: metatdata.custom_key_store_id,
+        cloud_hsm_cluster_id: metatdata.cloud_hsm_cluster_id,
+        multi_region: metatdata.multi_region.unwrap_or_default(),
+        multi_region_configuration: metatdata
+            .multi_region_configuration
+            .map(multi_region_configuration),
+        encryption_algorithms: metatdata.encryption_algorithms.unwrap_or_default(),
+        signing_algorithms: metatdata.signing_algorithms.unwrap_or_default(),
+    }
+}
+
+fn multi_region_configuration(
+    configuration: MultiRegionConfiguration,
+) -> model::MultiRegionConfiguration {
+    model::MultiRegionConfiguration {
+        multi_region_key_type: configuration.multi_region_key_type,
+        primary_key: configuration.primary_key.map(multi_region_key),
+        replica_keys: configuration
+            .replica_keys
+            .unwrap_or_default()
+            .into_iter()
+            .map(multi_region_key)
+            .collect(),
+    }
+}
+
+fn multi_region_key(key: MultiRegionKey) -> model::MultiRegionKey {
+    model::MultiRegionKey {
+        arn: key.arn.unwrap_or_default(),
+        region: key.region.unwrap_or_default(),
+    }
+}
+
+pub fn custom_key_store_entries(
+    entries: Vec<CustomKeyStoresListEntry>,
+) -> model::DescribeCustomKeyStoresOutput {
+    model::DescribeCustomKeyStoresOutput {
+        custom_key_stores: entries.into_iter().map(custom_key_store_entry).collect(),
+    }
+}
+
+pub fn custom_key_store_entry(entry: CustomKeyStoresListEntry) -> model::CustomKeyStore {
+    model::CustomKeyStore {
+        custom_key_store_id: entry.custom_key_store_id.unwrap_or_default(),
+        custom_key_store_name: entry.custom_key_store_name.unwrap_or_default(),
+        cloud_hsm_cluster_id: entry.cloud_hsm_cluster_id,
+        trust_anchor_certificate: entry.trust_anchor_certificate,
+        connection_state: entry.connection_state,
+        connection_error_code: entry.connection_error_code,
+        creation_date: entry.creation_date.and_then(epoch_to_date_time),
+    }
 }
 
 pub fn schedule_deletion_response(
     schedule_key_deletion_response: ScheduleKeyDeletionResponse,
-) -> Value {
-    json!({
-        "KeyId": schedule_key_deletion_response.key_id.unwrap_or_default(),
-        "DeletionDate": schedule_key_deletion_response.deletion_date.unwrap_or_default(),
-    })
+) -> model::ScheduleKeyDeletionOutput {
+    model::ScheduleKeyDeletionOutput {
+        key_id: schedule_key_deletion_response.key_id.unwrap_or_default(),
+        deletion_date: schedule_key_deletion_response
+            .deletion_date
+            .and_then(epoch_to_date_time),
+        key_state: schedule_key_deletion_response.key_state,
+        pending_window_in_days: schedule_key_deletion_response.pending_window_in_days,
+    }
 }
 
-pub fn cancel_deletion_response(response: CancelKeyDeletionResponse) -> Value {
-    json!({
-        "KeyId": response.key_id.unwrap_or_default(),
-    })
+pub fn cancel_deletion_response(
+    response: CancelKeyDeletionResponse,
+) -> model::CancelKeyDeletionOutput {
+    model::CancelKeyDeletionOutput {
+        key_id: response.key_id.unwrap_or_default(),
+    }
 }
 
-pub fn data_key_response(response: GenerateDataKeyResponse) -> Value {
-    let key_id: Option<String> = response.key_id;
-    let ciphertext_blob: Option<String> = bytes_to_base64(response.ciphertext_blob);
-    let plaintext: Option<String> = bytes_to_base64(response.plaintext);
-    parse_data_key_fields(key_id, ciphertext_blob, plaintext)
+pub fn data_key_response(response: GenerateDataKeyResponse) -> model::DataKey {
+    model::DataKey {
+        key_id: response.key_id.unwrap_or_default(),
+        ciphertext_blob: response.ciphertext_blob.unwrap_or_default(),
+        plaintext: response.plaintext,
+    }
 }
 
 pub fn data_key_without_plaintext_response(
     response: GenerateDataKeyWithoutPlaintextResponse,
-) -> Value {
-    let key_id: Option<String> = response.key_id;
-    let ciphertext_blob: Option<String> = bytes_to_base64(response.ciphertext_blob);
-    parse_data_key_fields(key_id, ciphertext_blob, None)
+) -> model::DataKey {
+    model::DataKey {
+        key_id: response.key_id.unwrap_or_default(),
+        ciphertext_blob: response.ciphertext_blob.unwrap_or_default(),
+        plaintext: None,
+    }
 }
 
-pub fn data_key_pair_response(response: GenerateDataKeyPairResponse) -> Value {
-    let key_id: Option<String> = response.key_id;
-    let key_pair_spec: Option<String> = response.key_pair_spec;
-    let private_key_ciphertext_blob: Option<String> =
-        bytes_to_base64(response.private_key_ciphertext_blob);
-    let private_key_plaintext: Option<String> = bytes_to_base64(response.private_key_plaintext);
-    let public_key: Option<String> = bytes_to_base64(response.public_key);
-    parse_data_key_pair_fields(
-        key_id,
-        key_pair_spec,
-        private_key_ciphertext_blob,
-        private_key_plaintext,
-        public_key,
-    )
+pub fn data_key_pair_response(response: GenerateDataKeyPairResponse) -> model::DataKeyPair {
+    model::DataKeyPair {
+        key_id: response.key_id.unwrap_or_default(),
+        key_pair_spec: response.key_pair_spec.unwrap_or_default(),
+        private_key_ciphertext_blob: response.private_key_ciphertext_blob.unwrap_or_default(),
+        private_key_plaintext: response.private_key_plaintext,
+        public_key: response.public_key.unwrap_or_default(),
+    }
 }
 
 pub fn data_key_pair_without_plaintext_response(
     response: GenerateDataKeyPairWithoutPlaintextResponse,
-) -> Value {
-    let key_id: Option<String> = response.key_id;
-    let key_pair_spec: Option<String> = response.key_pair_spec;
-    let private_key_ciphertext_blob: Option<String> =
-        bytes_to_base64(response.private_key_ciphertext_blob);
-    let public_key: Option<String> = bytes_to_base64(response.public_key);
-    parse_data_key_pair_fields(
-        key_id,
-        key_pair_spec,
-        private_key_ciphertext_blob,
-        public_key,
-        None,
-    )
-}
-
-pub fn encrypt_response(response: EncryptResponse) -> Value {
-    json!({
-        "KeyId": response.key_id.unwrap_or_default(),
-        "CiphertextBlob": bytes_to_base64(response.ciphertext_blob).unwrap_or_default(),
-        "EncryptionAlgorithm": response.encryption_algorithm.unwrap_or_default()
-    })
+) -> model::DataKeyPair {
+    model::DataKeyPair {
+        key_id: response.key_id.unwrap_or_default(),
+        key_pair_spec: response.key_pair_spec.unwrap_or_default(),
+        private_key_ciphertext_blob: response.private_key_ciphertext_blob.unwrap_or_default(),
+        private_key_plaintext: None,
+        public_key: response.public_key.unwrap_or_default(),
+    }
 }
 
-pub fn decrypt_response(response: DecryptResponse) -> Value {
-    json!({
-        "KeyId": response.key_id.unwrap_or_default(),
-        "Plaintext": bytes_to_base64(response.plaintext).unwrap_or_default(),
-        "EncryptionAlgorithm": response.encryption_algorithm.unwrap_or_default()
-    })
+pub fn encrypt_response(response: EncryptResponse) -> model::EncryptOutput {
+    model::EncryptOutput {
+        key_id: response.key_id.unwrap_or_default(),
+        ciphertext_blob: response.ciphertext_blob.unwrap_or_default(),
+        encryption_algorithm: response.encryption_algorithm.unwrap_or_default(),
+    }
 }
 
-pub fn sign_response(response: SignResponse) -> Value {
-    json!({
-        "KeyId": response.key_id.unwrap_or_default(),
-        "Signature": bytes_to_base64(response.signature).unwrap_or_default(),
-        "SigningAlgorithm": response.signing_algorithm.unwrap_or_default(),
-    })
+pub fn decrypt_response(response: DecryptResponse) -> model::DecryptOutput {
+    model::DecryptOutput {
+        key_id: response.key_id.unwrap_or_default(),
+        plaintext: response.plaintext.unwrap_or_default(),
+        encryption_algorithm: response.encryption_algorithm.unwrap_or_default(),
+    }
 }
 
-pub fn verify_response(response: VerifyResponse) -> Value {
-    json!({
-        "KeyId": response.key_id.unwrap_or_default(),
-        "SignatureValid": response.signature_valid.unwrap_or_default(),
-        "SigningAlgorithm": response.signing_algorithm.unwrap_or_default(),
-    })
+pub fn re_encrypt_response(response: ReEncryptResponse) -> model::ReEncryptOutput {
+    model::ReEncryptOutput {
+        source_key_id: response.source_key_id.unwrap_or_default(),
+        key_id: response.key_id.unwrap_or_default(),
+        ciphertext_blob: response.ciphertext_blob.unwrap_or_default(),
+        source_encryption_algorithm: response.source_encryption_algorithm.unwrap_or_default(),
+        destination_encryption_algorithm: response
+            .destination_encryption_algorithm
+            .unwrap_or_default(),
+    }
 }
 
-pub fn get_public_key_response(response: GetPublicKeyResponse) -> Value {
-    json!({
-        "KeyId": response.key_id.unwrap_or_default(),
-        "PublicKey": bytes_to_base64(response.public_key).unwrap_or_default(),
-        "CustomerMasterKeySpec": response.customer_master_key_spec.unwrap_or_default(),
-        "KeyUsage": response.key_usage.unwrap_or_default(),
-        "EncryptionAlgorithms": response.encryption_algorithms.unwrap_or_default(),
-        "SigningAlgorithms": response.signing_algorithms.unwrap_or_default(),
-    })
+pub fn sign_response(response: SignResponse) -> model::SignOutput {
+    model::SignOutput {
+        key_id: response.key_id.unwrap_or_default(),
+        signature: response.signature.unwrap_or_default(),
+        signing_algorithm: response.signing_algorithm.unwrap_or_default(),
+    }
 }
 
-pub fn generate_random_response(response: GenerateRandomResponse) -> Value {
-    json!({
-        "Plaintext": bytes_to_base64(response.plaintext).unwrap_or_default(),
-    })
+pub fn verify_response(response: VerifyResponse) -> model::VerifyOutput {
+    model::VerifyOutput {
+        key_id: response.key_id.unwrap_or_default(),
+        signature_valid: response.signature_valid.unwrap_or_default(),
+        signing_algorithm: response.signing_algorithm.unwrap_or_default(),
+    }
 }
 
-fn parse_data_key_fields(
-    key_id: Option<String>,
-    ciphertext_blob: Option<String>,
-    plaintext: Option<String>,
-) -> Value {
-    if plaintext.is_some() {
-        json!({
-            "CiphertextBlob": ciphertext_blob,
-            "Plaintext": plaintext,
-            "KeyId": key_id,
-        })
-    } else {
-        json!({
-            "CiphertextBlob": ciphertext_blob,
-            "KeyId": key_id,
-        })
+pub fn get_public_key_response(response: GetPublicKeyResponse) -> model::PublicKey {
+    model::PublicKey {
+        key_id: response.key_id.unwrap_or_default(),
+        public_key: response.public_key.unwrap_or_default(),
+        customer_master_key_spec: response.customer_master_key_spec.unwrap_or_default(),
+        key_usage: response.key_usage.unwrap_or_default(),
+        encryption_algorithms: response.encryption_algorithms.unwrap_or_default(),
+        signing_algorithms: response.signing_algorithms.unwrap_or_default(),
     }
 }
 
-fn parse_data_key_pair_fields(
-    key_id: Option<String>,
-    key_pair_spec: Option<String>,
-    private_key_ciphertext_blob: Option<String>,
-    private_key_plaintext: Option<String>,
-    public_key: Option<String>,
-) -> Value {
-    if private_key_plaintext.is_some() {
-        json!({
-            "KeyId": key_id,
-            "KeyPairSpec": key_pair_spec,
-            "PrivateKeyCiphertextBlob": private_key_ciphertext_blob,
-            "PrivateKeyPlaintext": private_key_plaintext,
-            "PublicKey": public_key,
-        })
-    } else {
-        json!({
-            "KeyId": key_id,
-            "KeyPairSpec": key_pair_spec,
-            "PrivateKeyCiphertextBlob": private_key_ciphertext_blob,
-            "PublicKey": public_key,
-        })
-    }
+pub fn generate_random_response(response: GenerateRandomResponse) -> Bytes {
+    response.plaintext.unwrap_or_default()
 }
 
-fn bytes_to_base64(bytes: Option<Bytes>) -> Option<String> {
-    if bytes.is_none() {
-        None
-    } else {
-        Some(base64::encode(bytes.unwrap_or_default()))
-    }
+/// AWS returns timestamps as (fractional) seconds since the epoch.
+/// KMS timestamps have millisecond precision; rounding drops the noise of the `f64` representation.
+fn epoch_to_date_time(seconds: f64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_millis_opt((seconds * 1000.0).round() as i64)
+        .single()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn test_parse_key_list_entries() {
@@ -234,7 +320,7 @@ mod tests {
         };
         mock_key_list.push(first_key);
         mock_key_list.push(second_key);
-        let actual_output = key_list_entries(mock_key_list);
+        let actual_output = json!(key_list_entries(mock_key_list));
         let expected_output = json!({ "Keys":
             [
                 {
@@ -272,45 +358,138 @@ mod tests {
             key_manager: Some("".to_string()),
             key_state: Some("Enabled".to_string()),
             key_usage: Some("ENCRYPT_DECRYPT".to_string()),
+            multi_region: Some(false),
+            multi_region_configuration: None,
             origin: Some("AWS_KMS".to_string()),
+            pending_deletion_window_in_days: None,
             signing_algorithms: None,
             valid_to: Some(12345678.90),
         };
-        let actual_output = key_metadata(mock_key_metadata);
+        let actual_output = json!(key_metadata(mock_key_metadata));
         let expected_output = json!({
             "KeyId": "abcd-4321-wxyz",
             "Arn": "arn:aws:kms:us-east-1:123456789:key/abcd-4321-wxyz",
+            "AWSAccountId": "1234567899",
             "Description": "Default master key that protects my EBS volumes when no other key is defined",
-            "Enabled": true
+            "Enabled": true,
+            "KeyState": "Enabled",
+            "KeyUsage": "ENCRYPT_DECRYPT",
+            "CustomerMasterKeySpec": "SYMMETRIC_DEFAULT",
+            "KeyManager": "",
+            "Origin": "AWS_KMS",
+            "CreationDate": "1970-01-15T06:56:07.890Z",
+            "DeletionDate": "1970-01-15T06:56:07.890Z",
+            "ValidTo": "1970-05-23T21:21:18.900Z",
+            "ExpirationModel": "",
+            "CustomKeyStoreId": "",
+            "CloudHsmClusterId": "",
+            "MultiRegion": false,
+            "EncryptionAlgorithms": ["SYMMETRIC_DEFAULT"],
+            "SigningAlgorithms": []
         });
         assert_eq!(actual_output, expected_output);
     }
 
+    #[test]
+    fn test_key_metadata_helpers() {
+        let mock_key_metadata = KeyMetadata {
+            key_id: "efgh-8765-stuv".to_string(),
+            key_manager: Some("CUSTOMER".to_string()),
+            key_state: Some("PendingDeletion".to_string()),
+            key_usage: Some("SIGN_VERIFY".to_string()),
+            customer_master_key_spec: Some("ECC_NIST_P256".to_string()),
+            deletion_date: Some(1606780800.0),
+            pending_deletion_window_in_days: Some(7),
+            ..Default::default()
+        };
+        let actual_output = key_metadata(mock_key_metadata);
+        assert!(actual_output.can_sign());
+        assert!(!actual_output.can_encrypt());
+        assert!(actual_output.is_asymmetric());
+        assert!(actual_output.is_pending_deletion());
+        assert!(!actual_output.is_enabled());
+        assert!(!actual_output.is_aws_managed());
+        assert_eq!(
+            actual_output.deletion_date,
+            Some(Utc.with_ymd_and_hms(2020, 12, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(actual_output.pending_deletion_window_in_days, Some(7));
+    }
+
     #[test]
     fn test_schedule_deletion_response() {
         let mock_key_deletion_response = ScheduleKeyDeletionResponse {
             key_id: Some("abcd-4321-wxyz".to_string()),
             deletion_date: Some(12345678.90),
+            key_state: Some("PendingDeletion".to_string()),
+            pending_window_in_days: Some(30),
+        };
+        let actual_output = json!(schedule_deletion_response(mock_key_deletion_response));
+        let expected_output = json!({
+            "KeyId": "abcd-4321-wxyz",
+            "DeletionDate": "1970-05-23T21:21:18.900Z",
+            "KeyState": "PendingDeletion",
+            "PendingWindowInDays": 30
+        });
+        assert_eq!(actual_output, expected_output);
+    }
+
+    #[test]
+    fn test_data_key_response() {
+        let mock_data_key_response = GenerateDataKeyResponse {
+            ciphertext_blob: Some(Bytes::from("abc-1234567890-$()*-_=+")),
+            key_id: Some("abcd-4321-wxyz".to_string()),
+            plaintext: Some(Bytes::from("abc-1234567890-$()*-_=+")),
         };
-        let actual_output = schedule_deletion_response(mock_key_deletion_response);
+        let actual_output = json!(data_key_response(mock_data_key_response));
         let expected_output = json!({
             "KeyId": "abcd-4321-wxyz",
-            "DeletionDate": 12345678.90
+            "CiphertextBlob": "YWJjLTEyMzQ1Njc4OTAtJCgpKi1fPSs=",
+            "Plaintext": "YWJjLTEyMzQ1Njc4OTAtJCgpKi1fPSs="
         });
         assert_eq!(actual_output, expected_output);
     }
 
     #[test]
-    fn test_bytes_to_base64() {
-        let bytes: Bytes = Bytes::from("abc-1234567890-$()*-_=+");
-        let actual: String = bytes_to_base64(Some(bytes)).unwrap();
-        let expected: String = "YWJjLTEyMzQ1Njc4OTAtJCgpKi1fPSs=".to_string();
-        assert_eq!(actual, expected);
+    fn test_data_key_without_plaintext_response() {
+        let mock_data_key_response = GenerateDataKeyWithoutPlaintextResponse {
+            ciphertext_blob: Some(Bytes::from("abc-1234567890-$()*-_=+")),
+            key_id: Some("abcd-4321-wxyz".to_string()),
+        };
+        let actual_output = json!(data_key_without_plaintext_response(mock_data_key_response));
+        let expected_output = json!({
+            "KeyId": "abcd-4321-wxyz",
+            "CiphertextBlob": "YWJjLTEyMzQ1Njc4OTAtJCgpKi1fPSs="
+        });
+        assert_eq!(actual_output, expected_output);
+    }
+
+    #[test]
+    fn test_data_key_pair_without_plaintext_response() {
+        let mock_data_key_pair_response = GenerateDataKeyPairWithoutPlaintextResponse {
+            key_id: Some("abcd-4321-wxyz".to_string()),
+            key_pair_spec: Some("ECC_NIST_P256".to_string()),
+            private_key_ciphertext_blob: Some(Bytes::from("private")),
+            public_key: Some(Bytes::from("public")),
+        };
+        let actual_output = data_key_pair_without_plaintext_response(mock_data_key_pair_response);
+        assert_eq!(
+            actual_output.private_key_ciphertext_blob,
+            Bytes::from("private")
+        );
+        assert_eq!(actual_output.private_key_plaintext, None);
+        assert_eq!(actual_output.public_key, Bytes::from("public"));
     }
 
     #[test]
-    fn test_bytes_to_base64_with_none() {
-        let actual: Option<String> = bytes_to_base64(None);
-        assert_eq!(None, actual);
+    fn test_data_key_round_trip() {
+        let data_key = model::DataKey {
+            key_id: "abcd-4321-wxyz".to_string(),
+            ciphertext_blob: Bytes::from("abc-1234567890-$()*-_=+"),
+            plaintext: None,
+        };
+        let serialized = serde_json::to_string(&data_key).unwrap();
+        let deserialized: model::DataKey = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(data_key, deserialized);
     }
 }
@@ -0,0 +1,703 @@
+//! Key policies: a typed model of the JSON document, a builder for it, and a linter for risky statements.
+//!
+//! ```
+//! use kms_rs::policy::{KeyPolicy, PolicyLinter, Statement};
+//!
+//! let policy = KeyPolicy::default_for_account("111122223333").statement(
+//!     Statement::allow()
+//!         .sid("Allow use of the key through S3")
+//!         .principal("arn:aws:iam::111122223333:role/uploader")
+//!         .action("kms:Encrypt")
+//!         .action("kms:GenerateDataKey*")
+//!         .via_service("s3.us-east-1.amazonaws.com")
+//!         .encryption_context("bucket", "reports"),
+//! );
+//! assert!(PolicyLinter::new().lint(&policy).is_empty());
+//! ```
+
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::KmsRsError;
+
+/// The only policy language version KMS supports.
+pub const VERSION: &str = "2012-10-17";
+
+/// A key policy document. Elements this model does not know about (e.g. `NotAction`) are kept as they are.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct KeyPolicy {
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "Statement", default)]
+    pub statements: Vec<Statement>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// One statement of a key policy.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Statement {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    pub effect: Effect,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal: Option<Principal>,
+    #[serde(
+        rename = "Action",
+        default,
+        with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub actions: Vec<String>,
+    #[serde(
+        rename = "Resource",
+        default,
+        with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub resources: Vec<String>,
+    /// Condition operator (e.g. `StringEquals`) to condition key (e.g. `kms:ViaService`) to values.
+    #[serde(
+        rename = "Condition",
+        default,
+        with = "conditions",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub conditions: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+/// The `Principal` element: everyone (`"*"`), or principals by type (`AWS`, `Service`, ...).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Principal {
+    Any,
+    Ids(BTreeMap<String, Vec<String>>),
+}
+
+/// A risky pattern found by [`PolicyLinter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// An `Allow` statement applies to every principal (`"*"`) and has no conditions.
+    PublicPrincipal,
+    /// `kms:*` (or `*`) is allowed to a principal that is neither the account root nor a declared admin.
+    FullAccessToNonAdmin,
+    /// The account root would no longer be allowed to call `kms:PutKeyPolicy`, so the key could become
+    /// unmanageable.
+    RootLockout,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub kind: LintKind,
+    /// The `Sid` of the offending statement, or its position (e.g. `#2`) when it has none.
+    pub statement: Option<String>,
+    pub message: String,
+}
+
+/// Checks key policies for risky patterns before they are put. Used by [`crate::KmsRs::put_key_policy`].
+///
+/// The account root (`arn:aws:iam::<account>:root`, or the bare account ID) is always an admin.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PolicyLinter {
+    admins: Vec<String>,
+    ignored: Vec<LintKind>,
+}
+
+impl KeyPolicy {
+    /// An empty policy.
+    pub fn new() -> KeyPolicy {
+        KeyPolicy {
+            version: VERSION.to_string(),
+            id: None,
+            statements: Vec::new(),
+            other: Map::new(),
+        }
+    }
+
+    /// The policy KMS attaches when a key is created without one: the account root may do anything, so
+    /// access can be delegated with IAM policies.
+    pub fn default_for_account(account_id: &str) -> KeyPolicy {
+        KeyPolicy::new().id("key-default-1").statement(
+            Statement::allow()
+                .sid("Enable IAM User Permissions")
+                .principal(format!("arn:aws:iam::{}:root", account_id))
+                .action("kms:*"),
+        )
+    }
+
+    pub fn id<S: Into<String>>(mut self, id: S) -> KeyPolicy {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Adds a statement. Can be called once per statement.
+    pub fn statement(mut self, statement: Statement) -> KeyPolicy {
+        self.statements.push(statement);
+        self
+    }
+
+    pub fn from_json(policy: &str) -> Result<KeyPolicy, KmsRsError> {
+        serde_json::from_str(policy)
+            .map_err(|err| KmsRsError::Parse(format!("Invalid key policy: {}", err)))
+    }
+
+    pub fn to_json(&self) -> String {
+        // Serializing maps and strings cannot fail.
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl Default for KeyPolicy {
+    fn default() -> KeyPolicy {
+        KeyPolicy::new()
+    }
+}
+
+impl Statement {
+    /// An `Allow` statement on `Resource: "*"`, the only resource a key policy can refer to.
+    pub fn allow() -> Statement {
+        Statement::new(Effect::Allow)
+    }
+
+    /// A `Deny` statement on `Resource: "*"`.
+    pub fn deny() -> Statement {
+        Statement::new(Effect::Deny)
+    }
+
+    fn new(effect: Effect) -> Statement {
+        Statement {
+            sid: None,
+            effect,
+            principal: None,
+            actions: Vec::new(),
+            resources: vec!["*".to_string()],
+            conditions: BTreeMap::new(),
+            other: Map::new(),
+        }
+    }
+
+    pub fn sid<S: Into<String>>(mut self, sid: S) -> Statement {
+        self.sid = Some(sid.into());
+        self
+    }
+
+    /// Adds an AWS principal: an account, IAM user or role ARN. Can be called once per principal.
+    pub fn principal<S: Into<String>>(self, arn: S) -> Statement {
+        self.principal_of_type("AWS", arn)
+    }
+
+    /// Adds an AWS service principal, e.g. `logs.us-east-1.amazonaws.com`.
+    pub fn service_principal<S: Into<String>>(self, service: S) -> Statement {
+        self.principal_of_type("Service", service)
+    }
+
+    /// Applies the statement to every principal (`"Principal": "*"`). Add conditions to narrow it down.
+    pub fn any_principal(mut self) -> Statement {
+        self.principal = Some(Principal::Any);
+        self
+    }
+
+    fn principal_of_type<S: Into<String>>(mut self, principal_type: &str, id: S) -> Statement {
+        let mut ids = match self.principal.take() {
+            Some(Principal::Ids(ids)) => ids,
+            _ => BTreeMap::new(),
+        };
+        ids.entry(principal_type.to_string())
+            .or_default()
+            .push(id.into());
+        self.principal = Some(Principal::Ids(ids));
+        self
+    }
+
+    /// Adds an action, e.g. `kms:Decrypt` or `kms:GenerateDataKey*`. Can be called once per action.
+    pub fn action<S: Into<String>>(mut self, action: S) -> Statement {
+        self.actions.push(action.into());
+        self
+    }
+
+    /// Adds a condition value, e.g. `condition("Bool", "kms:GrantIsForAWSResource", "true")`.
+    pub fn condition<O: Into<String>, K: Into<String>, V: Into<String>>(
+        mut self,
+        operator: O,
+        key: K,
+        value: V,
+    ) -> Statement {
+        self.conditions
+            .entry(operator.into())
+            .or_default()
+            .entry(key.into())
+            .or_default()
+            .push(value.into());
+        self
+    }
+
+    /// Requires the encryption context of the request to map `key` to `value`
+    /// (`StringEquals` on `kms:EncryptionContext:<key>`).
+    pub fn encryption_context<K: fmt::Display, V: Into<String>>(
+        self,
+        key: K,
+        value: V,
+    ) -> Statement {
+        self.condition(
+            "StringEquals",
+            format!("kms:EncryptionContext:{}", key),
+            value,
+        )
+    }
+
+    /// Requires the request to come from the given AWS service on behalf of the principal
+    /// (`StringEquals` on `kms:ViaService`), e.g. `s3.us-east-1.amazonaws.com`.
+    pub fn via_service<S: Into<String>>(self, service: S) -> Statement {
+        self.condition("StringEquals", "kms:ViaService", service)
+    }
+
+    /// The principal IDs of every type.
+    fn principal_ids(&self) -> Vec<&str> {
+        match &self.principal {
+            Some(Principal::Any) => vec!["*"],
+            Some(Principal::Ids(ids)) => ids.values().flatten().map(String::as_str).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn is_public(&self) -> bool {
+        match &self.principal {
+            Some(Principal::Any) => true,
+            Some(Principal::Ids(ids)) => ids
+                .get("AWS")
+                .is_some_and(|arns| arns.iter().any(|arn| arn == "*")),
+            None => false,
+        }
+    }
+
+    fn allows_action(&self, action: &str) -> bool {
+        self.actions
+            .iter()
+            .any(|pattern| wildcard_match(pattern, action))
+    }
+}
+
+impl PolicyLinter {
+    /// A linter for which only the account root is an admin.
+    pub fn new() -> PolicyLinter {
+        PolicyLinter::default()
+    }
+
+    /// Declares a principal ARN that may be granted `kms:*`. Can be called once per admin.
+    pub fn admin<S: Into<String>>(mut self, arn: S) -> PolicyLinter {
+        self.admins.push(arn.into());
+        self
+    }
+
+    /// Stops reporting findings of this kind.
+    pub fn ignore(mut self, kind: LintKind) -> PolicyLinter {
+        self.ignored.push(kind);
+        self
+    }
+
+    pub fn lint(&self, policy: &KeyPolicy) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+
+        for (index, statement) in policy.statements.iter().enumerate() {
+            if statement.effect != Effect::Allow {
+                continue;
+            }
+            let name = statement
+                .sid
+                .clone()
+                .unwrap_or_else(|| format!("#{}", index));
+
+            if statement.is_public() && statement.conditions.is_empty() {
+                findings.push(LintFinding {
+                    kind: LintKind::PublicPrincipal,
+                    statement: Some(name.clone()),
+                    message: format!(
+                        "Statement {} allows every principal (\"*\") without any condition",
+                        name
+                    ),
+                });
+            }
+            let full_access = statement
+                .actions
+                .iter()
+                .any(|action| action == "*" || action.eq_ignore_ascii_case("kms:*"));
+            if full_access {
+                for principal in statement.principal_ids() {
+                    if !is_account_root(principal) && !self.admins.iter().any(|a| a == principal) {
+                        findings.push(LintFinding {
+                            kind: LintKind::FullAccessToNonAdmin,
+                            statement: Some(name.clone()),
+                            message: format!(
+                                "Statement {} allows kms:* to {}, which is not an admin",
+                                name, principal
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+
+        if root_locked_out(policy) {
+            findings.push(LintFinding {
+                kind: LintKind::RootLockout,
+                statement: None,
+                message: "No statement lets the account root call kms:PutKeyPolicy unconditionally, so the key could become unmanageable".to_string(),
+            });
+        }
+
+        findings.retain(|finding| !self.ignored.contains(&finding.kind));
+        findings
+    }
+}
+
+/// Whether the account root would lose the ability to change the policy.
+fn root_locked_out(policy: &KeyPolicy) -> bool {
+    let applies_to_root =
+        |statement: &&Statement| statement.principal_ids().into_iter().any(is_account_root);
+    let allowed = policy
+        .statements
+        .iter()
+        .filter(|statement| statement.effect == Effect::Allow && statement.conditions.is_empty())
+        .filter(applies_to_root)
+        .any(|statement| statement.allows_action("kms:PutKeyPolicy"));
+    let denied = policy
+        .statements
+        .iter()
+        .filter(|statement| statement.effect == Effect::Deny && statement.conditions.is_empty())
+        .filter(|statement| statement.is_public() || applies_to_root(statement))
+        .any(|statement| statement.allows_action("kms:PutKeyPolicy"));
+    !allowed || denied
+}
+
+/// `arn:<partition>:iam::<account>:root` or a bare 12 digit account ID.
+fn is_account_root(principal: &str) -> bool {
+    let is_account_id = |id: &str| id.len() == 12 && id.bytes().all(|b| b.is_ascii_digit());
+    if is_account_id(principal) {
+        return true;
+    }
+    let parts: Vec<&str> = principal.split(':').collect();
+    matches!(
+        parts.as_slice(),
+        ["arn", _, "iam", "", account, "root"] if is_account_id(account)
+    )
+}
+
+/// IAM action matching: case insensitive, `*` matches any sequence and `?` any single character.
+fn wildcard_match(pattern: &str, action: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let action: Vec<char> = action.to_ascii_lowercase().chars().collect();
+    // Classic greedy matching with backtracking to the last `*`.
+    let (mut p, mut a) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while a < action.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == action[a]) {
+            p += 1;
+            a += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, a));
+            p += 1;
+        } else if let Some((star_p, star_a)) = star {
+            p = star_p + 1;
+            a = star_a + 1;
+            star = Some((star_p, star_a + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+impl Serialize for Principal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Principal::Any => serializer.serialize_str("*"),
+            Principal::Ids(ids) => {
+                let mut map = serializer.serialize_map(Some(ids.len()))?;
+                for (principal_type, values) in ids {
+                    map.serialize_entry(principal_type, &OneOrMany(values))?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Principal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Principal, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(any) if any == "*" => Ok(Principal::Any),
+            Value::Object(map) => map
+                .into_iter()
+                .map(|(principal_type, values)| {
+                    Ok((principal_type, one_or_many::from_value(values)?))
+                })
+                .collect::<Result<_, String>>()
+                .map(Principal::Ids)
+                .map_err(de::Error::custom),
+            other => Err(de::Error::custom(format!(
+                "Principal must be \"*\" or an object, got {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Serializes a list as a single string when it has exactly one element, like AWS does.
+struct OneOrMany<'a>(&'a [String]);
+
+impl Serialize for OneOrMany<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            [single] => serializer.serialize_str(single),
+            many => many.serialize(serializer),
+        }
+    }
+}
+
+/// A string or a list of strings.
+mod one_or_many {
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+    use serde_json::Value;
+
+    use super::OneOrMany;
+
+    pub fn serialize<S: Serializer>(values: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        OneOrMany(values).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        from_value(Value::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+
+    /// Booleans and numbers (e.g. `"Bool": {"aws:SecureTransport": true}`) are kept as strings.
+    pub fn from_value(value: Value) -> Result<Vec<String>, String> {
+        let scalar = |value: Value| match value {
+            Value::String(value) => Ok(value),
+            Value::Bool(value) => Ok(value.to_string()),
+            Value::Number(value) => Ok(value.to_string()),
+            other => Err(format!("Expected a string, got {}", other)),
+        };
+        match value {
+            Value::Array(values) => values.into_iter().map(scalar).collect(),
+            value => Ok(vec![scalar(value)?]),
+        }
+    }
+}
+
+/// The `Condition` element: operator to condition key to one or many values.
+mod conditions {
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::{SerializeMap, Serializer};
+    use serde_json::{Map, Value};
+    use std::collections::BTreeMap;
+
+    use super::{one_or_many, OneOrMany};
+
+    type Conditions = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+    pub fn serialize<S: Serializer>(
+        conditions: &Conditions,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(conditions.len()))?;
+        for (operator, keys) in conditions {
+            let keys: BTreeMap<&String, OneOrMany> = keys
+                .iter()
+                .map(|(key, values)| (key, OneOrMany(values)))
+                .collect();
+            map.serialize_entry(operator, &keys)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Conditions, D::Error> {
+        let operators = Map::<String, Value>::deserialize(deserializer)?;
+        let mut conditions = Conditions::new();
+        for (operator, keys) in operators {
+            let keys = match keys {
+                Value::Object(keys) => keys,
+                other => {
+                    return Err(de::Error::custom(format!(
+                        "Condition {} must be an object, got {}",
+                        operator, other
+                    )))
+                }
+            };
+            let mut values = BTreeMap::new();
+            for (key, value) in keys {
+                values.insert(
+                    key,
+                    one_or_many::from_value(value).map_err(de::Error::custom)?,
+                );
+            }
+            conditions.insert(operator, values);
+        }
+        Ok(conditions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const AWS_DEFAULT_POLICY: &str = r#"{
+        "Version": "2012-10-17",
+        "Id": "key-consolepolicy-3",
+        "Statement": [
+            {
+                "Sid": "Enable IAM User Permissions",
+                "Effect": "Allow",
+                "Principal": {"AWS": "arn:aws:iam::111122223333:root"},
+                "Action": "kms:*",
+                "Resource": "*"
+            },
+            {
+                "Sid": "Allow attachment of persistent resources",
+                "Effect": "Allow",
+                "Principal": {"AWS": ["arn:aws:iam::111122223333:role/a", "arn:aws:iam::111122223333:role/b"]},
+                "Action": ["kms:CreateGrant", "kms:ListGrants", "kms:RevokeGrant"],
+                "Resource": "*",
+                "Condition": {"Bool": {"kms:GrantIsForAWSResource": true}}
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_builder_serializes_like_aws() {
+        let policy = KeyPolicy::new().statement(
+            Statement::allow()
+                .principal("arn:aws:iam::111122223333:role/app")
+                .action("kms:Decrypt")
+                .via_service("s3.us-east-1.amazonaws.com")
+                .encryption_context("tenant", "acme"),
+        );
+        let expected = json!({
+            "Version": "2012-10-17",
+            "Statement": [{
+                "Effect": "Allow",
+                "Principal": {"AWS": "arn:aws:iam::111122223333:role/app"},
+                "Action": "kms:Decrypt",
+                "Resource": "*",
+                "Condition": {"StringEquals": {
+                    "kms:EncryptionContext:tenant": "acme",
+                    "kms:ViaService": "s3.us-east-1.amazonaws.com"
+                }}
+            }]
+        });
+        assert_eq!(
+            serde_json::from_str::<Value>(&policy.to_json()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let policy = KeyPolicy::from_json(AWS_DEFAULT_POLICY).unwrap();
+        assert_eq!(policy.statements.len(), 2);
+        assert_eq!(policy.statements[1].actions.len(), 3);
+        assert_eq!(
+            policy.statements[1].conditions["Bool"]["kms:GrantIsForAWSResource"],
+            vec!["true"]
+        );
+        assert_eq!(KeyPolicy::from_json(&policy.to_json()).unwrap(), policy);
+        assert!(PolicyLinter::new().lint(&policy).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_elements_are_kept() {
+        let policy = KeyPolicy::from_json(
+            r#"{"Version": "2012-10-17", "Statement": [
+                {"Effect": "Deny", "NotPrincipal": {"AWS": "111122223333"}, "NotAction": "kms:Decrypt", "Resource": "*"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy.statements[0].other["NotAction"],
+            json!("kms:Decrypt")
+        );
+        assert!(policy.to_json().contains("\"NotPrincipal\""));
+    }
+
+    #[test]
+    fn test_lint() {
+        let policy = KeyPolicy::new()
+            .statement(Statement::allow().any_principal().action("kms:Decrypt"))
+            .statement(
+                Statement::allow()
+                    .sid("Developers")
+                    .principal("arn:aws:iam::111122223333:role/developer")
+                    .principal("arn:aws:iam::111122223333:role/admin")
+                    .action("kms:*"),
+            );
+        let linter = PolicyLinter::new().admin("arn:aws:iam::111122223333:role/admin");
+        let kinds: Vec<(LintKind, Option<String>)> = linter
+            .lint(&policy)
+            .into_iter()
+            .map(|finding| (finding.kind, finding.statement))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (LintKind::PublicPrincipal, Some("#0".to_string())),
+                (
+                    LintKind::FullAccessToNonAdmin,
+                    Some("Developers".to_string())
+                ),
+                (LintKind::RootLockout, None),
+            ]
+        );
+        assert!(linter
+            .ignore(LintKind::RootLockout)
+            .lint(&policy)
+            .iter()
+            .all(|finding| finding.kind != LintKind::RootLockout));
+    }
+
+    #[test]
+    fn test_root_lockout() {
+        let root = "arn:aws:iam::111122223333:root";
+        let linted = |policy: KeyPolicy| {
+            PolicyLinter::new()
+                .lint(&policy)
+                .iter()
+                .any(|finding| finding.kind == LintKind::RootLockout)
+        };
+        assert!(!linted(KeyPolicy::new().statement(
+            Statement::allow().principal(root).action("kms:Put*")
+        )));
+        assert!(linted(KeyPolicy::new().statement(
+            Statement::allow().principal(root).action("kms:Decrypt")
+        )));
+        assert!(linted(
+            KeyPolicy::default_for_account("111122223333")
+                .statement(Statement::deny().any_principal().action("kms:PutKeyPolicy"))
+        ));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("kms:*", "kms:PutKeyPolicy"));
+        assert!(wildcard_match("KMS:putkey?olicy", "kms:PutKeyPolicy"));
+        assert!(wildcard_match("*", "kms:Decrypt"));
+        assert!(wildcard_match("kms:*Policy", "kms:PutKeyPolicy"));
+        assert!(!wildcard_match("kms:Get*", "kms:PutKeyPolicy"));
+    }
+}
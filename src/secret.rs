@@ -0,0 +1,123 @@
+//! Helpers for handling plaintext key material returned by AWS KMS.
+
+use bytes::Bytes;
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use std::collections::HashMap;
+use std::ptr::NonNull;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::crypto::PrivateKey;
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::model::DataKeyPair;
+
+/// Moves plaintext key material into a buffer that is zeroized on drop. When the `Bytes` are not shared,
+/// its own buffer is zeroized as well so no plaintext copy outlives the returned value.
+pub(crate) fn into_zeroizing(bytes: Bytes) -> Zeroizing<Vec<u8>> {
+    let copy = Zeroizing::new(bytes.to_vec());
+    if let Ok(mut unique) = bytes.try_into_mut() {
+        unique.as_mut().zeroize();
+    }
+    copy
+}
+
+/// Decrypts the private key of a data key pair through Decrypt, with the encryption context the pair was
+/// generated with, and checks it matches the pair's public key. Returns its PKCS #8 DER encoding.
+pub(crate) async fn decrypt_private_key(
+    kms: &KmsRs,
+    key_pair: &DataKeyPair,
+    encryption_context: HashMap<String, String>,
+) -> Result<Zeroizing<Vec<u8>>, KmsRsError> {
+    let output = kms
+        .decrypt(
+            Some(key_pair.key_id.clone()),
+            key_pair.private_key_ciphertext_blob.clone(),
+            Some(encryption_context).filter(|context| !context.is_empty()),
+            None,
+            None,
+        )
+        .await?;
+    let der = into_zeroizing(output.plaintext);
+    let private_key = PrivateKey::from_pkcs8_der(&key_pair.key_pair_spec, &der)?;
+    if private_key.public_key().to_der()? != key_pair.public_key {
+        return Err(KmsRsError::Crypto(
+            "The private key does not match the public key of the data key pair".to_string(),
+        ));
+    }
+    Ok(der)
+}
+
+/// Plaintext key material copied into a page-aligned, page-sized allocation of its own, so no other value
+/// shares its pages. On Unix those pages are locked with `mlock` (never written to swap); other platforms
+/// only get the zeroizing. The pages are zeroized on drop.
+pub(crate) struct LockedSecret {
+    pages: NonNull<u8>,
+    len: usize,
+    layout: Layout,
+}
+
+// `LockedSecret` owns its pages like a `Box<[u8]>` does.
+unsafe impl Send for LockedSecret {}
+unsafe impl Sync for LockedSecret {}
+
+impl LockedSecret {
+    /// Copies `bytes` into locked pages. Fails if the process may not lock that much memory (see
+    /// `RLIMIT_MEMLOCK`).
+    pub(crate) fn new(bytes: &[u8]) -> Result<LockedSecret, KmsRsError> {
+        let page_size = page_size();
+        let size = bytes.len().max(1).div_ceil(page_size) * page_size;
+        let layout = Layout::from_size_align(size, page_size)
+            .map_err(|err| KmsRsError::Crypto(err.to_string()))?;
+        let pages = match NonNull::new(unsafe { alloc_zeroed(layout) }) {
+            Some(pages) => pages,
+            None => handle_alloc_error(layout),
+        };
+
+        #[cfg(unix)]
+        {
+            if unsafe { libc::mlock(pages.as_ptr().cast(), size) } != 0 {
+                let err = std::io::Error::last_os_error();
+                unsafe { dealloc(pages.as_ptr(), layout) };
+                return Err(KmsRsError::Crypto(format!(
+                    "Could not lock key material in memory: {}",
+                    err
+                )));
+            }
+        }
+        // Only copied once the pages are locked, so the plaintext is never in them unlocked.
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), pages.as_ptr(), bytes.len()) };
+        Ok(LockedSecret {
+            pages,
+            len: bytes.len(),
+            layout,
+        })
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.pages.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for LockedSecret {
+    fn drop(&mut self) {
+        let pages = self.pages.as_ptr();
+        unsafe {
+            // Zeroize before unlocking, so the plaintext cannot be swapped out in between.
+            std::slice::from_raw_parts_mut(pages, self.layout.size()).zeroize();
+            #[cfg(unix)]
+            libc::munlock(pages.cast(), self.layout.size());
+            dealloc(pages, self.layout);
+        }
+    }
+}
+
+fn page_size() -> usize {
+    #[cfg(unix)]
+    {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page_size > 0 {
+            return page_size as usize;
+        }
+    }
+    4096
+}
@@ -0,0 +1,466 @@
+//! A local server speaking the AWS KMS wire protocol (JSON 1.1, `X-Amz-Target: TrentService.<Operation>`),
+//! backed by any [`KmsBackend`] (an [`InMemoryKms`](crate::InMemoryKms) by default).
+//!
+//! Point a [`KmsRs`](crate::KmsRs) (or any AWS SDK or CLI) at it with a custom endpoint to run
+//! integration tests without AWS. Errors use the same `__type`/`message` shape and HTTP status as KMS.
+//! Request signatures are not checked, so any credentials are accepted.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), kms_rs::KmsRsError> {
+//! use kms_rs::server::KmsServer;
+//! use kms_rs::InMemoryKms;
+//!
+//! KmsServer::new(InMemoryKms::new())
+//!     .serve(([127, 0, 0, 1], 4599).into())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `kms-rs-server` binary runs the same server from the command line.
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use rusoto_core::Region;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::backend::KmsBackend;
+use crate::error::KmsRsError;
+
+/// Prefix of the `X-Amz-Target` header of every KMS operation.
+const TARGET_PREFIX: &str = "TrentService.";
+
+/// An HTTP server exposing a [`KmsBackend`] as AWS KMS.
+#[derive(Clone)]
+pub struct KmsServer {
+    backend: Arc<dyn KmsBackend>,
+}
+
+/// An error response: HTTP status, exception type and message.
+struct Exception {
+    status: StatusCode,
+    error_type: String,
+    message: String,
+}
+
+impl Exception {
+    fn new<T: Into<String>, M: Into<String>>(
+        status: StatusCode,
+        error_type: T,
+        message: M,
+    ) -> Exception {
+        Exception {
+            status,
+            error_type: error_type.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<KmsRsError> for Exception {
+    fn from(error: KmsRsError) -> Exception {
+        let (status, error_type, message) = match error {
+            KmsRsError::NotFound(message) => (400, "NotFoundException", message),
+            KmsRsError::Disabled(message) => (400, "DisabledException", message),
+            KmsRsError::KmsInvalidState(message) => (400, "KMSInvalidStateException", message),
+            KmsRsError::InvalidCiphertext(message) => (400, "InvalidCiphertextException", message),
+            KmsRsError::IncorrectKey(message) => (400, "IncorrectKeyException", message),
+            KmsRsError::InvalidKeyUsage(message) => (400, "InvalidKeyUsageException", message),
+            KmsRsError::KeyUnavailable(message) => (500, "KeyUnavailableException", message),
+            KmsRsError::InvalidGrantToken(message) => (400, "InvalidGrantTokenException", message),
+            KmsRsError::InvalidGrantId(message) => (400, "InvalidGrantIdException", message),
+            KmsRsError::InvalidArn(message) => (400, "InvalidArnException", message),
+            KmsRsError::InvalidMarker(message) => (400, "InvalidMarkerException", message),
+            KmsRsError::InvalidAliasName(message) => (400, "InvalidAliasNameException", message),
+            KmsRsError::InvalidImportToken(message) => {
+                (400, "InvalidImportTokenException", message)
+            }
+            KmsRsError::ExpiredImportToken(message) => {
+                (400, "ExpiredImportTokenException", message)
+            }
+            KmsRsError::IncorrectKeyMaterial(message) => {
+                (400, "IncorrectKeyMaterialException", message)
+            }
+            KmsRsError::KmsInvalidSignature(message) => {
+                (400, "KMSInvalidSignatureException", message)
+            }
+            KmsRsError::MalformedPolicyDocument(message) | KmsRsError::UnsafePolicy(message) => {
+                (400, "MalformedPolicyDocumentException", message)
+            }
+            KmsRsError::Tag(message) => (400, "TagException", message),
+            KmsRsError::AlreadyExists(message) => (400, "AlreadyExistsException", message),
+            KmsRsError::LimitExceeded(message) => (400, "LimitExceededException", message),
+            KmsRsError::UnsupportedOperation(message) => {
+                (400, "UnsupportedOperationException", message)
+            }
+            KmsRsError::DependencyTimeout(message) => (503, "DependencyTimeoutException", message),
+            KmsRsError::KmsInternal(message) => (500, "KMSInternalException", message),
+            KmsRsError::CloudHsmClusterInUse(message) => {
+                (400, "CloudHsmClusterInUseException", message)
+            }
+            KmsRsError::CloudHsmClusterInvalidConfiguration(message) => {
+                (400, "CloudHsmClusterInvalidConfigurationException", message)
+            }
+            KmsRsError::CloudHsmClusterNotActive(message) => {
+                (400, "CloudHsmClusterNotActiveException", message)
+            }
+            KmsRsError::CloudHsmClusterNotFound(message) => {
+                (400, "CloudHsmClusterNotFoundException", message)
+            }
+            KmsRsError::CloudHsmClusterNotRelated(message) => {
+                (400, "CloudHsmClusterNotRelatedException", message)
+            }
+            KmsRsError::CustomKeyStoreHasCMKs(message) => {
+                (400, "CustomKeyStoreHasCMKsException", message)
+            }
+            KmsRsError::CustomKeyStoreInvalidState(message) => {
+                (400, "CustomKeyStoreInvalidStateException", message)
+            }
+            KmsRsError::CustomKeyStoreNameInUse(message) => {
+                (400, "CustomKeyStoreNameInUseException", message)
+            }
+            KmsRsError::CustomKeyStoreNotFound(message) => {
+                (400, "CustomKeyStoreNotFoundException", message)
+            }
+            KmsRsError::IncorrectTrustAnchor(message) => {
+                (400, "IncorrectTrustAnchorException", message)
+            }
+            KmsRsError::Throttling(message) => (400, "ThrottlingException", message),
+            KmsRsError::AccessDenied(message) => (400, "AccessDeniedException", message),
+            KmsRsError::Credentials(message) => (400, "UnrecognizedClientException", message),
+            KmsRsError::Validation(message) => (400, "ValidationException", message),
+            KmsRsError::Unknown {
+                status,
+                error_type: Some(error_type),
+                message,
+            } => {
+                let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_REQUEST);
+                return Exception::new(status, error_type, message);
+            }
+            KmsRsError::Unknown { message, .. }
+            | KmsRsError::HttpDispatch(message)
+            | KmsRsError::Parse(message)
+            | KmsRsError::Crypto(message)
+            | KmsRsError::Io(message) => (500, "KMSInternalException", message),
+        };
+        Exception::new(
+            StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            error_type,
+            message,
+        )
+    }
+}
+
+/// Deserializes the request of each operation, calls the backend and serializes its response.
+macro_rules! dispatch {
+    ($backend:expr, $operation:expr, $body:expr, { $($name:literal => $method:ident,)* }) => {
+        match $operation {
+            $(
+                $name => {
+                    let request = serde_json::from_slice($body).map_err(|err| {
+                        Exception::new(StatusCode::BAD_REQUEST, "SerializationException", err.to_string())
+                    })?;
+                    to_json(&$backend.$method(request).await?)
+                }
+            )*
+            operation => Err(Exception::new(
+                StatusCode::BAD_REQUEST,
+                "UnknownOperationException",
+                format!("Unknown operation {}", operation),
+            )),
+        }
+    };
+}
+
+fn to_json<T: Serialize>(response: &T) -> Result<Vec<u8>, Exception> {
+    match serde_json::to_value(response) {
+        // Operations without output (e.g. EnableKey) answer with an empty JSON object.
+        Ok(serde_json::Value::Null) => Ok(b"{}".to_vec()),
+        Ok(value) => Ok(value.to_string().into_bytes()),
+        Err(err) => Err(Exception::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "KMSInternalException",
+            err.to_string(),
+        )),
+    }
+}
+
+impl KmsServer {
+    /// Creates a server for `backend`.
+    pub fn new<B: KmsBackend + 'static>(backend: B) -> KmsServer {
+        KmsServer {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Binds `addr` and serves requests until the returned future is dropped or fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), KmsRsError> {
+        let (_, server) = self.bind(addr)?;
+        server.await
+    }
+
+    /// Binds `addr` (e.g. port 0 for any free port) and returns the bound address along with the future
+    /// serving requests.
+    pub fn bind(
+        self,
+        addr: SocketAddr,
+    ) -> Result<(SocketAddr, impl Future<Output = Result<(), KmsRsError>>), KmsRsError> {
+        let builder =
+            Server::try_bind(&addr).map_err(|err| KmsRsError::HttpDispatch(err.to_string()))?;
+        let make_service = make_service_fn(move |_| {
+            let server = self.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.handle(request).await) }
+                }))
+            }
+        });
+        let server = builder.serve(make_service);
+        let local_addr = server.local_addr();
+        let server = async move {
+            server
+                .await
+                .map_err(|err| KmsRsError::HttpDispatch(err.to_string()))
+        };
+        Ok((local_addr, server))
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let backend = self.backend_for(&request);
+        let result = match self.read(request).await {
+            Ok((operation, body)) => Self::call(&backend, &operation, &body).await,
+            Err(exception) => Err(exception),
+        };
+        let (status, body) = match result {
+            Ok(body) => (StatusCode::OK, body),
+            Err(exception) => (
+                exception.status,
+                serde_json::json!({
+                    "__type": exception.error_type,
+                    "message": exception.message,
+                })
+                .to_string()
+                .into_bytes(),
+            ),
+        };
+        Response::builder()
+            .status(status)
+            .header("Content-Type", "application/x-amz-json-1.1")
+            .body(Body::from(body))
+            .unwrap_or_default()
+    }
+
+    /// Reads the operation name from `X-Amz-Target` and the request body.
+    async fn read(&self, request: Request<Body>) -> Result<(String, Vec<u8>), Exception> {
+        if request.method() != Method::POST {
+            return Err(Exception::new(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "UnknownOperationException",
+                "Only POST requests are supported",
+            ));
+        }
+        let operation = request
+            .headers()
+            .get("X-Amz-Target")
+            .and_then(|target| target.to_str().ok())
+            .and_then(|target| target.strip_prefix(TARGET_PREFIX))
+            .map(str::to_string)
+            .ok_or_else(|| {
+                Exception::new(
+                    StatusCode::BAD_REQUEST,
+                    "UnknownOperationException",
+                    "Missing or invalid X-Amz-Target header",
+                )
+            })?;
+        let body = hyper::body::to_bytes(request.into_body())
+            .await
+            .map_err(|err| {
+                Exception::new(
+                    StatusCode::BAD_REQUEST,
+                    "SerializationException",
+                    err.to_string(),
+                )
+            })?;
+        Ok((operation, body.to_vec()))
+    }
+
+    /// The backend for the Region the request was signed for (from the credential scope of its
+    /// `Authorization` header), so clients of several Regions can share one server. Falls back to the
+    /// server's backend for unsigned requests and backends bound to a single Region.
+    fn backend_for(&self, request: &Request<Body>) -> Arc<dyn KmsBackend> {
+        let region = request
+            .headers()
+            .get("Authorization")
+            .and_then(|authorization| authorization.to_str().ok())
+            .and_then(|authorization| authorization.split("Credential=").nth(1))
+            .and_then(|credential| credential.split('/').nth(2));
+        match region {
+            Some(region) => {
+                let region = Region::Custom {
+                    name: region.to_string(),
+                    endpoint: String::new(),
+                };
+                self.backend
+                    .for_region(&region)
+                    .unwrap_or_else(|_| self.backend.clone())
+            }
+            None => self.backend.clone(),
+        }
+    }
+
+    async fn call(
+        backend: &Arc<dyn KmsBackend>,
+        operation: &str,
+        body: &[u8],
+    ) -> Result<Vec<u8>, Exception> {
+        dispatch!(backend, operation, body, {
+            "CancelKeyDeletion" => cancel_key_deletion,
+            "ConnectCustomKeyStore" => connect_custom_key_store,
+            "CreateAlias" => create_alias,
+            "CreateCustomKeyStore" => create_custom_key_store,
+            "CreateGrant" => create_grant,
+            "CreateKey" => create_key,
+            "Decrypt" => decrypt,
+            "DeleteAlias" => delete_alias,
+            "DeleteCustomKeyStore" => delete_custom_key_store,
+            "DeleteImportedKeyMaterial" => delete_imported_key_material,
+            "DescribeCustomKeyStores" => describe_custom_key_stores,
+            "DescribeKey" => describe_key,
+            "DisableKey" => disable_key,
+            "DisableKeyRotation" => disable_key_rotation,
+            "DisconnectCustomKeyStore" => disconnect_custom_key_store,
+            "EnableKey" => enable_key,
+            "EnableKeyRotation" => enable_key_rotation,
+            "Encrypt" => encrypt,
+            "GenerateDataKey" => generate_data_key,
+            "GenerateDataKeyPair" => generate_data_key_pair,
+            "GenerateDataKeyPairWithoutPlaintext" => generate_data_key_pair_without_plaintext,
+            "GenerateDataKeyWithoutPlaintext" => generate_data_key_without_plaintext,
+            "GenerateRandom" => generate_random,
+            "GetKeyPolicy" => get_key_policy,
+            "GetKeyRotationStatus" => get_key_rotation_status,
+            "GetParametersForImport" => get_parameters_for_import,
+            "GetPublicKey" => get_public_key,
+            "ImportKeyMaterial" => import_key_material,
+            "ListAliases" => list_aliases,
+            "ListGrants" => list_grants,
+            "ListKeyPolicies" => list_key_policies,
+            "ListKeys" => list_keys,
+            "ListResourceTags" => list_resource_tags,
+            "ListRetirableGrants" => list_retirable_grants,
+            "PutKeyPolicy" => put_key_policy,
+            "ReEncrypt" => re_encrypt,
+            "ReplicateKey" => replicate_key,
+            "RetireGrant" => retire_grant,
+            "RevokeGrant" => revoke_grant,
+            "ScheduleKeyDeletion" => schedule_key_deletion,
+            "Sign" => sign,
+            "TagResource" => tag_resource,
+            "UntagResource" => untag_resource,
+            "UpdateAlias" => update_alias,
+            "UpdateCustomKeyStore" => update_custom_key_store,
+            "UpdatePrimaryRegion" => update_primary_region,
+            "Verify" => verify,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CreateKeyOptions, InMemoryKms, KmsRs};
+    use bytes::Bytes;
+    use rusoto_core::credential::StaticProvider;
+
+    fn start_server() -> KmsRs {
+        let (addr, server) = KmsServer::new(InMemoryKms::new())
+            .bind(([127, 0, 0, 1], 0).into())
+            .unwrap();
+        tokio::spawn(server);
+        KmsRs::builder()
+            .endpoint(format!("http://{}", addr))
+            .credentials(StaticProvider::new_minimal(
+                "access_key".to_string(),
+                "secret_key".to_string(),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_end_to_end() {
+        let kms = start_server();
+        let key = kms.create_key(CreateKeyOptions::new()).await.unwrap();
+        assert!(key.is_enabled());
+        assert_eq!(kms.list_keys().await.unwrap().keys.len(), 1);
+
+        let encrypted = kms
+            .encrypt(
+                key.key_id.clone(),
+                Bytes::from_static(b"secret"),
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let decrypted = kms
+            .decrypt(None, encrypted.ciphertext_blob, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(decrypted.plaintext, Bytes::from_static(b"secret"));
+
+        kms.disable_key(&key.key_id).await.unwrap();
+        assert!(!kms.describe_key(&key.key_id).await.unwrap().enabled);
+        assert_eq!(kms.generate_random(32, None).await.unwrap().len(), 32);
+    }
+
+    #[tokio::test]
+    async fn test_routes_requests_by_signing_region() {
+        let kms = start_server().for_region(Region::UsWest2).unwrap();
+        let primary = kms
+            .create_key(CreateKeyOptions::new().multi_region(true))
+            .await
+            .unwrap();
+        kms.replicate_key(&primary.key_id, &Region::EuCentral1, None, Vec::new())
+            .await
+            .unwrap();
+
+        let eu_central_1 = kms.for_region(Region::EuCentral1).unwrap();
+        assert_eq!(eu_central_1.list_keys().await.unwrap().keys.len(), 1);
+        let replicas = kms.list_replicas(&primary.key_id).await.unwrap();
+        assert_eq!(replicas.len(), 1);
+        assert!(replicas[0].arn.contains(":eu-central-1:"));
+    }
+
+    #[tokio::test]
+    async fn test_returns_kms_error_shapes() {
+        let kms = start_server();
+        match kms
+            .describe_key("1234abcd-12ab-34cd-56ef-1234567890ab")
+            .await
+        {
+            Err(KmsRsError::NotFound(message)) => assert!(message.contains("does not exist")),
+            other => panic!("Expected a NotFound error, got {:?}", other),
+        }
+
+        let key_id = kms
+            .create_key(CreateKeyOptions::new())
+            .await
+            .unwrap()
+            .key_id;
+        kms.disable_key(&key_id).await.unwrap();
+        match kms
+            .generate_data_key(&key_id, Some("AES_256".to_string()), None, None, None)
+            .await
+        {
+            Err(KmsRsError::Disabled(_)) => (),
+            other => panic!("Expected a Disabled error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Signing with data key pairs, without calling KMS for every signature.
+//!
+//! A [`DataKeyPairSigner`] decrypts the private key of a pair from GenerateDataKeyPairWithoutPlaintext once
+//! through Decrypt, then signs locally, so the private key is only protected at rest by the CMK.
+
+use bytes::Bytes;
+use std::collections::HashMap;
+
+use crate::crypto::{self, PrivateKey};
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::model::DataKeyPair;
+use crate::secret::{decrypt_private_key, LockedSecret};
+
+/// Signs messages with the private key of a data key pair, held in locked memory (see
+/// [`DataKeyPairSigner::new`]) that is zeroized when the signer is dropped.
+///
+/// Signatures use the same encodings as KMS Sign (DER-encoded ECDSA, RSASSA-PSS with a salt as long as the
+/// digest), and verify against the pair's `PublicKey`.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), kms_rs::KmsRsError> {
+/// use kms_rs::{DataKeyPairSigner, KmsRs};
+/// use std::collections::HashMap;
+///
+/// let kms = KmsRs::default();
+/// let key_pair = kms
+///     .generate_data_key_pair_without_plaintext("alias/releases", "ECC_NIST_P256".to_string(), None, None)
+///     .await?;
+///
+/// let signer = DataKeyPairSigner::new(&kms, &key_pair, HashMap::new()).await?;
+/// let signature = signer.sign(b"release v1.0")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DataKeyPairSigner {
+    key_pair_spec: String,
+    signing_algorithm: String,
+    public_key: Bytes,
+    /// The PKCS #8 DER private key, parsed again for each signature.
+    private_key: LockedSecret,
+}
+
+impl DataKeyPairSigner {
+    /// Decrypts the `PrivateKeyCiphertextBlob` of `key_pair` with the CMK that generated it, given the
+    /// encryption context the pair was generated with (if any).
+    ///
+    /// Between signatures the private key is only held as its PKCS #8 DER encoding, in pages of its own that
+    /// are locked with `mlock` on Unix (this fails if the process exceeds its `RLIMIT_MEMLOCK`). The Decrypt
+    /// response reaches those pages through ordinary memory, and the copies this crate owns are zeroized.
+    ///
+    /// Each signature parses the key into a temporary value that is zeroized when the signature is done, but
+    /// is not itself in locked memory: on the stack for ECC keys, and on the heap for the big integers of RSA
+    /// keys.
+    ///
+    /// Signatures use the first algorithm KMS lists for the key pair spec: `RSASSA_PSS_SHA_256` for RSA pairs
+    /// and the ECDSA algorithm of the curve for ECC pairs. See [`DataKeyPairSigner::with_signing_algorithm`].
+    pub async fn new(
+        kms: &KmsRs,
+        key_pair: &DataKeyPair,
+        encryption_context: HashMap<String, String>,
+    ) -> Result<DataKeyPairSigner, KmsRsError> {
+        let signing_algorithm = crypto::signing_algorithms(&key_pair.key_pair_spec)
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                KmsRsError::Validation(format!(
+                    "Unsupported key pair spec {}",
+                    key_pair.key_pair_spec
+                ))
+            })?;
+        let der = decrypt_private_key(kms, key_pair, encryption_context).await?;
+
+        Ok(DataKeyPairSigner {
+            key_pair_spec: key_pair.key_pair_spec.clone(),
+            signing_algorithm,
+            public_key: key_pair.public_key.clone(),
+            private_key: LockedSecret::new(&der)?,
+        })
+    }
+
+    /// Signs with another algorithm the key pair spec supports, such as `RSASSA_PKCS1_V1_5_SHA_512` for an
+    /// RSA pair.
+    pub fn with_signing_algorithm(
+        mut self,
+        signing_algorithm: &str,
+    ) -> Result<DataKeyPairSigner, KmsRsError> {
+        if !crypto::signing_algorithms(&self.key_pair_spec)
+            .iter()
+            .any(|algorithm| algorithm == signing_algorithm)
+        {
+            return Err(KmsRsError::Validation(format!(
+                "Algorithm {} is incompatible with key pair spec {}.",
+                signing_algorithm, self.key_pair_spec
+            )));
+        }
+        self.signing_algorithm = signing_algorithm.to_string();
+        Ok(self)
+    }
+
+    /// The algorithm signatures are made with.
+    pub fn signing_algorithm(&self) -> &str {
+        &self.signing_algorithm
+    }
+
+    /// The DER-encoded `SubjectPublicKeyInfo` signatures verify against.
+    pub fn public_key(&self) -> &Bytes {
+        &self.public_key
+    }
+
+    /// Signs `message`, of any size, hashing it with the digest of the signing algorithm.
+    pub fn sign(&self, message: &[u8]) -> Result<Bytes, KmsRsError> {
+        self.sign_digest(&crypto::digest(&self.signing_algorithm, message)?)
+    }
+
+    /// Signs a digest already computed with the hash of the signing algorithm (SHA-256, SHA-384 or SHA-512).
+    pub fn sign_digest(&self, digest: &[u8]) -> Result<Bytes, KmsRsError> {
+        PrivateKey::from_pkcs8_der(&self.key_pair_spec, self.private_key.as_slice())?
+            .sign_digest(&self.signing_algorithm, digest)
+            .map(Bytes::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::data_key_pair;
+    use crate::InMemoryKms;
+
+    #[tokio::test]
+    async fn test_signatures_verify_with_public_key() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        for key_pair_spec in &[
+            "RSA_2048",
+            "ECC_NIST_P256",
+            "ECC_NIST_P384",
+            "ECC_NIST_P521",
+            "ECC_SECG_P256K1",
+        ] {
+            let key_pair = data_key_pair(&kms, key_pair_spec, None).await;
+            let public_key = crypto::PublicKey::from_der(&key_pair.public_key).unwrap();
+            for algorithm in crypto::signing_algorithms(key_pair_spec) {
+                let signer = DataKeyPairSigner::new(&kms, &key_pair, HashMap::new())
+                    .await
+                    .unwrap()
+                    .with_signing_algorithm(&algorithm)
+                    .unwrap();
+                let signature = signer.sign(b"release v1.0").unwrap();
+                let digest = crypto::digest(&algorithm, b"release v1.0").unwrap();
+                assert!(public_key
+                    .verify_digest(&algorithm, &digest, &signature)
+                    .unwrap());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_algorithm_of_other_spec() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_pair = data_key_pair(&kms, "ECC_NIST_P256", None).await;
+        let signer = DataKeyPairSigner::new(&kms, &key_pair, HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(signer.signing_algorithm(), "ECDSA_SHA_256");
+        match signer.with_signing_algorithm("ECDSA_SHA_384") {
+            Err(KmsRsError::Validation(_)) => (),
+            Err(other) => panic!("Expected a Validation error, got {:?}", other),
+            Ok(_) => panic!("Expected a Validation error"),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//! Locking helpers shared by the in-memory state of this crate.
+
+use std::sync::{Mutex, MutexGuard};
+
+/// Locks `mutex`, recovering it if a thread panicked while holding it. Every critical section in this crate
+/// leaves its data consistent before anything that can panic, so a poisoned lock is still usable.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
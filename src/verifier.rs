@@ -0,0 +1,301 @@
+//! Signature verification without calling KMS.
+//!
+//! A [`LocalVerifier`] fetches the public key of a CMK once through GetPublicKey, then checks signatures
+//! locally with the same algorithms, limits and errors as the Verify operation, so high-volume verification
+//! costs nothing beyond the first GetPublicKey call.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::crypto;
+use crate::error::KmsRsError;
+use crate::kms::KmsRs;
+use crate::model::VerifyOutput;
+use crate::sync::lock;
+
+/// The largest message Verify accepts, in bytes.
+const MAX_MESSAGE_BYTES: usize = 4096;
+
+/// Verifies signatures made by KMS Sign with the cached public keys of asymmetric `SIGN_VERIFY` CMKs.
+///
+/// [`LocalVerifier::verify`] returns the same output and errors as [`KmsRs::verify`]: an invalid signature
+/// is a [`KmsRsError::KmsInvalidSignature`] error, and an algorithm the CMK does not support an
+/// [`KmsRsError::InvalidKeyUsage`] error. Unlike KMS, it keeps accepting signatures after the CMK is
+/// disabled or its key policy changes, until [`LocalVerifier::forget`] is called.
+///
+/// ```
+/// # async fn run() -> Result<(), kms_rs::KmsRsError> {
+/// use bytes::Bytes;
+/// use kms_rs::{CreateKeyOptions, InMemoryKms, KmsRs, LocalVerifier};
+///
+/// let kms = KmsRs::from_backend(InMemoryKms::new());
+/// let key = kms
+///     .create_key(
+///         CreateKeyOptions::new()
+///             .key_usage("SIGN_VERIFY")
+///             .customer_master_key_spec("ECC_NIST_P256"),
+///     )
+///     .await?;
+/// let signature = kms
+///     .sign(
+///         key.key_id.clone(),
+///         Bytes::from_static(b"release v1.0"),
+///         None,
+///         "ECDSA_SHA_256".to_string(),
+///         None,
+///     )
+///     .await?
+///     .signature;
+///
+/// let verifier = LocalVerifier::new(&kms);
+/// verifier
+///     .verify(&key.key_id, b"release v1.0", None, &signature, "ECDSA_SHA_256")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LocalVerifier {
+    kms: KmsRs,
+    /// The public keys fetched so far, by the key ID they were requested with.
+    keys: Arc<Mutex<HashMap<String, Arc<VerificationKey>>>>,
+}
+
+/// The parts of a GetPublicKey response needed to verify signatures.
+struct VerificationKey {
+    key_arn: String,
+    key_spec: String,
+    key_usage: String,
+    signing_algorithms: Vec<String>,
+    public_key: crypto::PublicKey,
+}
+
+impl LocalVerifier {
+    /// Creates a verifier that fetches public keys through `kms`.
+    pub fn new(kms: &KmsRs) -> LocalVerifier {
+        LocalVerifier {
+            kms: kms.clone(),
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Verifies a signature of `message` (the message itself, or its digest when `message_type` is `DIGEST`)
+    /// made by the CMK `key_id` with `signing_algorithm`, calling GetPublicKey the first time the CMK is used.
+    /// Like KMS, an invalid signature is a [`KmsRsError::KmsInvalidSignature`] error rather than an output
+    /// with `signature_valid: false`.
+    pub async fn verify(
+        &self,
+        key_id: &str,
+        message: &[u8],
+        message_type: Option<&str>,
+        signature: &[u8],
+        signing_algorithm: &str,
+    ) -> Result<VerifyOutput, KmsRsError> {
+        let key = self.key(key_id).await?;
+        if key.key_usage != "SIGN_VERIFY" {
+            return Err(KmsRsError::InvalidKeyUsage(format!(
+                "{} key usage is {} which is not valid for Verify.",
+                key.key_arn, key.key_usage
+            )));
+        }
+        if !key
+            .signing_algorithms
+            .iter()
+            .any(|algorithm| algorithm == signing_algorithm)
+        {
+            return Err(KmsRsError::InvalidKeyUsage(format!(
+                "Algorithm {} is incompatible with key spec {}.",
+                signing_algorithm, key.key_spec
+            )));
+        }
+        if message.len() > MAX_MESSAGE_BYTES {
+            return Err(KmsRsError::Validation(format!(
+                "Message must be at most {} bytes, got {}",
+                MAX_MESSAGE_BYTES,
+                message.len()
+            )));
+        }
+        let digest = match message_type.unwrap_or("RAW") {
+            "RAW" => crypto::digest(signing_algorithm, message)?,
+            "DIGEST" => message.to_vec(),
+            message_type => {
+                return Err(KmsRsError::Validation(format!(
+                    "Unsupported message type {}",
+                    message_type
+                )))
+            }
+        };
+
+        if !key
+            .public_key
+            .verify_digest(signing_algorithm, &digest, signature)?
+        {
+            return Err(KmsRsError::KmsInvalidSignature(String::new()));
+        }
+        Ok(VerifyOutput {
+            key_id: key.key_arn.clone(),
+            signature_valid: true,
+            signing_algorithm: signing_algorithm.to_string(),
+        })
+    }
+
+    /// Drops the cached public key of `key_id`, so the next verification fetches it again.
+    pub fn forget(&self, key_id: &str) {
+        self.cache().remove(key_id);
+    }
+
+    async fn key(&self, key_id: &str) -> Result<Arc<VerificationKey>, KmsRsError> {
+        if let Some(key) = self.cache().get(key_id) {
+            return Ok(key.clone());
+        }
+
+        let response = self.kms.get_public_key(key_id.to_string(), None).await?;
+        let key = Arc::new(VerificationKey {
+            public_key: crypto::PublicKey::from_der(&response.public_key)?,
+            key_arn: response.key_id,
+            key_spec: response.customer_master_key_spec,
+            key_usage: response.key_usage,
+            signing_algorithms: response.signing_algorithms,
+        });
+        self.cache().insert(key_id.to_string(), key.clone());
+        Ok(key)
+    }
+
+    fn cache(&self) -> MutexGuard<'_, HashMap<String, Arc<VerificationKey>>> {
+        lock(&self.keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CreateKeyOptions, InMemoryKms};
+    use bytes::Bytes;
+
+    async fn signing_key(kms: &KmsRs, key_spec: &str) -> String {
+        kms.create_key(
+            CreateKeyOptions::new()
+                .key_usage("SIGN_VERIFY")
+                .customer_master_key_spec(key_spec),
+        )
+        .await
+        .unwrap()
+        .key_id
+    }
+
+    #[tokio::test]
+    async fn test_matches_kms_verify() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let verifier = LocalVerifier::new(&kms);
+        for key_spec in &[
+            "RSA_2048",
+            "ECC_NIST_P256",
+            "ECC_NIST_P384",
+            "ECC_NIST_P521",
+            "ECC_SECG_P256K1",
+        ] {
+            let key_id = signing_key(&kms, key_spec).await;
+            for algorithm in crypto::signing_algorithms(key_spec) {
+                let message = Bytes::from_static(b"release v1.0");
+                let signature = kms
+                    .sign(
+                        key_id.clone(),
+                        message.clone(),
+                        None,
+                        algorithm.clone(),
+                        None,
+                    )
+                    .await
+                    .unwrap()
+                    .signature;
+
+                let remote = kms
+                    .verify(
+                        key_id.clone(),
+                        message.clone(),
+                        None,
+                        signature.clone(),
+                        algorithm.clone(),
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                let local = verifier
+                    .verify(&key_id, &message, None, &signature, &algorithm)
+                    .await
+                    .unwrap();
+                assert_eq!(local, remote);
+
+                let digest = crypto::digest(&algorithm, &message).unwrap();
+                verifier
+                    .verify(&key_id, &digest, Some("DIGEST"), &signature, &algorithm)
+                    .await
+                    .unwrap();
+                match verifier
+                    .verify(&key_id, b"tampered", None, &signature, &algorithm)
+                    .await
+                {
+                    Err(KmsRsError::KmsInvalidSignature(_)) => (),
+                    other => panic!("Expected a KmsInvalidSignature error, got {:?}", other),
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unsupported_algorithm() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_id = signing_key(&kms, "ECC_NIST_P256").await;
+        let verifier = LocalVerifier::new(&kms);
+        match verifier
+            .verify(
+                &key_id,
+                b"message",
+                None,
+                b"signature",
+                "RSASSA_PSS_SHA_256",
+            )
+            .await
+        {
+            Err(KmsRsError::InvalidKeyUsage(_)) => (),
+            other => panic!("Expected an InvalidKeyUsage error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caches_public_keys() {
+        let kms = KmsRs::from_backend(InMemoryKms::new());
+        let key_id = signing_key(&kms, "ECC_NIST_P256").await;
+        let signature = kms
+            .sign(
+                key_id.clone(),
+                Bytes::from_static(b"message"),
+                None,
+                "ECDSA_SHA_256".to_string(),
+                None,
+            )
+            .await
+            .unwrap()
+            .signature;
+        let verifier = LocalVerifier::new(&kms);
+        verifier
+            .verify(&key_id, b"message", None, &signature, "ECDSA_SHA_256")
+            .await
+            .unwrap();
+
+        // GetPublicKey fails for a disabled CMK, so only the cached public key can verify now.
+        kms.disable_key(&key_id).await.unwrap();
+        verifier
+            .verify(&key_id, b"message", None, &signature, "ECDSA_SHA_256")
+            .await
+            .unwrap();
+        verifier.forget(&key_id);
+        match verifier
+            .verify(&key_id, b"message", None, &signature, "ECDSA_SHA_256")
+            .await
+        {
+            Err(KmsRsError::Disabled(_)) => (),
+            other => panic!("Expected a Disabled error, got {:?}", other),
+        }
+    }
+}